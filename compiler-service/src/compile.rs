@@ -5,7 +5,7 @@ use isymtope_build::*;
 use isymtope_generate::*;
 
 fn parse_template(src: &str) -> IsymtopeGenerateResult<DocumentProvider> {
-    let template = Rc::new(parser::parse_str(&src)?);
+    let template = Rc::new(parser::parse_source(&src, Some("app.ism"))?);
 
     // Create document provider
     let mut ctx: DefaultProcessingContext<ProcessedExpression> =
@@ -1,3 +1,5 @@
+use error::*;
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Loc<T, P> {
     pub inner: T,
@@ -30,3 +32,31 @@ where
         write!(f, "<{:?}@{:?}>", self.inner, self.pos)
     }
 }
+
+/// Location of a node within its template source, kept for diagnostics reported
+/// after parsing. Spans do not take part in comparing or hashing nodes.
+#[derive(Debug, Clone, Default)]
+pub struct NodeSpan(Option<SourceSpan>);
+
+impl NodeSpan {
+    /// Span from `start` to `end`, resolved against the template source it was parsed from.
+    pub fn from_source(start: usize, end: usize, src: &str, file_name: Option<&str>) -> Self {
+        NodeSpan(Some(SourceSpan::new(start, end).resolved(src, file_name)))
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.0.as_ref()
+    }
+}
+
+impl PartialEq for NodeSpan {
+    fn eq(&self, _other: &NodeSpan) -> bool {
+        true
+    }
+}
+
+impl Eq for NodeSpan {}
+
+impl ::std::hash::Hash for NodeSpan {
+    fn hash<H: ::std::hash::Hasher>(&self, _state: &mut H) {}
+}
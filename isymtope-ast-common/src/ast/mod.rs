@@ -4,16 +4,59 @@ pub mod nodes;
 pub use self::loc::*;
 pub use self::nodes::*;
 
+use std::collections::HashSet;
+
 use expressions::*;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Template(Vec<TemplateNode<SourceExpression>>);
 
 impl Template {
+    /// Creates a template, keying its content by the path through the template
+    /// to each node, so that keys do not depend on the order nodes are created in.
     pub fn new(children: Vec<TemplateNode<SourceExpression>>) -> Self {
+        let mut children = children;
+        let mut content_idx = 0;
+
+        for child in children.iter_mut() {
+            match *child {
+                TemplateNode::ComponentDefinition(ref mut c, _) => c.assign_keys(),
+                TemplateNode::RouteDefinition(ref mut r, _) => r.assign_keys(),
+                TemplateNode::Content(ref mut n, _) => {
+                    n.assign_keys(&format!("content/{}", content_idx));
+                    content_idx += 1;
+                }
+                _ => {}
+            };
+        }
+
         Template(children)
     }
 
+    /// Moves the components defined in this template into the namespace `ns`,
+    /// so that `todo` defined in `components/todo.ism` is called as `todo::todo`.
+    pub fn qualify_components(&mut self, ns: &str) {
+        let names: HashSet<String> = self.0.iter()
+            .filter_map(|n| match *n {
+                TemplateNode::ComponentDefinition(ref c, _) => Some(c.name().to_owned()),
+                _ => None,
+            })
+            .collect();
+
+        for child in self.0.iter_mut() {
+            match *child {
+                TemplateNode::ComponentDefinition(ref mut c, _) => c.qualify_components(ns, &names),
+                TemplateNode::RouteDefinition(ref mut r, _) => r.qualify_components(ns, &names),
+                TemplateNode::Content(ref mut n, _) => n.qualify_components(ns, &names),
+                _ => {}
+            };
+        }
+    }
+
+    pub fn into_children(self) -> Vec<TemplateNode<SourceExpression>> {
+        self.0
+    }
+
     pub fn children<'a>(&'a self) -> impl Iterator<Item = &'a TemplateNode<SourceExpression>> {
         self.0.iter()
     }
@@ -2,6 +2,7 @@
 // use itertools::Itertools;
 // use itertools::FoldWhile::{Continue, Done};
 
+use std::collections::HashSet;
 use std::collections::hash_map::{HashMap, Entry};
 
 use error::*;
@@ -9,7 +10,7 @@ use expressions::*;
 use ast::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct ComponentDefinition<T>(String, FormalParams<T>, Option<Vec<ContentNode<T>>>);
+pub struct ComponentDefinition<T>(String, FormalParams<T>, Option<Vec<ContentNode<T>>>, Option<String>);
 
 impl<T: Clone> ComponentDefinition<T> {
     pub fn new(
@@ -17,7 +18,11 @@ impl<T: Clone> ComponentDefinition<T> {
         params: FormalParams<T>,
         children: Option<Vec<ContentNode<T>>>,
     ) -> Self {
-        ComponentDefinition(name, params, children)
+        ComponentDefinition(name, params, children, None)
+    }
+
+    pub fn with_doc(self, doc: Option<String>) -> Self {
+        ComponentDefinition(self.0, self.1, self.2, doc)
     }
 
     pub fn name(&self) -> &str {
@@ -28,10 +33,42 @@ impl<T: Clone> ComponentDefinition<T> {
         self.1.params()
     }
 
+    pub fn formal_params(&self) -> &FormalParams<T> {
+        &self.1
+    }
+
+    pub fn param_types<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a TypeAnnotation)> {
+        self.1.param_types()
+    }
+
     pub fn children<'a>(&'a self) -> Option<impl Iterator<Item = &'a ContentNode<T>>> {
         self.2.as_ref().map(|v| v.iter())
     }
 
+    /// Documentation from `///` comments preceding the definition
+    pub fn doc(&self) -> Option<&str> {
+        self.3.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn assign_keys(&mut self) {
+        let path = format!("component:{}", self.0);
+        if let Some(ref mut children) = self.2 {
+            assign_content_keys(children, &path);
+        };
+    }
+
+    /// Moves this component into the namespace `ns`, qualifying its own name and
+    /// any calls it makes to the other components in `names`.
+    pub fn qualify_components(&mut self, ns: &str, names: &HashSet<String>) {
+        if names.contains(&self.0) {
+            self.0 = format!("{}::{}", ns, self.0);
+        };
+
+        if let Some(ref mut children) = self.2 {
+            qualify_content_components(children, ns, names);
+        };
+    }
+
     pub fn gather_value_binding_mappings(&self) -> DocumentProcessingResult<HashMap<String, String>> {
         let mut mappings: HashMap<String, String> = Default::default();
         let mut visitor = DefaultContentNodeVisitor::default();
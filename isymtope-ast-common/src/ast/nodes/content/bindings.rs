@@ -49,6 +49,10 @@ impl<T> ElementEventBinding<T> {
         self.0.as_ref().map(|s| s.as_str())
     }
 
+    pub fn params<'a>(&'a self) -> Option<impl Iterator<Item = &'a str>> {
+        self.1.params()
+    }
+
     pub fn actions<'a>(&'a self) -> Option<impl Iterator<Item = &'a ActionOp<T>>> {
         self.2.as_ref().map(|v| v.iter())
     }
@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::collections::HashSet;
 
 use util::*;
 use error::*;
@@ -61,6 +62,27 @@ impl<T> ElementNode<T> {
     pub fn parent_tag(&self) -> Option<&str> {
         self.2.as_ref().map(|s| s.as_str())
     }
+
+    /// Keys this element, and its children, by their path through the template.
+    pub fn assign_keys(&mut self, path: &str) {
+        self.1 = element_key_for_path(path);
+
+        if let Some(box ref mut children) = self.5 {
+            assign_content_keys(children, path);
+        };
+    }
+
+    /// Qualifies this element, if it calls one of the components in `names`,
+    /// and its children with the namespace `ns`.
+    pub fn qualify_components(&mut self, ns: &str, names: &HashSet<String>) {
+        if names.contains(&self.0) {
+            self.0 = format!("{}::{}", ns, self.0);
+        };
+
+        if let Some(box ref mut children) = self.5 {
+            qualify_content_components(children, ns, names);
+        };
+    }
 }
 
 impl TryProcessFrom<ElementNode<SourceExpression>> for ElementNode<ProcessedExpression> {
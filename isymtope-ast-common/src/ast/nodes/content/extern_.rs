@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::collections::HashSet;
 
 use error::*;
 use traits::*;
@@ -20,6 +21,20 @@ impl<T: Clone> ExternNode<T> {
     }
 }
 
+impl<T> ExternNode<T> {
+    pub fn assign_keys(&mut self, path: &str) {
+        if let Some(box ref mut children) = self.0 {
+            assign_content_keys(children, path);
+        };
+    }
+
+    pub fn qualify_components(&mut self, ns: &str, names: &HashSet<String>) {
+        if let Some(box ref mut children) = self.0 {
+            qualify_content_components(children, ns, names);
+        };
+    }
+}
+
 impl TryProcessFrom<ExternNode<SourceExpression>> for ExternNode<ProcessedExpression> {
     fn try_process_from(
         src: &ExternNode<SourceExpression>,
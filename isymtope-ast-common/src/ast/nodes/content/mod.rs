@@ -1,5 +1,7 @@
 use std::marker::PhantomData;
+use std::collections::HashSet;
 
+use util::*;
 use error::*;
 use traits::*;
 use expressions::*;
@@ -21,16 +23,126 @@ pub enum ContentNode<T> {
     Element(ElementNode<T>, PhantomData<T>),
     Extern(ExternNode<T>, PhantomData<T>),
     // Value(ExpressionValue<T>, String, PhantomData<T>),
+    /// Loop over a collection, with optional item and index aliases and `key = expr` clause
     ForNode(
         Option<String>,
+        Option<String>,
+        Box<ExpressionValue<T>>,
+        Option<Box<ExpressionValue<T>>>,
+        Option<Box<Vec<ContentNode<T>>>>,
+        PhantomData<T>,
+    ),
+    /// Conditional content; an `else if` is an else branch holding a single `IfNode`
+    IfNode(
         Box<ExpressionValue<T>>,
+        Box<Vec<ContentNode<T>>>,
         Option<Box<Vec<ContentNode<T>>>>,
         PhantomData<T>,
     ),
     ExpressionValue(Box<ExpressionValue<T>>, String, PhantomData<T>),
+    /// Trusted HTML from `raw(expr)`, which is written without escaping
+    RawValue(Box<ExpressionValue<T>>, String, PhantomData<T>),
+    /// Named slot: content passed to a component (`slot header { .. }`), or where
+    /// a component renders it (`slot header;`)
+    Slot(String, Option<Box<Vec<ContentNode<T>>>>, PhantomData<T>),
     Primitive(Primitive, PhantomData<T>),
 }
 
+/// Keys each node in `nodes` by its position under `path`.
+pub fn assign_content_keys<T>(nodes: &mut Vec<ContentNode<T>>, path: &str) {
+    for (idx, node) in nodes.iter_mut().enumerate() {
+        node.assign_keys(&format!("{}/{}", path, idx));
+    }
+}
+
+/// Qualifies calls to the components in `names` with the namespace `ns`.
+pub fn qualify_content_components<T>(nodes: &mut Vec<ContentNode<T>>, ns: &str, names: &HashSet<String>) {
+    for node in nodes.iter_mut() {
+        node.qualify_components(ns, names);
+    }
+}
+
+impl<T> ContentNode<T> {
+    /// Qualifies calls to the components in `names`, within this node and its
+    /// descendants, with the namespace `ns`.
+    pub fn qualify_components(&mut self, ns: &str, names: &HashSet<String>) {
+        match *self {
+            ContentNode::Element(ref mut e, _) => e.qualify_components(ns, names),
+            ContentNode::Extern(ref mut e, _) => e.qualify_components(ns, names),
+
+            ContentNode::ForNode(_, _, _, _, Some(box ref mut children), _) |
+            ContentNode::Slot(_, Some(box ref mut children), _) => {
+                qualify_content_components(children, ns, names);
+            }
+
+            ContentNode::IfNode(_, box ref mut children, ref mut else_children, _) => {
+                qualify_content_components(children, ns, names);
+                if let Some(box ref mut else_children) = *else_children {
+                    qualify_content_components(else_children, ns, names);
+                };
+            }
+
+            _ => {}
+        };
+    }
+
+    /// Keys this node, and its descendants, by their path through the template.
+    pub fn assign_keys(&mut self, path: &str) {
+        match *self {
+            ContentNode::Element(ref mut e, _) => e.assign_keys(path),
+            ContentNode::Extern(ref mut e, _) => e.assign_keys(path),
+
+            ContentNode::ForNode(_, _, _, _, Some(box ref mut children), _) => {
+                assign_content_keys(children, path);
+            }
+
+            ContentNode::IfNode(_, box ref mut children, ref mut else_children, _) => {
+                assign_content_keys(children, path);
+                if let Some(box ref mut else_children) = *else_children {
+                    assign_content_keys(else_children, &format!("{}/else", path));
+                };
+            }
+
+            ContentNode::Slot(_, Some(box ref mut children), _) => {
+                assign_content_keys(children, path);
+            }
+
+            ContentNode::ExpressionValue(_, ref mut key, _) | ContentNode::RawValue(_, ref mut key, _) => {
+                *key = element_key_for_path(path);
+            }
+
+            _ => {}
+        };
+    }
+}
+
+/// Binds the item and index aliases of a `for` loop within the current scope, to
+/// the item of the loop with the given key if any, otherwise to the current item.
+pub fn bind_loop_aliases(
+    ctx: &mut ProcessingContext,
+    loop_key: Option<&str>,
+    item_alias: Option<&String>,
+    index_alias: Option<&String>,
+) -> DocumentProcessingResult<()> {
+    if let Some(item_alias) = item_alias {
+        let binding = match loop_key {
+            Some(loop_key) => CommonBindings::LoopItem(loop_key.to_owned(), Default::default()),
+            None => CommonBindings::CurrentItem(Default::default()),
+        };
+        ctx.bind_ident(item_alias.to_owned(), binding)?;
+    };
+
+    if let Some(index_alias) = index_alias {
+        let binding = match loop_key {
+            Some(loop_key) => CommonBindings::LoopItemIndex(loop_key.to_owned()),
+            None => CommonBindings::CurrentItemIndex,
+        };
+        ctx.bind_ident(index_alias.to_owned(), binding)?;
+    };
+
+    Ok(())
+}
+
 impl TryProcessFrom<ContentNode<SourceExpression>> for ContentNode<ProcessedExpression> {
     fn try_process_from(
         src: &ContentNode<SourceExpression>,
@@ -46,24 +158,59 @@ impl TryProcessFrom<ContentNode<SourceExpression>> for ContentNode<ProcessedExpr
                 Default::default(),
             )),
 
-            ContentNode::ForNode(ref s, ref e, Some(ref n), _) => Ok(ContentNode::ForNode(
-                s.to_owned(),
-                Box::new(TryProcessFrom::try_process_from(e, ctx)?),
-                Some(Box::new(TryProcessFrom::try_process_from(n, ctx)?)),
+            ContentNode::ForNode(ref s, ref i, ref e, ref k, ref n, _) => {
+                let coll = TryProcessFrom::try_process_from(e, ctx)?;
+
+                ctx.push_child_scope();
+                bind_loop_aliases(ctx, None, s.as_ref(), i.as_ref())?;
+                let key = match *k {
+                    Some(ref k) => Some(Box::new(TryProcessFrom::try_process_from(k, ctx)?)),
+                    None => None,
+                };
+                let children = match *n {
+                    Some(ref n) => Some(Box::new(TryProcessFrom::try_process_from(n, ctx)?)),
+                    None => None,
+                };
+                ctx.pop_scope();
+
+                Ok(ContentNode::ForNode(
+                    s.to_owned(),
+                    i.to_owned(),
+                    Box::new(coll),
+                    key,
+                    children,
+                    Default::default(),
+                ))
+            }
+
+            ContentNode::IfNode(ref cond, ref children, ref else_children, _) => Ok(ContentNode::IfNode(
+                Box::new(TryProcessFrom::try_process_from(cond, ctx)?),
+                Box::new(TryProcessFrom::try_process_from(children, ctx)?),
+                match *else_children {
+                    Some(ref n) => Some(Box::new(TryProcessFrom::try_process_from(n, ctx)?)),
+                    None => None,
+                },
                 Default::default(),
             )),
-            ContentNode::ForNode(ref s, ref e, _, _) => Ok(ContentNode::ForNode(
-                s.to_owned(),
+
+            ContentNode::ExpressionValue(ref e, ref s, _) => Ok(ContentNode::ExpressionValue(
                 Box::new(TryProcessFrom::try_process_from(e, ctx)?),
-                None,
+                s.to_owned(),
                 Default::default(),
             )),
-
-            ContentNode::ExpressionValue(ref e, ref s, _) => Ok(ContentNode::ExpressionValue(
+            ContentNode::RawValue(ref e, ref s, _) => Ok(ContentNode::RawValue(
                 Box::new(TryProcessFrom::try_process_from(e, ctx)?),
                 s.to_owned(),
                 Default::default(),
             )),
+            ContentNode::Slot(ref name, ref n, _) => Ok(ContentNode::Slot(
+                name.to_owned(),
+                match *n {
+                    Some(ref n) => Some(Box::new(TryProcessFrom::try_process_from(n, ctx)?)),
+                    None => None,
+                },
+                Default::default(),
+            )),
 
             ContentNode::Primitive(ref p, _) => {
                 Ok(ContentNode::Primitive(p.to_owned(), Default::default()))
@@ -87,16 +234,28 @@ impl TryEvalFrom<ContentNode<ProcessedExpression>> for ContentNode<OutputExpress
                 Default::default(),
             )),
 
-            ContentNode::ForNode(ref s, ref e, Some(ref n), _) => Ok(ContentNode::ForNode(
+            ContentNode::ForNode(ref s, ref i, ref e, ref k, ref n, _) => Ok(ContentNode::ForNode(
                 s.to_owned(),
+                i.to_owned(),
                 Box::new(TryEvalFrom::try_eval_from(e, ctx)?),
-                Some(Box::new(TryEvalFrom::try_eval_from(n, ctx)?)),
+                match *k {
+                    Some(ref k) => Some(Box::new(TryEvalFrom::try_eval_from(k, ctx)?)),
+                    None => None,
+                },
+                match *n {
+                    Some(ref n) => Some(Box::new(TryEvalFrom::try_eval_from(n, ctx)?)),
+                    None => None,
+                },
                 Default::default(),
             )),
-            ContentNode::ForNode(ref s, ref e, _, _) => Ok(ContentNode::ForNode(
-                s.to_owned(),
-                Box::new(TryEvalFrom::try_eval_from(e, ctx)?),
-                None,
+
+            ContentNode::IfNode(ref cond, ref children, ref else_children, _) => Ok(ContentNode::IfNode(
+                Box::new(TryEvalFrom::try_eval_from(cond, ctx)?),
+                Box::new(TryEvalFrom::try_eval_from(children, ctx)?),
+                match *else_children {
+                    Some(ref n) => Some(Box::new(TryEvalFrom::try_eval_from(n, ctx)?)),
+                    None => None,
+                },
                 Default::default(),
             )),
 
@@ -105,6 +264,19 @@ impl TryEvalFrom<ContentNode<ProcessedExpression>> for ContentNode<OutputExpress
                 s.to_owned(),
                 Default::default(),
             )),
+            ContentNode::RawValue(ref e, ref s, _) => Ok(ContentNode::RawValue(
+                Box::new(TryEvalFrom::try_eval_from(e, ctx)?),
+                s.to_owned(),
+                Default::default(),
+            )),
+            ContentNode::Slot(ref name, ref n, _) => Ok(ContentNode::Slot(
+                name.to_owned(),
+                match *n {
+                    Some(ref n) => Some(Box::new(TryEvalFrom::try_eval_from(n, ctx)?)),
+                    None => None,
+                },
+                Default::default(),
+            )),
 
             ContentNode::Primitive(ref p, _) => {
                 Ok(ContentNode::Primitive(p.to_owned(), Default::default()))
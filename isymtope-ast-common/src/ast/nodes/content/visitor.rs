@@ -10,6 +10,18 @@ pub trait ContentNodeVisitor<T> {
 #[derive(Debug, Default)]
 pub struct DefaultContentNodeVisitor;
 
+fn branch_children<'a, T>(n: &'a ContentNode<T>) -> Vec<&'a ContentNode<T>> {
+    match *n {
+        ContentNode::IfNode(_, box ref children, ref else_children, _) => children
+            .iter()
+            .chain(else_children.iter().flat_map(|v| v.iter()))
+            .collect(),
+        ContentNode::ForNode(_, _, _, _, Some(box ref children), _) => children.iter().collect(),
+        ContentNode::Slot(_, Some(box ref children), _) => children.iter().collect(),
+        _ => Default::default(),
+    }
+}
+
 impl<T> ContentNodeVisitor<T> for DefaultContentNodeVisitor {
     fn visit_nodes<F: FnMut(&ContentNode<T>) -> DocumentProcessingResult<()>>(&mut self, n: &ContentNode<T>, f: &mut F) -> DocumentProcessingResult<()> {
         // Visit node first
@@ -24,6 +36,11 @@ impl<T> ContentNodeVisitor<T> for DefaultContentNodeVisitor {
             }
         };
 
+        // Visit all branches of conditionals and loop bodies
+        for child in branch_children(n) {
+            self.visit_nodes(child, f)?;
+        }
+
         Ok(())
     }
 
@@ -40,6 +57,10 @@ impl<T> ContentNodeVisitor<T> for DefaultContentNodeVisitor {
             };
         };
 
+        for child in branch_children(n) {
+            self.visit_elements(child, f)?;
+        }
+
         Ok(())
     }
 
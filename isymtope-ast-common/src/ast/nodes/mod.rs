@@ -6,6 +6,7 @@ pub mod extern_component;
 pub mod query;
 pub mod route;
 pub mod store;
+pub mod type_def;
 
 pub use self::action::*;
 pub use self::template::*;
@@ -15,3 +16,4 @@ pub use self::extern_component::*;
 pub use self::query::*;
 pub use self::route::*;
 pub use self::store::*;
+pub use self::type_def::*;
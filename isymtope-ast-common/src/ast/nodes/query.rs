@@ -7,11 +7,32 @@ use objects::*;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct QueryDefinition<T>(String, FormalParams<T>, Vec<QueryComponent<T>>);
+pub struct QueryDefinition<T>(String, FormalParams<T>, Vec<QueryComponent<T>>, Option<String>);
 
 impl<T> QueryDefinition<T> {
     pub fn new(name: String, params: FormalParams<T>, children: Vec<QueryComponent<T>>) -> Self {
-        QueryDefinition(name, params, children)
+        QueryDefinition(name, params, children, None)
+    }
+
+    pub fn with_doc(self, doc: Option<String>) -> Self {
+        QueryDefinition(self.0, self.1, self.2, doc)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn params<'a>(&'a self) -> Option<impl Iterator<Item = &'a str>> {
+        self.1.params()
+    }
+
+    pub fn components<'a>(&'a self) -> impl Iterator<Item = &'a QueryComponent<T>> {
+        self.2.iter()
+    }
+
+    /// Documentation from `///` comments preceding the definition
+    pub fn doc(&self) -> Option<&str> {
+        self.3.as_ref().map(|s| s.as_str())
     }
 }
 
@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::collections::HashSet;
 
 use error::*;
 use traits::*;
@@ -8,20 +9,62 @@ use ast::*;
 use util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct RouteDefinition<T>(String, FormalParams<T>, RouteAction<T>, PhantomData<T>);
+pub struct RouteDefinition<T>(String, FormalParams<T>, RouteAction<T>, Option<String>, PhantomData<T>);
 
 impl<T> RouteDefinition<T> {
     pub fn new(pattern: String, params: FormalParams<T>, action: RouteAction<T>) -> Self {
-        RouteDefinition(pattern, params, action, Default::default())
+        RouteDefinition(pattern, params, action, None, Default::default())
+    }
+
+    pub fn with_doc(self, doc: Option<String>) -> Self {
+        RouteDefinition(self.0, self.1, self.2, doc, Default::default())
     }
 
     pub fn pattern(&self) -> &str {
         &self.0
     }
 
+    pub fn params<'a>(&'a self) -> Option<impl Iterator<Item = &'a str>> {
+        self.1.params()
+    }
+
+    pub fn action(&self) -> &RouteAction<T> {
+        &self.2
+    }
+
+    /// Documentation from `///` comments preceding the definition
+    pub fn doc(&self) -> Option<&str> {
+        self.3.as_ref().map(|s| s.as_str())
+    }
+
+    /// Binds each formal param to the pattern segment of the same name, in the current scope
+    pub fn bind_params(&self, ctx: &mut ProcessingContext) -> DocumentProcessingResult<()> {
+        if let Some(params) = self.params() {
+            for param in params {
+                let binding = CommonBindings::NamedRouteParam(param.to_owned(), Default::default());
+                ctx.bind_ident(param.to_owned(), binding)?;
+            }
+        };
+
+        Ok(())
+    }
+
     pub fn function_key(&self) -> &str {
         "key"
     }
+
+    pub fn assign_keys(&mut self) {
+        let path = format!("route:{}", self.0);
+        if let RouteAction::Content(Some(ref mut children), _) = self.2 {
+            assign_content_keys(children, &path);
+        };
+    }
+
+    pub fn qualify_components(&mut self, ns: &str, names: &HashSet<String>) {
+        if let RouteAction::Content(Some(ref mut children), _) = self.2 {
+            qualify_content_components(children, ns, names);
+        };
+    }
 }
 
 impl<I, O> TryProcessFrom<RouteDefinition<I>> for Route<O>
@@ -38,12 +81,28 @@ where
         eprintln!("TryProcess RouteDefinition<I>: Params: {:?}", src.1);
         eprintln!("TryProcess RouteDefinition<I>: Action: {:?}", src.2);
 
+        let route_pattern = RoutePattern::parse(&src.0)?;
         let params: FormalParams<O> = TryProcessFrom::try_process_from(&src.1, ctx)?;
+
+        // Each formal param is bound to the pattern segment of the same name
+        if let Some(param) = params
+            .params()
+            .and_then(|mut v| v.find(|p| !route_pattern.param_names().any(|name| name == *p)))
+        {
+            return Err(try_process_from_err!(format!(
+                "Route [{}] has no parameter named [{}]",
+                &src.0, param
+            )));
+        };
+
+        ctx.push_child_scope();
+        src.bind_params(ctx)?;
         let action: RouteActionValue<O> = TryProcessFrom::try_process_from(&src.2, ctx)?;
+        ctx.pop_scope();
         eprintln!("TryProcess RouteDefinition<O>: Params(b): {:?}", params);
         eprintln!("TryProcess RouteDefinition<O>: Action(b): {:?}", action);
 
-        Ok(Route::new(src.0.to_owned(), params, action))
+        Ok(Route::new(src.0.to_owned(), route_pattern, params, action))
     }
 }
 
@@ -81,6 +140,7 @@ where
         );
 
         Ok(match *src {
+            // Content is processed along with the rest of the document's content, see `Document`
             RouteAction::Content(..) => {
                 let block_id = allocate_element_key();
                 let ops: Vec<ElementOp<O>> = Default::default();
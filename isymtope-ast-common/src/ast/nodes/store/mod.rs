@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 use expressions::*;
 use objects::*;
+use ast::*;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -57,7 +58,7 @@ impl ExternReducerNode {
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum StoreCommonNode<T> {
-    LetNode(String, Option<ExpressionValue<T>>, PhantomData<T>),
+    LetNode(String, Option<ExpressionValue<T>>, Option<TypeAnnotation>, PhantomData<T>),
     ApiNode(String, Option<Vec<StoreApiChildNode<T>>>, PhantomData<T>),
     ExternReducerModuleNode(ExternReducerModuleNode, PhantomData<T>),
     ExternReducerNode(ExternReducerNode, PhantomData<T>),
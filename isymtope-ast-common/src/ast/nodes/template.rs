@@ -5,10 +5,13 @@ use super::*;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TemplateNode<T> {
     UseStmt(String),
+    /// Template file to load and merge, relative to the app root
+    UseFile(String),
     ComponentDefinition(ComponentDefinition<T>, PhantomData<T>),
     ExternComponentDefinition(ExternComponentDefinition<T>, PhantomData<T>),
     RouteDefinition(RouteDefinition<T>, PhantomData<T>),
     StoreDefinition(StoreDefinition<T>, PhantomData<T>),
     QueryDefinition(QueryDefinition<T>, PhantomData<T>),
+    TypeDefinition(TypeDefinition, PhantomData<T>),
     Content(ContentNode<T>, PhantomData<T>),
 }
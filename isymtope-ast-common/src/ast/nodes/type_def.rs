@@ -0,0 +1,201 @@
+use std::fmt;
+
+use linked_hash_map::LinkedHashMap;
+
+use error::*;
+use ast::*;
+use types::*;
+
+/// Type named in an annotation (`int`, `Todo`, `[Todo]`, `map [int]` or `{text: string}`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeNode {
+    Named(String),
+    Array(Box<TypeNode>),
+    Map(Box<TypeNode>),
+    Object(Vec<(String, TypeNode)>),
+}
+
+impl TypeNode {
+    /// Resolves this type against the declared record types, returning the name
+    /// of the first type which is not declared.
+    pub fn resolve(&self, types: &LinkedHashMap<String, TypeDefinition>) -> Result<VarType, String> {
+        self.resolve_within(types, &mut Vec::new())
+    }
+
+    fn resolve_within<'a>(
+        &'a self,
+        types: &'a LinkedHashMap<String, TypeDefinition>,
+        resolving: &mut Vec<&'a str>,
+    ) -> Result<VarType, String> {
+        match *self {
+            TypeNode::Named(ref name) => match name.as_str() {
+                "any" => Ok(VarType::Unknown),
+                "null" => Ok(VarType::Null),
+                "bool" => Ok(VarType::Bool),
+                "int" => Ok(VarType::Int),
+                "float" => Ok(VarType::Float),
+                "string" => Ok(VarType::String),
+
+                // Recursive types are not expanded further
+                name if resolving.contains(&name) => Ok(VarType::Unknown),
+
+                name => {
+                    let def = types.get(name).ok_or_else(|| name.to_owned())?;
+                    resolving.push(def.name());
+                    let ty = resolve_fields(def.fields(), types, resolving);
+                    resolving.pop();
+                    ty
+                }
+            },
+
+            TypeNode::Array(box ref ty) => Ok(VarType::Array(Box::new(ty.resolve_within(types, resolving)?))),
+            TypeNode::Map(box ref ty) => Ok(VarType::Map(Box::new(ty.resolve_within(types, resolving)?))),
+            TypeNode::Object(ref fields) => resolve_fields(fields.iter(), types, resolving),
+        }
+    }
+
+    /// Type in the notation used by JSDoc
+    pub fn js_doc(&self) -> String {
+        match *self {
+            TypeNode::Named(ref name) => match name.as_str() {
+                "any" => "*".to_owned(),
+                "bool" => "boolean".to_owned(),
+                "int" | "float" => "number".to_owned(),
+                name => name.to_owned(),
+            },
+            TypeNode::Array(box ref ty) => format!("Array<{}>", ty.js_doc()),
+            TypeNode::Map(box ref ty) => format!("Map<*, {}>", ty.js_doc()),
+            TypeNode::Object(ref fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|&(ref name, ref ty)| format!("{}: {}", name, ty.js_doc()))
+                    .collect();
+                format!("{{{}}}", fields.join(", "))
+            }
+        }
+    }
+}
+
+fn resolve_fields<'a, I: IntoIterator<Item = &'a (String, TypeNode)>>(
+    fields: I,
+    types: &'a LinkedHashMap<String, TypeDefinition>,
+    resolving: &mut Vec<&'a str>,
+) -> Result<VarType, String> {
+    let mut resolved = Vec::new();
+    for &(ref name, ref ty) in fields {
+        resolved.push((name.to_owned(), ty.resolve_within(types, resolving)?));
+    }
+
+    Ok(VarType::Object(resolved))
+}
+
+impl fmt::Display for TypeNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypeNode::Named(ref name) => write!(f, "{}", name),
+            TypeNode::Array(ref ty) => write!(f, "[{}]", ty),
+            TypeNode::Map(ref ty) => write!(f, "map [{}]", ty),
+            TypeNode::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (idx, &(ref name, ref ty)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, ty)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Type annotation on a store value or param (`let todos: [Todo]`, `action add(entry: Todo)`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeAnnotation(Box<TypeNode>, NodeSpan);
+
+impl TypeAnnotation {
+    pub fn new(ty: TypeNode, span: NodeSpan) -> Self {
+        TypeAnnotation(Box::new(ty), span)
+    }
+
+    pub fn ty(&self) -> &TypeNode {
+        &self.0
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.1.span()
+    }
+
+    pub fn resolve(&self, types: &LinkedHashMap<String, TypeDefinition>) -> DocumentProcessingResult<VarType> {
+        self.0.resolve(types).map_err(|name| match self.span() {
+            Some(span) => try_process_from_err!(format!("{}: Unknown type [{}]", span, name)),
+            None => try_process_from_err!(format!("Unknown type [{}]", name)),
+        })
+    }
+}
+
+/// Record type declaration (`type Todo { text: string, complete: bool }`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeDefinition(String, Vec<(String, TypeNode)>, Option<String>, NodeSpan);
+
+impl TypeDefinition {
+    pub fn new(name: String, fields: Vec<(String, TypeNode)>) -> Self {
+        TypeDefinition(name, fields, None, Default::default())
+    }
+
+    pub fn with_doc(self, doc: Option<String>) -> Self {
+        TypeDefinition(self.0, self.1, doc, self.3)
+    }
+
+    pub fn with_span(self, span: NodeSpan) -> Self {
+        TypeDefinition(self.0, self.1, self.2, span)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn fields<'a>(&'a self) -> impl Iterator<Item = &'a (String, TypeNode)> {
+        self.1.iter()
+    }
+
+    /// Documentation from `///` comments preceding the declaration
+    pub fn doc(&self) -> Option<&str> {
+        self.2.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.3.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_node_resolve() {
+        let named = |s: &str| TypeNode::Named(s.to_owned());
+        let todo = TypeDefinition::new(
+            "Todo".to_owned(),
+            vec![
+                ("text".to_owned(), named("string")),
+                ("children".to_owned(), TypeNode::Array(Box::new(named("Todo")))),
+            ],
+        );
+        let mut types: LinkedHashMap<String, TypeDefinition> = Default::default();
+        types.insert("Todo".to_owned(), todo);
+
+        let todos = TypeNode::Array(Box::new(named("Todo")));
+        assert_eq!(
+            todos.resolve(&types),
+            Ok(VarType::Array(Box::new(VarType::Object(vec![
+                ("text".to_owned(), VarType::String),
+                ("children".to_owned(), VarType::Array(Box::new(VarType::Unknown))),
+            ]))))
+        );
+        assert_eq!(named("Item").resolve(&types), Err("Item".to_owned()));
+        assert_eq!(todos.js_doc(), "Array<Todo>");
+        assert_eq!(format!("{}", TypeNode::Map(Box::new(named("int")))), "map [int]");
+    }
+}
@@ -1,37 +1,291 @@
 use std::error::Error;
-use std::fmt;
+use std::fmt::{self, Write};
 
-#[derive(Debug)]
+/// Location of a parse error within a template source, as a byte span plus
+/// the (1-based) line and column of its start once resolved against the source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceSpan {
+    file_name: Option<String>,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    source_line: Option<String>,
+}
+
+impl SourceSpan {
+    pub fn new(start: usize, end: usize) -> Self {
+        SourceSpan {
+            start: start,
+            end: end,
+            ..Default::default()
+        }
+    }
+
+    pub fn at(pos: usize) -> Self {
+        SourceSpan::new(pos, pos)
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn source_line(&self) -> Option<&str> {
+        self.source_line.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.line > 0
+    }
+
+    /// Computes line, column and the text of the line containing the start of this span.
+    pub fn resolve(&mut self, src: &str, file_name: Option<&str>) {
+        let start = ::std::cmp::min(self.start, src.len());
+        let line_start = src[..start].rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line_end = src[start..]
+            .find('\n')
+            .map(|n| start + n)
+            .unwrap_or_else(|| src.len());
+
+        self.line = src[..line_start].matches('\n').count() + 1;
+        self.column = src[line_start..start].chars().count() + 1;
+        self.source_line = Some(src[line_start..line_end].trim_right_matches('\r').to_owned());
+        self.file_name = file_name.map(|s| s.to_owned());
+    }
+
+    pub fn resolved(mut self, src: &str, file_name: Option<&str>) -> Self {
+        self.resolve(src, file_name);
+        self
+    }
+
+    /// Writes the source line containing this span, with a caret marking the span.
+    pub fn write_snippet(&self, out: &mut String) -> fmt::Result {
+        if let Some(source_line) = self.source_line() {
+            let line_no = format!("{}", self.line());
+            let gutter: String = line_no.chars().map(|_| ' ').collect();
+            let indent: String = source_line
+                .chars()
+                .take(self.column() - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let remaining = (source_line.chars().count() + 1 - self.column()).max(1);
+            let width = self.end().saturating_sub(self.start()).max(1).min(remaining);
+            let carets: String = (0..width).map(|_| '^').collect();
+
+            writeln!(out, "{} |", gutter)?;
+            writeln!(out, "{} | {}", line_no, source_line)?;
+            writeln!(out, "{} | {}{}", gutter, indent, carets)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file_name = self.file_name().unwrap_or("<template>");
+
+        if self.is_resolved() {
+            write!(f, "{}:{}:{}", file_name, self.line, self.column)
+        } else {
+            write!(f, "{}@{}", file_name, self.start)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum TemplateParseError {
-    UnrecognizedToken(usize),
-    UnexpectedToken(usize),
-    UnterminatedString(usize),
-    InvalidNumber(usize),
-    Other
+    UnrecognizedToken(SourceSpan, String, Vec<String>),
+    UnexpectedEof(SourceSpan, Vec<String>),
+    ExtraToken(SourceSpan, String),
+    UnexpectedToken(SourceSpan),
+    UnterminatedString(SourceSpan),
+    UnterminatedComment(SourceSpan),
+    InvalidEscape(SourceSpan),
+    InvalidNumber(SourceSpan),
+    UnterminatedInterpolation(SourceSpan),
+    InvalidInterpolation(SourceSpan),
+    Multiple(Vec<TemplateParseError>),
+}
+
+impl TemplateParseError {
+    pub fn span(&self) -> &SourceSpan {
+        match *self {
+            TemplateParseError::UnrecognizedToken(ref span, ..)
+            | TemplateParseError::UnexpectedEof(ref span, _)
+            | TemplateParseError::ExtraToken(ref span, _)
+            | TemplateParseError::UnexpectedToken(ref span)
+            | TemplateParseError::UnterminatedString(ref span)
+            | TemplateParseError::UnterminatedComment(ref span)
+            | TemplateParseError::InvalidEscape(ref span)
+            | TemplateParseError::InvalidNumber(ref span)
+            | TemplateParseError::UnterminatedInterpolation(ref span)
+            | TemplateParseError::InvalidInterpolation(ref span) => span,
+            TemplateParseError::Multiple(ref errors) => errors[0].span(),
+        }
+    }
+
+    /// Individual errors, for errors collected from a single pass over the template.
+    pub fn errors(&self) -> Vec<&TemplateParseError> {
+        match *self {
+            TemplateParseError::Multiple(ref errors) => errors.iter().collect(),
+            _ => vec![self],
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut SourceSpan {
+        match *self {
+            TemplateParseError::UnrecognizedToken(ref mut span, ..)
+            | TemplateParseError::UnexpectedEof(ref mut span, _)
+            | TemplateParseError::ExtraToken(ref mut span, _)
+            | TemplateParseError::UnexpectedToken(ref mut span)
+            | TemplateParseError::UnterminatedString(ref mut span)
+            | TemplateParseError::UnterminatedComment(ref mut span)
+            | TemplateParseError::InvalidEscape(ref mut span)
+            | TemplateParseError::InvalidNumber(ref mut span)
+            | TemplateParseError::UnterminatedInterpolation(ref mut span)
+            | TemplateParseError::InvalidInterpolation(ref mut span) => span,
+            TemplateParseError::Multiple(ref mut errors) => errors[0].span_mut(),
+        }
+    }
+
+    /// Tokens the parser would have accepted at this point, if known.
+    pub fn expected(&self) -> Option<&[String]> {
+        match *self {
+            TemplateParseError::UnrecognizedToken(_, _, ref expected)
+            | TemplateParseError::UnexpectedEof(_, ref expected) if !expected.is_empty() => {
+                Some(expected)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolves the error location against the template source it was produced from.
+    pub fn with_source(mut self, src: &str, file_name: Option<&str>) -> Self {
+        if let TemplateParseError::Multiple(ref mut errors) = self {
+            for err in errors.iter_mut() {
+                err.span_mut().resolve(src, file_name);
+            }
+        } else {
+            self.span_mut().resolve(src, file_name);
+        }
+        self
+    }
+
+    /// Renders this error with the offending source line, a caret marking the
+    /// span and the set of expected tokens.
+    pub fn diagnostic(&self) -> String {
+        let mut out = String::new();
+        self.write_diagnostic(&mut out).ok();
+        out
+    }
+
+    fn write_diagnostic(&self, out: &mut String) -> fmt::Result {
+        if let TemplateParseError::Multiple(ref errors) = *self {
+            for (idx, err) in errors.iter().enumerate() {
+                if idx > 0 {
+                    writeln!(out, "")?;
+                }
+                err.write_diagnostic(out)?;
+            }
+            return Ok(());
+        }
+
+        let span = self.span();
+        writeln!(out, "error: {}", self.message())?;
+        writeln!(out, "  --> {}", span)?;
+        span.write_snippet(out)?;
+
+        if let Some(expected) = self.expected() {
+            writeln!(out, "  = expected one of: {}", expected.join(", "))?;
+        }
+
+        Ok(())
+    }
+
+    fn message(&self) -> String {
+        match *self {
+            TemplateParseError::UnrecognizedToken(_, ref token, _) => format!("unexpected token `{}`", token),
+            TemplateParseError::UnexpectedEof(..) => "unexpected end of template".to_owned(),
+            TemplateParseError::ExtraToken(_, ref token) => format!("extra token `{}`", token),
+            TemplateParseError::UnexpectedToken(_) => "unexpected character".to_owned(),
+            TemplateParseError::UnterminatedString(_) => "unterminated string".to_owned(),
+            TemplateParseError::UnterminatedComment(_) => "unterminated block comment".to_owned(),
+            TemplateParseError::InvalidEscape(_) => "invalid escape sequence in string".to_owned(),
+            TemplateParseError::InvalidNumber(_) => "invalid number".to_owned(),
+            TemplateParseError::UnterminatedInterpolation(_) => {
+                "unterminated interpolation in string (write `\\{` for a literal brace)".to_owned()
+            }
+            TemplateParseError::InvalidInterpolation(_) => {
+                "invalid expression in string interpolation (write `\\{` for a literal brace)".to_owned()
+            }
+            TemplateParseError::Multiple(ref errors) => format!("{} (and {} more errors)", errors[0].message(), errors.len() - 1),
+        }
+    }
 }
 
 impl Error for TemplateParseError {
     fn description(&self) -> &str {
         match *self {
-            TemplateParseError::UnrecognizedToken(_) => "Unrecognized token",
+            TemplateParseError::UnrecognizedToken(..) => "Unrecognized token",
+            TemplateParseError::UnexpectedEof(..) => "Unexpected end of template",
+            TemplateParseError::ExtraToken(..) => "Extra token",
             TemplateParseError::UnexpectedToken(_) => "Unexpected token",
             TemplateParseError::UnterminatedString(_) => "Unterminated string",
+            TemplateParseError::UnterminatedComment(_) => "Unterminated block comment",
+            TemplateParseError::InvalidEscape(_) => "Invalid escape sequence",
             TemplateParseError::InvalidNumber(_) => "Invalid number",
-            TemplateParseError::Other => "Other parsing error"
+            TemplateParseError::UnterminatedInterpolation(_) => "Unterminated string interpolation",
+            TemplateParseError::InvalidInterpolation(_) => "Invalid string interpolation",
+            TemplateParseError::Multiple(_) => "Multiple parsing errors",
         }
     }
 }
 
 impl fmt::Display for TemplateParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            TemplateParseError::UnrecognizedToken(n) => write!(f, "Unrecognized token at pos {0}", n),
-            TemplateParseError::UnexpectedToken(n) => write!(f, "Unexpected token at pos {0}", n),
-            TemplateParseError::UnterminatedString(n) => write!(f, "Unterminated string starting at pos {0}", n),
-            TemplateParseError::InvalidNumber(n) => write!(f, "Invalid number starting at pos {0}", n),
-            TemplateParseError::Other => write!(f, "Other parsing error")
-        }
+        write!(f, "{}: {}", self.span(), self.message())
     }
 }
 
 pub type TemplateParseResult<T> = ::std::result::Result<T, TemplateParseError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_diagnostic() {
+        let src = "store {\n    let a = 1;\n}\n\ndiv { @ }\n";
+        let pos = src.find('@').unwrap();
+        let err = TemplateParseError::UnrecognizedToken(
+            SourceSpan::new(pos, pos + 1),
+            "@".to_owned(),
+            vec!["\"(\"".to_owned(), "\"}\"".to_owned()],
+        ).with_source(src, Some("app.ism"));
+
+        assert_eq!(err.span().line(), 5);
+        assert_eq!(err.span().column(), 7);
+        assert_eq!(format!("{}", err), "app.ism:5:7: unexpected token `@`");
+        assert_eq!(
+            err.diagnostic(),
+            "error: unexpected token `@`\n  --> app.ism:5:7\n  |\n5 | div { @ }\n  |       ^\n  = expected one of: \"(\", \"}\"\n"
+        );
+    }
+}
@@ -37,6 +37,8 @@ pub enum DocumentProcessingError {
     IOError(IOError),
     #[fail(display = "Error formatting template")]
     FormatError(fmt::Error),
+    #[fail(display = "Error importing template: {}", _0)]
+    ImportError(String),
 
     #[cfg(feature = "types")]
     #[fail(display = "Type error")]
@@ -63,6 +65,47 @@ pub enum DocumentProcessingError {
     SessionError(#[cause] SessionError),
 }
 
+impl DocumentProcessingError {
+    pub fn parse_error(&self) -> Option<&TemplateParseError> {
+        match *self {
+            DocumentProcessingError::TemplateParseError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "types")]
+    pub fn type_error(&self) -> Option<&DocumentTypeError> {
+        match *self {
+            DocumentProcessingError::TypeError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Rendered diagnostic, for errors caused by the template rather than the compiler.
+    pub fn diagnostic(&self) -> Option<String> {
+        #[cfg(feature = "types")]
+        {
+            if let Some(err) = self.type_error() {
+                return Some(err.diagnostic());
+            };
+        }
+
+        match *self {
+            DocumentProcessingError::ImportError(..) => Some(format!("error: {}\n", self)),
+            DocumentProcessingError::TryProcessFromError(ref msg, _)
+            | DocumentProcessingError::TryEvalFromError(ref msg, _) => Some(format!("error: {}\n", msg)),
+            _ => self.parse_error().map(|err| err.diagnostic()),
+        }
+    }
+}
+
+#[cfg(feature = "types")]
+impl From<DocumentTypeError> for DocumentProcessingError {
+    fn from(err: DocumentTypeError) -> Self {
+        DocumentProcessingError::TypeError(err)
+    }
+}
+
 impl From<TemplateParseError> for DocumentProcessingError {
     fn from(err: TemplateParseError) -> Self {
         DocumentProcessingError::TemplateParseError(err)
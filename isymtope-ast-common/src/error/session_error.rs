@@ -1,7 +1,19 @@
+#[cfg(feature = "types")]
+use types::*;
+
 #[derive(Debug, Fail)]
 pub enum SessionError {
     #[fail(display = "Error getting session value")]
     ValueGetError,
+    #[fail(display = "Session not found")]
+    NotFound,
+    #[fail(display = "Session has expired")]
+    Expired,
+    #[fail(display = "Session storage error: {}", _0)]
+    StorageError(String),
+    #[cfg(feature = "types")]
+    #[fail(display = "Session value [{}] has type {}, but was set to a value of type {}", _0, _1, _2)]
+    ValueTypeError(String, VarType, VarType),
 }
 
 pub type SessionResult<T> = ::std::result::Result<T, SessionError>;
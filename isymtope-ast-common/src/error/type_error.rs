@@ -1,34 +1,124 @@
-use std::fmt;
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+
+use error::*;
+use types::*;
 
 #[derive(Debug)]
 pub enum DocumentTypeError {
     TypeError(String),
-    // MismatchActionParam(String, String, VarType, VarType)
+    MismatchActionParam(String, String, VarType, VarType, Option<SourceSpan>),
+    UnknownActionParam(String, String, Option<SourceSpan>),
+    MismatchActionResult(String, VarType, VarType, Option<SourceSpan>),
+    MismatchComponentProp(String, String, VarType, VarType, Option<SourceSpan>),
+    UnknownComponentProp(String, String, Option<SourceSpan>),
+    MismatchMethodShape(String, VarType, Option<SourceSpan>),
+    MismatchDeclaredType(String, VarType, VarType, Option<SourceSpan>),
+    Multiple(Vec<DocumentTypeError>),
 }
 
-// // impl DocumentTypeError {
-//     pub fn mismatch_action_param(complete_key: &str, param_key: &str, existing_ty: &VarType, ty: &VarType) -> DocumentTypeError {
-//         DocumentTypeError::MismatchActionParam(complete_key.to_owned(), param_key.to_owned(), existing_ty.to_owned(), ty.to_owned())
-//     }
-// }
+impl DocumentTypeError {
+    pub fn mismatch_action_param(complete_key: &str, param_key: &str, existing_ty: &VarType, ty: &VarType, span: Option<&SourceSpan>) -> DocumentTypeError {
+        DocumentTypeError::MismatchActionParam(complete_key.to_owned(), param_key.to_owned(), existing_ty.to_owned(), ty.to_owned(), span.cloned())
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match *self {
+            DocumentTypeError::TypeError(_) => None,
+            DocumentTypeError::MismatchActionParam(_, _, _, _, ref span)
+            | DocumentTypeError::UnknownActionParam(_, _, ref span)
+            | DocumentTypeError::MismatchActionResult(_, _, _, ref span)
+            | DocumentTypeError::MismatchComponentProp(_, _, _, _, ref span)
+            | DocumentTypeError::UnknownComponentProp(_, _, ref span)
+            | DocumentTypeError::MismatchMethodShape(_, _, ref span)
+            | DocumentTypeError::MismatchDeclaredType(_, _, _, ref span) => span.as_ref(),
+            DocumentTypeError::Multiple(ref errors) => errors[0].span(),
+        }
+    }
+
+    /// Individual errors, for errors collected from a single pass over the document.
+    pub fn errors(&self) -> Vec<&DocumentTypeError> {
+        match *self {
+            DocumentTypeError::Multiple(ref errors) => errors.iter().collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// Renders each error with the source line it refers to, where known.
+    pub fn diagnostic(&self) -> String {
+        let mut out = String::new();
+        self.write_diagnostic(&mut out).ok();
+        out
+    }
+
+    fn write_diagnostic(&self, out: &mut String) -> fmt::Result {
+        for (idx, err) in self.errors().into_iter().enumerate() {
+            if idx > 0 {
+                writeln!(out, "")?;
+            }
+
+            writeln!(out, "error: {}", err.message())?;
+            if let Some(span) = err.span() {
+                writeln!(out, "  --> {}", span)?;
+                span.write_snippet(out)?;
+            };
+        }
+
+        Ok(())
+    }
+
+    fn message(&self) -> String {
+        match *self {
+            DocumentTypeError::TypeError(ref e) => e.to_owned(),
+            DocumentTypeError::MismatchActionParam(ref complete_key, ref param_key, ref existing_ty, ref ty, _) => {
+                format!("action ({}) param ({}) was previously dispatched with type {}, but is dispatched here with type {}", complete_key, param_key, existing_ty, ty)
+            }
+            DocumentTypeError::UnknownActionParam(ref complete_key, ref param_key, _) => {
+                format!("action ({}) has no param ({})", complete_key, param_key)
+            }
+            DocumentTypeError::MismatchActionResult(ref complete_key, ref store_ty, ref ty, _) => {
+                format!("action ({}) results in type {}, but the store value has type {}", complete_key, ty, store_ty)
+            }
+            DocumentTypeError::MismatchComponentProp(ref component, ref prop, ref existing_ty, ref ty, _) => {
+                format!("component ({}) prop ({}) was previously passed type {}, but is passed type {} here", component, prop, existing_ty, ty)
+            }
+            DocumentTypeError::UnknownComponentProp(ref component, ref prop, _) => {
+                format!("component ({}) has no prop ({})", component, prop)
+            }
+            DocumentTypeError::MismatchMethodShape(ref method, ref ty, _) => {
+                format!("pipeline method ({}) requires a collection, but is applied to type {}", method, ty)
+            }
+            DocumentTypeError::MismatchDeclaredType(ref desc, ref declared_ty, ref ty, _) => {
+                format!("{} is declared with type {}, but is given type {}", desc, declared_ty, ty)
+            }
+            DocumentTypeError::Multiple(ref errors) => {
+                format!("{} (and {} more errors)", errors[0].message(), errors.len() - 1)
+            }
+        }
+    }
+}
 
 impl Error for DocumentTypeError {
     fn description(&self) -> &str {
         match self {
             &DocumentTypeError::TypeError(..) => "Type error in document",
-            // &DocumentTypeError::MismatchActionParam(..) => "Type error in document: reducer action param has different type than previous dispatch of this action."
+            &DocumentTypeError::MismatchActionParam(..) => "Type error in document: reducer action param has different type than previous dispatch of this action.",
+            &DocumentTypeError::UnknownActionParam(..) => "Type error in document: reducer action has no such param.",
+            &DocumentTypeError::MismatchActionResult(..) => "Type error in document: reducer action results in a different type than the store value.",
+            &DocumentTypeError::MismatchComponentProp(..) => "Type error in document: component prop has different type than previous use of this component.",
+            &DocumentTypeError::UnknownComponentProp(..) => "Type error in document: component has no such prop.",
+            &DocumentTypeError::MismatchMethodShape(..) => "Type error in document: pipeline method applied to a value that is not a collection.",
+            &DocumentTypeError::MismatchDeclaredType(..) => "Type error in document: value does not match its declared type.",
+            &DocumentTypeError::Multiple(..) => "Multiple type errors in document",
         }
     }
 }
 
 impl Display for DocumentTypeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &DocumentTypeError::TypeError(ref e) => e.fmt(f),
-            // &DocumentTypeError::MismatchActionParam(ref complete_key, ref param_key, ref previous_ty, ref new_ty) => {
-            //     write!(f, "Type error: reducer action ({}) has existing type for param ({}) of ({:?}), attempting to dispatch the action again with a different type ({:?}) for this parameter.",
-            //         complete_key, param_key, previous_ty, new_ty)
-            // }
+        match self.span() {
+            Some(span) => write!(f, "{}: {}", span, self.message()),
+            None => write!(f, "{}", self.message()),
         }
     }
 }
@@ -2,6 +2,7 @@
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum UnaryOpType {
     Negate,
+    Minus,
 }
 
 #[allow(dead_code)]
@@ -11,12 +12,15 @@ pub enum BinaryOpType {
     Sub,
     Mul,
     Div,
+    Mod,
     EqualTo,
     NotEqualTo,
     LessThan,
     GreaterThan,
     LessThanOrEqualTo,
     GreaterThanOrEqualTo,
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -2,16 +2,102 @@ use error::*;
 use traits::*;
 use expressions::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+#[derive(Debug, Clone)]
 pub enum Primitive {
     CharVal(char),
     StringVal(String),
     Int32Val(i32),
+    FloatVal(f64),
     BoolVal(bool),
     NullVal,
     Undefined,
 }
 
+// Floats are compared and hashed by their bit pattern so that primitives can
+// still be used as keys.
+impl PartialEq for Primitive {
+    fn eq(&self, other: &Primitive) -> bool {
+        match (self, other) {
+            (&Primitive::CharVal(a), &Primitive::CharVal(b)) => a == b,
+            (&Primitive::StringVal(ref a), &Primitive::StringVal(ref b)) => a == b,
+            (&Primitive::Int32Val(a), &Primitive::Int32Val(b)) => a == b,
+            (&Primitive::FloatVal(a), &Primitive::FloatVal(b)) => a.to_bits() == b.to_bits(),
+            (&Primitive::BoolVal(a), &Primitive::BoolVal(b)) => a == b,
+            (&Primitive::NullVal, &Primitive::NullVal) => true,
+            (&Primitive::Undefined, &Primitive::Undefined) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Primitive {}
+
+impl Hash for Primitive {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            Primitive::CharVal(c) => c.hash(state),
+            Primitive::StringVal(ref s) => s.hash(state),
+            Primitive::Int32Val(n) => n.hash(state),
+            Primitive::FloatVal(f) => f.to_bits().hash(state),
+            Primitive::BoolVal(b) => b.hash(state),
+            Primitive::NullVal | Primitive::Undefined => {}
+        }
+    }
+}
+
+/// Formats a float the way the client would display it.
+pub fn float_to_string(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_owned()
+    } else if f.is_infinite() && f > 0.0 {
+        "Infinity".to_owned()
+    } else if f.is_infinite() {
+        "-Infinity".to_owned()
+    } else {
+        format!("{}", f)
+    }
+}
+
+impl Primitive {
+    /// Numeric value of this primitive, promoting integers to floats.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Primitive::Int32Val(n) => Some(f64::from(n)),
+            Primitive::FloatVal(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Whether this value is considered true by `&&`, `||` and conditionals, as on the client.
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            Primitive::CharVal(_) => true,
+            Primitive::StringVal(ref s) => !s.is_empty(),
+            Primitive::Int32Val(n) => n != 0,
+            Primitive::FloatVal(f) => f != 0.0 && !f.is_nan(),
+            Primitive::BoolVal(b) => b,
+            Primitive::NullVal | Primitive::Undefined => false,
+        }
+    }
+
+    /// Value as it appears when concatenated with a string, matching the client.
+    pub fn to_concat_string(&self) -> String {
+        match *self {
+            Primitive::CharVal(c) => c.to_string(),
+            Primitive::StringVal(ref s) => s.to_owned(),
+            Primitive::Int32Val(n) => n.to_string(),
+            Primitive::FloatVal(f) => float_to_string(f),
+            Primitive::BoolVal(b) => b.to_string(),
+            Primitive::NullVal => "null".to_owned(),
+            Primitive::Undefined => "undefined".to_owned(),
+        }
+    }
+}
+
 impl TryProcessFrom<ExpressionValue<OutputExpression>> for Primitive {
     fn try_process_from(
         src: &ExpressionValue<OutputExpression>,
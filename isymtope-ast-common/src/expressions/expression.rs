@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
@@ -15,9 +16,14 @@ pub enum CommonBindings<T> {
     CurrentReducerState(PhantomData<T>),
     CurrentItem(PhantomData<T>),
     CurrentItemIndex,
+    /// Item of the `for` loop with the given key, so that nested loops can refer
+    /// to the items of enclosing loops
+    LoopItem(String, PhantomData<T>),
+    LoopItemIndex(String),
     NamedReducerKey(String, PhantomData<T>),
     NamedReducerActionParam(String, PhantomData<T>),
     NamedQueryParam(String, PhantomData<T>),
+    NamedRouteParam(String, PhantomData<T>),
     NamedComponentProp(String, PhantomData<T>),
     ComponentPropsObject(PhantomData<T>),
     NamedElementBoundValue(String, PhantomData<T>),
@@ -34,6 +40,7 @@ impl<T> CommonBindings<T> {
             CommonBindings::NamedReducerActionParam(ref s, _) => Some(s.as_str()),
             CommonBindings::NamedComponentProp(ref s, _) => Some(s.as_str()),
             CommonBindings::NamedQueryParam(ref s, _) => Some(s.as_str()),
+            CommonBindings::NamedRouteParam(ref s, _) => Some(s.as_str()),
             CommonBindings::NamedEventBoundValue(ref s, _) => Some(s.as_str()),
             CommonBindings::PathAlias(ref s, _) => Some(s.as_str()),
             _ => None,
@@ -55,6 +62,8 @@ where
             }
             CommonBindings::CurrentItem(_) => Ok(CommonBindings::CurrentItem(Default::default())),
             CommonBindings::CurrentItemIndex => Ok(CommonBindings::CurrentItemIndex),
+            CommonBindings::LoopItem(ref key, _) => Ok(CommonBindings::LoopItem(key.to_owned(), Default::default())),
+            CommonBindings::LoopItemIndex(ref key) => Ok(CommonBindings::LoopItemIndex(key.to_owned())),
             CommonBindings::NamedReducerKey(ref s, _) => Ok(CommonBindings::NamedReducerKey(
                 s.to_owned(),
                 Default::default(),
@@ -66,6 +75,10 @@ where
                 s.to_owned(),
                 Default::default(),
             )),
+            CommonBindings::NamedRouteParam(ref s, _) => Ok(CommonBindings::NamedRouteParam(
+                s.to_owned(),
+                Default::default(),
+            )),
             CommonBindings::NamedComponentProp(ref s, _) => Ok(
                 CommonBindings::NamedComponentProp(s.to_owned(), Default::default()),
             ),
@@ -152,6 +165,15 @@ pub enum ExpressionValue<T> {
 }
 
 impl<T> ExpressionValue<T> {
+    /// Truthiness of an evaluated value, if it can be determined without the client.
+    pub fn truthiness(&self) -> Option<bool> {
+        match *self {
+            ExpressionValue::Primitive(ref p) => Some(p.is_truthy()),
+            ExpressionValue::Expression(Expression::Composite(_)) => Some(true),
+            _ => None,
+        }
+    }
+
     pub fn is_primitive(&self) -> bool {
         match *self {
             ExpressionValue::Primitive(..) => true,
@@ -255,16 +277,36 @@ impl<T: Debug> MapIdents<T> for ExpressionValue<T> {
 
 #[allow(dead_code)]
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
-pub struct FormalParams<T>(Option<Vec<String>>, PhantomData<T>);
+pub struct FormalParams<T>(Option<Vec<String>>, Vec<(String, TypeAnnotation)>, PhantomData<T>);
 
 impl<T> FormalParams<T> {
     pub fn new(v: Option<Vec<String>>) -> Self {
-        FormalParams(v, Default::default())
+        FormalParams(v, Default::default(), Default::default())
+    }
+
+    /// Params, each with an optional type annotation
+    pub fn with_types(v: Option<Vec<(String, Option<TypeAnnotation>)>>) -> Self {
+        let v = v.unwrap_or_default();
+        let types = v.iter()
+            .filter_map(|&(ref name, ref ty)| ty.as_ref().map(|ty| (name.to_owned(), ty.to_owned())))
+            .collect();
+        let params = if v.is_empty() { None } else { Some(v.into_iter().map(|(name, _)| name).collect()) };
+
+        FormalParams(params, types, Default::default())
     }
 
     pub fn params<'a>(&'a self) -> Option<impl Iterator<Item = &'a str>> {
         self.0.as_ref().map(|v| v.iter().map(|s| s.as_str()))
     }
+
+    /// Type annotation of the param `name`, if any
+    pub fn param_type(&self, name: &str) -> Option<&TypeAnnotation> {
+        self.1.iter().find(|p| p.0 == name).map(|p| &p.1)
+    }
+
+    pub fn param_types<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a TypeAnnotation)> {
+        self.1.iter().map(|&(ref name, ref ty)| (name.as_str(), ty))
+    }
 }
 
 impl<I, O> TryProcessFrom<FormalParams<I>> for FormalParams<O>
@@ -275,7 +317,7 @@ where
         src: &FormalParams<I>,
         _ctx: &mut ProcessingContext,
     ) -> DocumentProcessingResult<Self> {
-        Ok(FormalParams(src.0.clone(), Default::default()))
+        Ok(FormalParams(src.0.clone(), src.1.clone(), Default::default()))
     }
 }
 
@@ -287,7 +329,7 @@ where
         src: &FormalParams<I>,
         _ctx: &mut OutputContext,
     ) -> DocumentProcessingResult<Self> {
-        Ok(FormalParams(src.0.clone(), Default::default()))
+        Ok(FormalParams(src.0.clone(), src.1.clone(), Default::default()))
     }
 }
 
@@ -343,11 +385,15 @@ where
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct PropValue<T>(String, ExpressionValue<T>, Option<String>);
+pub struct PropValue<T>(String, ExpressionValue<T>, Option<String>, NodeSpan);
 
 impl<T> PropValue<T> {
     pub fn new(key: String, e: ExpressionValue<T>, alias: Option<String>) -> Self {
-        PropValue(key, e, alias)
+        PropValue(key, e, alias, Default::default())
+    }
+
+    pub fn with_span(self, span: NodeSpan) -> Self {
+        PropValue(self.0, self.1, self.2, span)
     }
 
     pub fn key(&self) -> &str {
@@ -359,6 +405,9 @@ impl<T> PropValue<T> {
     pub fn alias(&self) -> Option<&str> {
         self.2.as_ref().map(|s| s.as_str())
     }
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.3.span()
+    }
 }
 
 impl<I, O> TryProcessFrom<PropValue<I>> for PropValue<O>
@@ -372,7 +421,8 @@ where
         Ok(PropValue(
             src.0.clone(),
             TryProcessFrom::try_process_from(&src.1, ctx)?,
-            src.2.clone()
+            src.2.clone(),
+            src.3.clone()
         ))
     }
 }
@@ -388,7 +438,8 @@ where
         Ok(PropValue(
             src.0.clone(),
             TryEvalFrom::try_eval_from(&src.1, ctx)?,
-            src.2.clone()
+            src.2.clone(),
+            src.3.clone()
         ))
     }
 }
@@ -425,11 +476,15 @@ where
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct ElementPropValue<T>(String, ExpressionValue<T>);
+pub struct ElementPropValue<T>(String, ExpressionValue<T>, NodeSpan);
 
 impl<T> ElementPropValue<T> {
     pub fn new(key: String, e: ExpressionValue<T>) -> Self {
-        ElementPropValue(key, e)
+        ElementPropValue(key, e, Default::default())
+    }
+
+    pub fn with_span(self, span: NodeSpan) -> Self {
+        ElementPropValue(self.0, self.1, span)
     }
 
     pub fn name(&self) -> &str {
@@ -438,6 +493,9 @@ impl<T> ElementPropValue<T> {
     pub fn expr(&self) -> &ExpressionValue<T> {
         &self.1
     }
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.2.span()
+    }
 }
 
 impl<I, O> TryProcessFrom<ElementPropValue<I>> for ElementPropValue<O>
@@ -457,6 +515,7 @@ where
         Ok(ElementPropValue(
             src.0.clone(),
             TryProcessFrom::try_process_from(&src.1, ctx)?,
+            src.2.clone(),
         ))
     }
 }
@@ -478,6 +537,7 @@ where
         Ok(ElementPropValue(
             src.0.clone(),
             TryEvalFrom::try_eval_from(&src.1, ctx)?,
+            src.2.clone(),
         ))
     }
 }
@@ -676,6 +736,84 @@ impl TryProcessFrom<Expression<SourceExpression>> for ExpressionValue<ProcessedE
     }
 }
 
+/// Evaluates an arithmetic or equality operation on two numbers, promoting to
+/// float when either side is a float or an integer division is inexact.
+fn eval_numeric_op(
+    op: &BinaryOpType,
+    a: &Primitive,
+    b: &Primitive,
+) -> DocumentProcessingResult<Option<Primitive>> {
+    let overflow = || try_eval_from_err!(format!("Numeric overflow evaluating {:?} {:?} {:?}", a, op, b));
+    let div_by_zero = || try_eval_from_err!(format!("Division by zero evaluating {:?} {:?} {:?}", a, op, b));
+
+    if let (&Primitive::Int32Val(x), &Primitive::Int32Val(y)) = (a, b) {
+        return Ok(match *op {
+            BinaryOpType::Add => Some(Primitive::Int32Val(x.checked_add(y).ok_or_else(overflow)?)),
+            BinaryOpType::Sub => Some(Primitive::Int32Val(x.checked_sub(y).ok_or_else(overflow)?)),
+            BinaryOpType::Mul => Some(Primitive::Int32Val(x.checked_mul(y).ok_or_else(overflow)?)),
+            BinaryOpType::Div if y == 0 => Err(div_by_zero())?,
+            BinaryOpType::Div => match x.checked_rem(y) {
+                Some(0) => Some(Primitive::Int32Val(x.checked_div(y).ok_or_else(overflow)?)),
+                Some(_) => Some(Primitive::FloatVal(f64::from(x) / f64::from(y))),
+                None => Err(overflow())?,
+            },
+            BinaryOpType::Mod if y == 0 => Err(div_by_zero())?,
+            BinaryOpType::Mod => Some(Primitive::Int32Val(x.checked_rem(y).unwrap_or(0))),
+            BinaryOpType::EqualTo => Some(Primitive::BoolVal(x == y)),
+            BinaryOpType::NotEqualTo => Some(Primitive::BoolVal(x != y)),
+            _ => None,
+        });
+    }
+
+    let (x, y) = match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Ok(None),
+    };
+
+    let res = match *op {
+        BinaryOpType::Add => x + y,
+        BinaryOpType::Sub => x - y,
+        BinaryOpType::Mul => x * y,
+        BinaryOpType::Div | BinaryOpType::Mod if y == 0.0 => Err(div_by_zero())?,
+        BinaryOpType::Div => x / y,
+        BinaryOpType::Mod => x % y,
+        BinaryOpType::EqualTo => return Ok(Some(Primitive::BoolVal(x == y))),
+        BinaryOpType::NotEqualTo => return Ok(Some(Primitive::BoolVal(x != y))),
+        _ => return Ok(None),
+    };
+
+    if res.is_infinite() && x.is_finite() && y.is_finite() {
+        return Err(overflow());
+    }
+
+    Ok(Some(Primitive::FloatVal(res)))
+}
+
+/// Orders two primitives of comparable types. The inner value is `None` for
+/// unordered floats (NaN), which compare false against everything.
+fn compare_primitives(a: &Primitive, b: &Primitive) -> Option<Option<Ordering>> {
+    match (a, b) {
+        (&Primitive::StringVal(ref a), &Primitive::StringVal(ref b)) => Some(Some(a.cmp(b))),
+        (&Primitive::CharVal(ref a), &Primitive::CharVal(ref b)) => Some(Some(a.cmp(b))),
+        (&Primitive::BoolVal(ref a), &Primitive::BoolVal(ref b)) => Some(Some(a.cmp(b))),
+        (&Primitive::Int32Val(ref a), &Primitive::Int32Val(ref b)) => Some(Some(a.cmp(b))),
+        _ => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => Some(a.partial_cmp(&b)),
+            _ => None,
+        },
+    }
+}
+
+fn eval_ordering(op: &BinaryOpType, ord: Option<Ordering>) -> Option<bool> {
+    match *op {
+        BinaryOpType::LessThan => Some(ord == Some(Ordering::Less)),
+        BinaryOpType::LessThanOrEqualTo => Some(ord == Some(Ordering::Less) || ord == Some(Ordering::Equal)),
+        BinaryOpType::GreaterThan => Some(ord == Some(Ordering::Greater)),
+        BinaryOpType::GreaterThanOrEqualTo => Some(ord == Some(Ordering::Greater) || ord == Some(Ordering::Equal)),
+        _ => None,
+    }
+}
+
 fn eval_expression<T>(
     src: &Expression<T>,
     ctx: &mut OutputContext,
@@ -707,6 +845,16 @@ where
                     Some(ExpressionValue::Primitive(Primitive::BoolVal(!b)))
                 }
 
+                (&UnaryOpType::Minus, &ExpressionValue::Primitive(Primitive::Int32Val(n))) => {
+                    let n = n.checked_neg()
+                        .ok_or_else(|| try_eval_from_err!(format!("Integer overflow evaluating -({})", n)))?;
+                    Some(ExpressionValue::Primitive(Primitive::Int32Val(n)))
+                }
+
+                (&UnaryOpType::Minus, &ExpressionValue::Primitive(Primitive::FloatVal(f))) => {
+                    Some(ExpressionValue::Primitive(Primitive::FloatVal(-f)))
+                }
+
                 _ => None,
             };
 
@@ -720,31 +868,46 @@ where
         }
 
         Expression::BinaryOp(ref op, box ref a, box ref b) => {
-            let a = TryEvalFrom::try_eval_from(a, ctx)?;
-            let b = TryEvalFrom::try_eval_from(b, ctx)?;
+            let a: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(a, ctx)?;
+
+            // Logical operators short-circuit and, as on the client, yield one of their operands
+            match (op, a.truthiness()) {
+                (&BinaryOpType::And, Some(false)) | (&BinaryOpType::Or, Some(true)) => return Ok(Some(a)),
+                (&BinaryOpType::And, Some(true)) | (&BinaryOpType::Or, Some(false)) => {
+                    return Ok(Some(TryEvalFrom::try_eval_from(b, ctx)?))
+                }
+                _ => {}
+            };
+
+            let b: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(b, ctx)?;
+
+            if let (&ExpressionValue::Primitive(ref pa), &ExpressionValue::Primitive(ref pb)) = (&a, &b) {
+                if let Some(res) = compare_primitives(pa, pb).and_then(|ord| eval_ordering(op, ord)) {
+                    return Ok(Some(ExpressionValue::Primitive(Primitive::BoolVal(res))));
+                }
+            }
 
             let res = match (op, &a, &b) {
                 (
                     _,
-                    &ExpressionValue::Primitive(Primitive::Int32Val(a)),
-                    &ExpressionValue::Primitive(Primitive::Int32Val(b)),
-                ) => {
-                    match op {
-                        &BinaryOpType::Add => Some(ExpressionValue::Primitive(Primitive::Int32Val(a + b))),
-                        &BinaryOpType::Sub => Some(ExpressionValue::Primitive(Primitive::Int32Val(a - b))),
-                        &BinaryOpType::Mul => Some(ExpressionValue::Primitive(Primitive::Int32Val(a * b))),
-                        &BinaryOpType::Div => Some(ExpressionValue::Primitive(Primitive::Int32Val(a / b))),
-                        &BinaryOpType::EqualTo => Some(ExpressionValue::Primitive(Primitive::BoolVal(a == b))),
-                        &BinaryOpType::NotEqualTo => Some(ExpressionValue::Primitive(Primitive::BoolVal(a != b))),
-                        _ => None
-                    }
+                    &ExpressionValue::Primitive(ref a),
+                    &ExpressionValue::Primitive(ref b),
+                ) if a.as_f64().is_some() && b.as_f64().is_some() => {
+                    eval_numeric_op(op, a, b)?.map(ExpressionValue::Primitive)
                 }
 
+                // String concatenation (including lowered interpolation)
                 (
                     &BinaryOpType::Add,
                     &ExpressionValue::Primitive(Primitive::StringVal(ref a)),
+                    &ExpressionValue::Primitive(ref b),
+                ) => Some(ExpressionValue::Primitive(Primitive::StringVal(format!("{}{}", a, b.to_concat_string())))),
+
+                (
+                    &BinaryOpType::Add,
+                    &ExpressionValue::Primitive(ref a),
                     &ExpressionValue::Primitive(Primitive::StringVal(ref b)),
-                ) => Some(ExpressionValue::Primitive(Primitive::StringVal(format!("{}{}", a, b)))),
+                ) => Some(ExpressionValue::Primitive(Primitive::StringVal(format!("{}{}", a.to_concat_string(), b)))),
 
                 (
                     &BinaryOpType::EqualTo,
@@ -1364,3 +1527,46 @@ impl TryEvalFrom<ExpressionValue<OutputExpression>>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(op: BinaryOpType, a: Primitive, b: Primitive) -> DocumentProcessingResult<Option<Primitive>> {
+        eval_numeric_op(&op, &a, &b)
+    }
+
+    #[test]
+    fn test_eval_numeric_op() {
+        use self::Primitive::*;
+
+        assert_eq!(eval(BinaryOpType::Add, Int32Val(2), Int32Val(-3)).ok(), Some(Some(Int32Val(-1))));
+        assert_eq!(eval(BinaryOpType::Div, Int32Val(8), Int32Val(2)).ok(), Some(Some(Int32Val(4))));
+        assert_eq!(eval(BinaryOpType::Div, Int32Val(7), Int32Val(2)).ok(), Some(Some(FloatVal(3.5))));
+        assert_eq!(eval(BinaryOpType::Mod, Int32Val(-7), Int32Val(3)).ok(), Some(Some(Int32Val(-1))));
+        assert_eq!(eval(BinaryOpType::Mul, FloatVal(9.99), Int32Val(2)).ok(), Some(Some(FloatVal(19.98))));
+        assert_eq!(eval(BinaryOpType::EqualTo, Int32Val(1), FloatVal(1.0)).ok(), Some(Some(BoolVal(true))));
+
+        assert!(eval(BinaryOpType::Div, Int32Val(1), Int32Val(0)).is_err());
+        assert!(eval(BinaryOpType::Mod, FloatVal(1.5), FloatVal(0.0)).is_err());
+        assert!(eval(BinaryOpType::Add, Int32Val(::std::i32::MAX), Int32Val(1)).is_err());
+        assert!(eval(BinaryOpType::Div, Int32Val(::std::i32::MIN), Int32Val(-1)).is_err());
+    }
+
+    #[test]
+    fn test_eval_ordering() {
+        use self::Primitive::*;
+
+        let cmp = |op: BinaryOpType, a: Primitive, b: Primitive| {
+            compare_primitives(&a, &b).and_then(|ord| eval_ordering(&op, ord))
+        };
+
+        assert_eq!(cmp(BinaryOpType::LessThan, Int32Val(1), Int32Val(2)), Some(true));
+        assert_eq!(cmp(BinaryOpType::GreaterThanOrEqualTo, Int32Val(2), FloatVal(2.0)), Some(true));
+        assert_eq!(cmp(BinaryOpType::LessThanOrEqualTo, FloatVal(::std::f64::NAN), Int32Val(1)), Some(false));
+        assert_eq!(cmp(BinaryOpType::GreaterThan, StringVal("b".into()), StringVal("a".into())), Some(true));
+        assert_eq!(cmp(BinaryOpType::LessThan, BoolVal(false), BoolVal(true)), Some(true));
+        assert_eq!(cmp(BinaryOpType::LessThan, StringVal("1".into()), Int32Val(2)), None);
+        assert_eq!(cmp(BinaryOpType::EqualTo, Int32Val(1), Int32Val(1)), None);
+    }
+}
@@ -9,7 +9,13 @@ use ast::*;
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SourceLensValue<T> {
-    ForLens(Option<String>, Box<ExpressionValue<T>>, PhantomData<T>),
+    /// Iterates a collection, with optional item alias and `key = expr` clause
+    ForLens(
+        Option<String>,
+        Box<ExpressionValue<T>>,
+        Option<Box<ExpressionValue<T>>>,
+        PhantomData<T>,
+    ),
     GetLens(Option<String>, Box<ExpressionValue<T>>, PhantomData<T>),
     QueryLens(Option<String>, LensQueryCall<T>, PhantomData<T>),
 }
@@ -17,7 +23,12 @@ pub enum SourceLensValue<T> {
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LensValue<T> {
-    ForLens(Option<String>, Box<ExpressionValue<T>>, PhantomData<T>),
+    ForLens(
+        Option<String>,
+        Box<ExpressionValue<T>>,
+        Option<Box<ExpressionValue<T>>>,
+        PhantomData<T>,
+    ),
     GetLens(Option<String>, Box<ExpressionValue<T>>, PhantomData<T>),
     QueryLens(Option<String>, QueryCall<T>, PhantomData<T>),
 }
@@ -26,7 +37,7 @@ impl<T> SourceLensValue<T> {
     pub fn default_alias(&self) -> DocumentProcessingResult<String> {
         // Alias is provided within lens expression
         match *self {
-            SourceLensValue::ForLens(Some(ref s), _, _)
+            SourceLensValue::ForLens(Some(ref s), ..)
             | SourceLensValue::GetLens(Some(ref s), _, _)
             | SourceLensValue::QueryLens(Some(ref s), _, _) => Ok(s.to_owned()),
 
@@ -48,7 +59,7 @@ impl<T> LensValue<T> {
     {
         // Alias is provided within lens expression
         match *self {
-            LensValue::ForLens(Some(ref s), _, _)
+            LensValue::ForLens(Some(ref s), ..)
             | LensValue::GetLens(Some(ref s), _, _)
             | LensValue::QueryLens(Some(ref s), _, _) => Ok(s.to_owned()),
 
@@ -78,7 +89,7 @@ impl<T> LensValue<T> {
         T: Clone,
     {
         match *self {
-            LensValue::ForLens(_, box ref expr, _) | LensValue::GetLens(_, box ref expr, _) => {
+            LensValue::ForLens(_, box ref expr, ..) | LensValue::GetLens(_, box ref expr, _) => {
                 Ok(expr.to_owned())
             }
 
@@ -95,12 +106,22 @@ impl TryProcessFrom<SourceLensValue<SourceExpression>> for LensValue<ProcessedEx
         ctx: &mut ProcessingContext,
     ) -> DocumentProcessingResult<Self> {
         match *src {
-            SourceLensValue::ForLens(ref s, box ref a, _) => {
-                let lens = LensValue::ForLens(
-                    s.to_owned(),
-                    Box::new(TryProcessFrom::try_process_from(a, ctx)?),
-                    Default::default(),
-                );
+            SourceLensValue::ForLens(ref s, box ref a, ref key, _) => {
+                let coll = TryProcessFrom::try_process_from(a, ctx)?;
+
+                // The key expression is evaluated for each item
+                let key = match *key {
+                    Some(box ref key) => {
+                        ctx.push_child_scope();
+                        bind_loop_aliases(ctx, None, s.as_ref(), None)?;
+                        let key = TryProcessFrom::try_process_from(key, ctx);
+                        ctx.pop_scope();
+                        Some(Box::new(key?))
+                    }
+                    None => None,
+                };
+
+                let lens = LensValue::ForLens(s.to_owned(), Box::new(coll), key, Default::default());
                 Ok(lens)
             }
 
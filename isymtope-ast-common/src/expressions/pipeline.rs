@@ -7,14 +7,23 @@ use itertools::FoldWhile::*;
 use error::*;
 use traits::*;
 use expressions::*;
+use ast::*;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct PipelineValue<T>(Box<ExpressionValue<T>>, Box<Vec<PipelineComponentValue<T>>>);
+pub struct PipelineValue<T>(Box<ExpressionValue<T>>, Box<Vec<PipelineComponentValue<T>>>, NodeSpan);
 
 impl<T> PipelineValue<T> {
     pub fn new(e: ExpressionValue<T>, v: Vec<PipelineComponentValue<T>>) -> Self {
-        PipelineValue(Box::new(e), Box::new(v))
+        PipelineValue(Box::new(e), Box::new(v), Default::default())
+    }
+
+    pub fn with_span(self, span: NodeSpan) -> Self {
+        PipelineValue(self.0, self.1, span)
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.2.span()
     }
 
     pub fn head(&self) -> &ExpressionValue<T> {
@@ -48,7 +57,7 @@ impl<T: Debug> MapIdents<T> for PipelineValue<T> {
         let components: Vec<_> = components.into_iter().map(|c| c.map_idents(ctx)).collect();
         let components: Vec<_> = ok_or_error(components)?.collect();
 
-        Ok(PipelineValue(Box::new(head), Box::new(components)))
+        Ok(PipelineValue(Box::new(head), Box::new(components), self.2))
     }
 }
 
@@ -68,7 +77,7 @@ where
         let expr: ExpressionValue<O> = TryProcessFrom::try_process_from(expr, ctx)?;
         let pcv: Vec<PipelineComponentValue<O>> = TryProcessFrom::try_process_from(pcv, ctx)?;
 
-        Ok(PipelineValue(Box::new(expr), Box::new(pcv)))
+        Ok(PipelineValue(Box::new(expr), Box::new(pcv), src.2.clone()))
     }
 }
 
@@ -90,7 +99,7 @@ where
         let expr: ExpressionValue<O> = TryEvalFrom::try_eval_from(expr, ctx)?;
         let pcv: Vec<PipelineComponentValue<O>> = TryEvalFrom::try_eval_from(pcv, ctx)?;
 
-        Ok(PipelineValue(Box::new(expr), Box::new(pcv)))
+        Ok(PipelineValue(Box::new(expr), Box::new(pcv), src.2.clone()))
     }
 }
 
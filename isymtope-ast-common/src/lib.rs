@@ -10,16 +10,15 @@ extern crate wasm_log;
 #[macro_use]
 extern crate lazy_static;
 
-#[cfg(feature = "uuid_v4")]
-extern crate uuid;
-
 #[macro_use]
 extern crate failure;
 extern crate itertools;
 extern crate linked_hash_map;
-extern crate rand;
 extern crate regex;
 
+#[cfg(feature = "session_time")]
+extern crate time;
+
 #[macro_use]
 pub mod error;
 
@@ -27,6 +26,7 @@ pub mod ast;
 pub mod expressions;
 pub mod objects;
 pub mod traits;
+pub mod types;
 pub mod util;
 
 pub use self::ast::*;
@@ -34,4 +34,5 @@ pub use self::error::*;
 pub use self::expressions::*;
 pub use self::objects::*;
 pub use self::traits::*;
+pub use self::types::*;
 pub use self::util::*;
@@ -1,4 +1,5 @@
 use expressions::*;
+use ast::*;
 use objects::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -26,6 +27,10 @@ impl<T> Component<T> {
         self.params.params()
     }
 
+    pub fn param_types<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a TypeAnnotation)> {
+        self.params.param_types()
+    }
+
     #[allow(dead_code)]
     pub fn block<'a>(&'a self) -> &'a Block<T> {
         &self.block
@@ -11,6 +11,7 @@ use traits::*;
 use expressions::*;
 use objects::*;
 use ast::*;
+use types::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Document {
@@ -26,6 +27,7 @@ pub struct Document {
     libraries: LinkedHashMap<String, LibraryObject>,
 
     event_bindings: Vec<ElementEventBindingName<ProcessedExpression>>,
+    types: LinkedHashMap<String, TypeDefinition>,
 }
 
 impl Document {
@@ -40,6 +42,7 @@ impl Document {
         routes: LinkedHashMap<String, Route<ProcessedExpression>>,
         libraries: LinkedHashMap<String, LibraryObject>,
         event_bindings: Vec<ElementEventBindingName<ProcessedExpression>>,
+        types: LinkedHashMap<String, TypeDefinition>,
     ) -> Self {
         Document {
             root_block: root_block,
@@ -52,6 +55,7 @@ impl Document {
             routes: routes,
             libraries: libraries,
             event_bindings: event_bindings,
+            types: types,
         }
     }
 
@@ -110,6 +114,16 @@ impl Document {
     ) -> impl Iterator<Item = &'a ElementEventBindingName<ProcessedExpression>> {
         self.event_bindings.iter()
     }
+
+    /// Declared record types
+    pub fn types<'a>(&'a self) -> impl Iterator<Item = &'a TypeDefinition> {
+        self.types.values()
+    }
+
+    /// Resolves a type annotation against the declared record types.
+    pub fn resolve_type(&self, annotation: &TypeAnnotation) -> DocumentProcessingResult<VarType> {
+        annotation.resolve(&self.types)
+    }
 }
 
 pub trait ContentProcessingContext<T>: Debug {
@@ -142,6 +156,21 @@ impl DefaultContentProcessingContext {
     }
 }
 
+/// Whether the expression is `children` within a component which has no prop of that name,
+/// which writes the content passed to the component.
+fn is_default_slot(
+    ctx: &mut ProcessingContext,
+    expr: &ExpressionValue<SourceExpression>,
+) -> DocumentProcessingResult<bool> {
+    match *expr {
+        ExpressionValue::Expression(Expression::Ident(ref ident, _)) if ident == "children" => {
+            Ok(ctx.is_environment(&ProcessingScopeEnvironment::ComponentDefinition)?
+                && ctx.find_ident(ident)?.is_none())
+        }
+        _ => Ok(false),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ContentProcessor {
     component_names: Rc<HashSet<String>>,
@@ -160,12 +189,29 @@ impl ContentProcessor {
         }
     }
 
+    /// Processes the content passed for a slot in the scope of the call site.
+    fn process_slot(
+        &mut self,
+        ctx: &mut ProcessingContext,
+        content_ctx: &mut ContentProcessingContext<ProcessedExpression>,
+        name: &str,
+        children: Vec<&ContentNode<SourceExpression>>,
+    ) -> DocumentProcessingResult<ComponentSlot<ProcessedExpression>> {
+        let mut slot_processor = ContentProcessor::new(self.component_names.clone());
+        for child in children {
+            slot_processor.process_content_node(ctx, content_ctx, child)?;
+        }
+
+        Ok(ComponentSlot::new(name.to_owned(), slot_processor.into_block()))
+    }
+
     fn process_component_call(
         &mut self,
-        _ctx: &mut ProcessingContext,
-        _content_ctx: &mut ContentProcessingContext<ProcessedExpression>,
+        ctx: &mut ProcessingContext,
+        content_ctx: &mut ContentProcessingContext<ProcessedExpression>,
         attrs: Vec<ElementAttrValue<ProcessedExpression>>,
         el_desc: ElementDescriptor<ProcessedExpression>,
+        children: Vec<&ContentNode<SourceExpression>>,
     ) -> DocumentProcessingResult<()> {
         eprintln!(
             ">>>>> Processing component call with tag: {}",
@@ -200,8 +246,9 @@ impl ContentProcessor {
         );
 
         let (for_lens, positionals) = match first_pos {
-            Some(&ExpressionValue::Lens(LensValue::ForLens(ref key, box ref expr, _), _)) => {
-                (Some((key.to_owned(), expr.to_owned())), &positionals[1..])
+            Some(&ExpressionValue::Lens(LensValue::ForLens(ref key, box ref expr, ref item_key_expr, _), _)) => {
+                let item_key_expr = item_key_expr.as_ref().map(|e| e.as_ref().to_owned());
+                (Some((key.to_owned(), expr.to_owned(), item_key_expr)), &positionals[1..])
             }
             _ => (None, &positionals[..]),
         };
@@ -227,7 +274,7 @@ impl ContentProcessor {
         eprintln!("ContentProcessor: alias_props: {:?}", alias_props);
 
         let for_item_props: Vec<_> = match for_lens {
-            Some((Some(ref item_key), _)) => {
+            Some((Some(ref item_key), ..)) => {
                 let binding = CommonBindings::CurrentItem(Default::default());
                 let prop = ElementPropValue::new(
                     item_key.to_owned(),
@@ -256,13 +303,42 @@ impl ContentProcessor {
             .collect();
         eprintln!("ContentProcessor: component_props: {:?}", component_props);
 
-        let comp_desc = ComponentInstanceDescriptor::new(el_desc, None, Some(component_props));
+        // Children given as `slot name { .. }` fill that slot, and the rest fill the default slot
+        let mut default_children: Vec<&ContentNode<SourceExpression>> = Default::default();
+        let mut named_children: LinkedHashMap<String, Vec<&ContentNode<SourceExpression>>> = Default::default();
+        for child in children {
+            match *child {
+                ContentNode::Slot(ref name, Some(box ref slot_children), _) => {
+                    named_children.entry(name.to_owned()).or_insert_with(Default::default).extend(slot_children.iter());
+                }
+                _ => default_children.push(child),
+            };
+        }
+
+        // Slot content of a mapped component can refer to the current item by its alias
+        ctx.push_child_scope();
+        if let Some((ref item_alias, ..)) = for_lens {
+            bind_loop_aliases(ctx, None, item_alias.as_ref(), None)?;
+        };
+
+        let mut slots: Vec<ComponentSlot<ProcessedExpression>> = Default::default();
+        if !default_children.is_empty() {
+            slots.push(self.process_slot(ctx, content_ctx, "children", default_children)?);
+        };
+        for (name, slot_children) in named_children {
+            slots.push(self.process_slot(ctx, content_ctx, &name, slot_children)?);
+        }
+        ctx.pop_scope();
+
+        let comp_desc = ComponentInstanceDescriptor::new(el_desc, None, Some(component_props))
+            .with_slots(slots);
 
         let inst: ElementOp<ProcessedExpression> = match for_lens {
-            Some((ref item_key, ref expr)) => ElementOp::MapInstanceComponent(
+            Some((ref item_key, ref expr, ref item_key_expr)) => ElementOp::MapInstanceComponent(
                 comp_desc,
                 item_key.to_owned(),
                 expr.to_owned(),
+                item_key_expr.to_owned(),
                 Default::default(),
             ),
 
@@ -368,9 +444,11 @@ impl ContentProcessor {
             false,
         );
 
+        let children: Vec<_> = n.children().map(|v| v.collect()).unwrap_or_default();
+
         // Invoke component
         if self.component_names.contains(tag) {
-            return self.process_component_call(ctx, content_ctx, attrs, desc);
+            return self.process_component_call(ctx, content_ctx, attrs, desc, children);
         }
 
         if let Some(events) = desc.events() {
@@ -379,8 +457,6 @@ impl ContentProcessor {
             }
         };
 
-        let children: Vec<_> = n.children().map(|v| v.collect()).unwrap_or_default();
-
         let has_children = children.len() > 0;
         let is_script = tag == "script" || tag == "SCRIPT";
         let is_iframe = tag == "iframe" || tag == "IFRAME";
@@ -440,6 +516,100 @@ impl ContentProcessor {
         Ok(())
     }
 
+    /// Flattens an `if` / `else if` / `else` chain into a single conditional block of ops.
+    fn process_if_node(
+        &mut self,
+        ctx: &mut ProcessingContext,
+        content_ctx: &mut ContentProcessingContext<ProcessedExpression>,
+        n: &ContentNode<SourceExpression>,
+    ) -> DocumentProcessingResult<()> {
+        let key = allocate_element_key();
+        let mut is_first = true;
+        let mut node = n;
+
+        loop {
+            let (cond, children, else_children) = match *node {
+                ContentNode::IfNode(box ref cond, box ref children, ref else_children, _) => {
+                    (cond, children, else_children)
+                }
+                _ => break,
+            };
+
+            let cond: ExpressionValue<ProcessedExpression> =
+                TryProcessFrom::try_process_from(cond, ctx)?;
+            if is_first {
+                self.ops.push(ElementOp::StartIf(cond, key.to_owned()));
+            } else {
+                self.ops.push(ElementOp::ElseIf(cond, key.to_owned()));
+            }
+            is_first = false;
+
+            for child in children {
+                self.process_content_node(ctx, content_ctx, child)?;
+            }
+
+            match *else_children {
+                Some(box ref else_children) => match else_children.get(0) {
+                    Some(else_if @ &ContentNode::IfNode(..)) if else_children.len() == 1 => {
+                        node = else_if;
+                    }
+
+                    _ => {
+                        self.ops.push(ElementOp::Else(key.to_owned()));
+                        for child in else_children {
+                            self.process_content_node(ctx, content_ctx, child)?;
+                        }
+                        break;
+                    }
+                },
+
+                None => break,
+            };
+        }
+
+        self.ops.push(ElementOp::EndIf(key));
+        Ok(())
+    }
+
+    /// Wraps the ops for the loop body in a `MapCollection` block, evaluated once per item.
+    fn process_for_node(
+        &mut self,
+        ctx: &mut ProcessingContext,
+        content_ctx: &mut ContentProcessingContext<ProcessedExpression>,
+        item_alias: Option<&String>,
+        index_alias: Option<&String>,
+        coll: &ExpressionValue<SourceExpression>,
+        item_key: Option<&ExpressionValue<SourceExpression>>,
+        children: Option<&Vec<ContentNode<SourceExpression>>>,
+    ) -> DocumentProcessingResult<()> {
+        let key = allocate_element_key();
+        let coll: ExpressionValue<ProcessedExpression> =
+            TryProcessFrom::try_process_from(coll, ctx)?;
+
+        ctx.push_child_scope();
+        bind_loop_aliases(ctx, Some(&key), item_alias, index_alias)?;
+
+        let item_key: Option<ExpressionValue<ProcessedExpression>> = match item_key {
+            Some(item_key) => Some(TryProcessFrom::try_process_from(item_key, ctx)?),
+            None => None,
+        };
+
+        self.ops.push(ElementOp::MapCollection(
+            key.to_owned(),
+            item_alias.map(|s| s.to_owned()),
+            coll,
+            item_key,
+            Default::default(),
+        ));
+        for child in children.into_iter().flat_map(|v| v.iter()) {
+            self.process_content_node(ctx, content_ctx, child)?;
+        }
+        ctx.pop_scope();
+
+        self.ops.push(ElementOp::EndBlock(key));
+        Ok(())
+    }
+
     pub fn process_content_node(
         &mut self,
         ctx: &mut ProcessingContext,
@@ -451,6 +621,11 @@ impl ContentProcessor {
         match *n {
             ContentNode::Element(ref e, _) => self.process_element(ctx, content_ctx, e),
 
+            ContentNode::ExpressionValue(box ref expr, _, _) if is_default_slot(ctx, expr)? => {
+                self.ops.push(ElementOp::WriteSlot("children".to_owned()));
+                Ok(())
+            }
+
             ContentNode::ExpressionValue(box ref expr, ref key, _) => {
                 let expr: ExpressionValue<ProcessedExpression> =
                     TryProcessFrom::try_process_from(expr, ctx)?;
@@ -458,6 +633,44 @@ impl ContentProcessor {
                 Ok(())
             }
 
+            ContentNode::RawValue(box ref expr, ref key, _) => {
+                let expr: ExpressionValue<ProcessedExpression> =
+                    TryProcessFrom::try_process_from(expr, ctx)?;
+                self.ops.push(ElementOp::WriteRawValue(expr, key.to_owned()));
+                Ok(())
+            }
+
+            ContentNode::IfNode(..) => self.process_if_node(ctx, content_ctx, n),
+
+            ContentNode::Slot(ref name, None, _) => {
+                if !ctx.is_environment(&ProcessingScopeEnvironment::ComponentDefinition)? {
+                    return Err(try_process_from_err!(format!(
+                        "Slot [{}] can only be written within a component",
+                        name
+                    )));
+                };
+
+                self.ops.push(ElementOp::WriteSlot(name.to_owned()));
+                Ok(())
+            }
+
+            ContentNode::Slot(ref name, Some(_), _) => Err(try_process_from_err!(format!(
+                "Content for slot [{}] can only be passed to a component",
+                name
+            ))),
+
+            ContentNode::ForNode(ref item_alias, ref index_alias, box ref coll, ref item_key, ref children, _) => {
+                self.process_for_node(
+                    ctx,
+                    content_ctx,
+                    item_alias.as_ref(),
+                    index_alias.as_ref(),
+                    coll,
+                    item_key.as_ref().map(|e| e.as_ref()),
+                    children.as_ref().map(|v| v.as_ref()),
+                )
+            }
+
             ContentNode::Extern(ref ext, _) => {
 
                 if let Some(children) = ext.children() {
@@ -499,6 +712,40 @@ impl TryProcessFrom<Template> for Document {
         ast: &Template,
         ctx: &mut ProcessingContext,
     ) -> DocumentProcessingResult<Document> {
+        // Keys allocated while processing are numbered from the start of each document
+        let _element_keys = ElementKeyScope::enter("document");
+
+        // Template files should have been merged in by the reader
+        if let Some(path) = ast.children()
+            .filter_map(|n| match *n {
+                TemplateNode::UseFile(ref p) => Some(p),
+                _ => None
+            })
+            .nth(0) {
+            return Err(try_process_from_err!(format!("Cannot import template [{}]: imports are only supported when loading templates from a file", path)));
+        };
+
+        // Record types, which may be declared in any template file
+        let mut types: LinkedHashMap<String, TypeDefinition> = Default::default();
+        for def in ast.children().filter_map(|n| match *n {
+            TemplateNode::TypeDefinition(ref t, _) => Some(t),
+            _ => None,
+        }) {
+            if types.contains_key(def.name()) {
+                let location = def.span().map(|s| format!("{}: ", s)).unwrap_or_default();
+                return Err(try_process_from_err!(format!("{}Duplicate type [{}]", location, def.name())));
+            };
+            types.insert(def.name().to_owned(), def.to_owned());
+        }
+
+        for def in types.values() {
+            for &(_, ref ty) in def.fields() {
+                ty.resolve(&types).map_err(|name| {
+                    let location = def.span().map(|s| format!("{}: ", s)).unwrap_or_default();
+                    try_process_from_err!(format!("{}Unknown type [{}] in type [{}]", location, name, def.name()))
+                })?;
+            }
+        }
 
         // Use statements
         let use_statements = ast.children()
@@ -519,15 +766,14 @@ impl TryProcessFrom<Template> for Document {
 
         let default_reducer_key: Option<String> = None;
 
-        let store_definition = ast.children()
+        // Stores from imported templates are merged into a single store
+        let root_children: Vec<_> = ast.children()
             .filter_map(|n| match *n {
-                TemplateNode::StoreDefinition(ref n, _) => Some(n),
+                TemplateNode::StoreDefinition(ref n, _) => n.children(),
                 _ => None,
             })
-            .nth(0);
-
-        let root_children = store_definition.and_then(|n| n.children());
-        let root_children: Vec<_> = root_children.map(|v| v.collect()).unwrap_or_default();
+            .flat_map(|v| v)
+            .collect();
 
         let root_default_nodes: Vec<_> = root_children
             .iter()
@@ -535,7 +781,7 @@ impl TryProcessFrom<Template> for Document {
                 StoreRootScopeNode::Common(ref c, _) => Some(c)
             })
             .filter_map(|n| match *n {
-                StoreCommonNode::LetNode(ref s, ref e, _) => Some((s.to_owned(), e.to_owned())),
+                StoreCommonNode::LetNode(ref s, ref e, ref ty, _) => Some((s.to_owned(), (e.to_owned(), ty.to_owned()))),
                 _ => None,
             })
             .collect();
@@ -593,12 +839,14 @@ impl TryProcessFrom<Template> for Document {
                     .collect();
                 let actions: Vec<_> = ok_or_error(actions)?.collect();
 
-                let default_value = reducer_defaults
+                let (default_value, annotation) = reducer_defaults
                     .get(scope.as_str())
-                    .and_then(|e| e.to_owned());
+                    .map(|v| v.to_owned())
+                    .unwrap_or_default();
 
                 let shape = default_value.as_ref().map(|e| e.shape());
-                let reducer = Reducer::new(scope.to_owned(), Some(actions), default_value, shape);
+                let reducer = Reducer::new(scope.to_owned(), Some(actions), default_value, shape)
+                    .with_annotation(annotation);
 
                 Ok(reducer)
             })
@@ -612,10 +860,14 @@ impl TryProcessFrom<Template> for Document {
                 .map(|r| TryProcessFrom::try_process_from(&r, ctx)),
         )?.collect();
 
-        let reducers: LinkedHashMap<String, Reducer<ProcessedExpression>> = root_reducers
-            .into_iter()
-            .map(|r| (r.key().to_owned(), r))
-            .collect();
+        let mut reducers: LinkedHashMap<String, Reducer<ProcessedExpression>> = Default::default();
+        for reducer in root_reducers {
+            let key = reducer.key().to_owned();
+            if reducers.contains_key(&key) {
+                return Err(try_process_from_err!(format!("Duplicate reducer [{}] in store", key)));
+            };
+            reducers.insert(key, reducer);
+        }
 
         eprintln!("Document: reducers: {:?}", reducers);
 
@@ -676,6 +928,7 @@ impl TryProcessFrom<Template> for Document {
         );
 
         for component_node in component_nodes {
+            let _element_keys = ElementKeyScope::enter(&format!("component:{}", component_node.name()));
             let children: Vec<_> = component_node
                 .children()
                 .map(|v| v.collect())
@@ -723,11 +976,10 @@ impl TryProcessFrom<Template> for Document {
             let block = content_processor.into_block();
 
             let name = component_node.name().to_owned();
-            let params: Option<Vec<_>> = component_node
-                .params()
-                .map(|v| v.map(|s| s.to_owned()).collect());
+            let params: FormalParams<ProcessedExpression> =
+                TryProcessFrom::try_process_from(component_node.formal_params(), ctx)?;
 
-            let component = Component::new(name.to_owned(), FormalParams::new(params), block);
+            let component = Component::new(name.to_owned(), params, block);
 
             components.insert(name, component);
 
@@ -746,7 +998,7 @@ impl TryProcessFrom<Template> for Document {
             .collect();
         eprintln!("Document: content_nodes: {:?}", content_nodes);
 
-        let mut content_processor: ContentProcessor = ContentProcessor::new(component_names);
+        let mut content_processor: ContentProcessor = ContentProcessor::new(component_names.clone());
         for ref n in content_nodes {
             eprintln!("Document: process content node: {:?}", n);
             content_processor.process_content_node(ctx, &mut content_ctx, n)?;
@@ -774,9 +1026,23 @@ impl TryProcessFrom<Template> for Document {
 
         for routing_node in routing_nodes {
             let pattern = routing_node.pattern().to_owned();
-            let route: Route<ProcessedExpression> =
+            let mut route: Route<ProcessedExpression> =
                 TryProcessFrom::try_process_from(routing_node, ctx)?;
 
+            // Content routes are rendered in place of the root block
+            if let RouteAction::Content(ref children, _) = *routing_node.action() {
+                ctx.push_child_scope();
+                routing_node.bind_params(ctx)?;
+
+                let mut content_processor = ContentProcessor::new(component_names.clone());
+                for child in children.iter().flat_map(|v| v) {
+                    content_processor.process_content_node(ctx, &mut content_ctx, child)?;
+                }
+                route = route.with_block(content_processor.into_block());
+
+                ctx.pop_scope();
+            };
+
             eprintln!("Document: inserting route for {}: {:?}", pattern, route);
             routes.insert(pattern, route);
         }
@@ -805,9 +1071,28 @@ impl TryProcessFrom<Template> for Document {
             routes,
             libraries,
             event_bindings,
+            types,
         );
 
         eprintln!("Completed Constructing Document");
+
+        // Every annotation must name a declared type
+        let annotations = doc.reducers()
+            .into_iter()
+            .flat_map(|v| v)
+            .flat_map(|(_, reducer)| {
+                let actions = reducer.actions().into_iter().flat_map(|v| v);
+                reducer.annotation().into_iter()
+                    .chain(actions.flat_map(|a| a.param_types().map(|(_, ty)| ty)))
+            })
+            .chain(doc.components().flat_map(|c| c.param_types().map(|(_, ty)| ty)));
+        for annotation in annotations {
+            doc.resolve_type(annotation)?;
+        }
+
+        #[cfg(feature = "types")]
+        check_document(ast, &doc)?;
+
         Ok(doc)
     }
 }
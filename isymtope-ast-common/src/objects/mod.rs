@@ -3,6 +3,7 @@ pub mod library;
 pub mod ops;
 pub mod reducer;
 pub mod route;
+pub mod route_pattern;
 pub mod block;
 pub mod component;
 pub mod query;
@@ -11,6 +12,7 @@ pub use self::library::*;
 pub use self::ops::*;
 pub use self::reducer::*;
 pub use self::route::*;
+pub use self::route_pattern::*;
 pub use self::block::*;
 pub use self::component::*;
 pub use self::query::*;
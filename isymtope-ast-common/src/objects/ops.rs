@@ -7,6 +7,7 @@ use error::*;
 use traits::*;
 use expressions::*;
 use ast::*;
+use objects::*;
 // use output::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -145,11 +146,49 @@ where
     }
 }
 
+/// Content passed to a component at its call site, written where the component
+/// renders the slot with the same name (`children` for the default slot).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ComponentSlot<T>(String, Block<T>);
+
+impl<T> ComponentSlot<T> {
+    pub fn new(name: String, block: Block<T>) -> Self {
+        ComponentSlot(name, block)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn block(&self) -> &Block<T> {
+        &self.1
+    }
+}
+
+impl TryProcessFrom<ComponentSlot<SourceExpression>> for ComponentSlot<ProcessedExpression> {
+    fn try_process_from(
+        src: &ComponentSlot<SourceExpression>,
+        ctx: &mut ProcessingContext,
+    ) -> DocumentProcessingResult<Self> {
+        Ok(ComponentSlot(src.0.to_owned(), TryProcessFrom::try_process_from(&src.1, ctx)?))
+    }
+}
+
+impl TryEvalFrom<ComponentSlot<ProcessedExpression>> for ComponentSlot<OutputExpression> {
+    fn try_eval_from(
+        src: &ComponentSlot<ProcessedExpression>,
+        ctx: &mut OutputContext,
+    ) -> DocumentProcessingResult<Self> {
+        Ok(ComponentSlot(src.0.to_owned(), TryEvalFrom::try_eval_from(&src.1, ctx)?))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ComponentInstanceDescriptor<T>(
     ElementDescriptor<T>,
     Option<String>,
     Option<Vec<ElementPropValue<T>>>,
+    Option<Vec<ComponentSlot<T>>>,
 );
 
 impl<T> ComponentInstanceDescriptor<T> {
@@ -158,7 +197,11 @@ impl<T> ComponentInstanceDescriptor<T> {
         s: Option<String>,
         component_props: Option<Vec<ElementPropValue<T>>>,
     ) -> Self {
-        ComponentInstanceDescriptor(desc, s, component_props)
+        ComponentInstanceDescriptor(desc, s, component_props, None)
+    }
+
+    pub fn with_slots(self, slots: Vec<ComponentSlot<T>>) -> Self {
+        ComponentInstanceDescriptor(self.0, self.1, self.2, Some(slots))
     }
 
     pub fn desc(&self) -> &ElementDescriptor<T> {
@@ -168,6 +211,7 @@ impl<T> ComponentInstanceDescriptor<T> {
     pub fn merge_props(self, new_props: Vec<ElementPropValue<T>>) -> Self {
         let element_desc = self.0;
         let s = self.1;
+        let slots = self.3;
 
         let props: Vec<_> = self.2.map(|v| v.into_iter().collect()).unwrap_or_default();
 
@@ -179,7 +223,7 @@ impl<T> ComponentInstanceDescriptor<T> {
 
         let props: Vec<_> = props.into_iter().map(|(_, p)| p).collect();
 
-        ComponentInstanceDescriptor(element_desc, s, Some(props))
+        ComponentInstanceDescriptor(element_desc, s, Some(props), slots)
     }
 
     pub fn tag(&self) -> &str {
@@ -192,6 +236,10 @@ impl<T> ComponentInstanceDescriptor<T> {
     pub fn props<'a>(&'a self) -> Option<impl Iterator<Item = &'a ElementPropValue<T>>> {
         self.2.as_ref().map(|v| v.iter())
     }
+
+    pub fn slots<'a>(&'a self) -> Option<impl Iterator<Item = &'a ComponentSlot<T>>> {
+        self.3.as_ref().map(|v| v.iter())
+    }
 }
 
 impl TryProcessFrom<ComponentInstanceDescriptor<SourceExpression>>
@@ -210,11 +258,14 @@ impl TryProcessFrom<ComponentInstanceDescriptor<SourceExpression>>
             TryProcessFrom::try_process_from(&src.0, ctx)?;
         let component_props: Option<Vec<ElementPropValue<ProcessedExpression>>> =
             TryProcessFrom::try_process_from(&src.2, ctx)?;
+        let slots: Option<Vec<ComponentSlot<ProcessedExpression>>> =
+            TryProcessFrom::try_process_from(&src.3, ctx)?;
 
         Ok(ComponentInstanceDescriptor(
             desc,
             src.1.to_owned(),
             component_props,
+            slots,
         ))
     }
 }
@@ -235,6 +286,10 @@ impl TryEvalFrom<ComponentInstanceDescriptor<ProcessedExpression>>
             TryEvalFrom::try_eval_from(&src.2, ctx)?;
         eprintln!("TryEval ComponentInstanceDescriptor -> ComponentInstanceDescriptor component_props: {:?}", component_props);
 
+        // Slot content is evaluated in the scope of the call site
+        let slots: Option<Vec<ComponentSlot<OutputExpression>>> =
+            TryEvalFrom::try_eval_from(&src.3, ctx)?;
+
         ctx.push_child_scope();
 
         if let Some(ref component_props) = component_props {
@@ -260,6 +315,7 @@ impl TryEvalFrom<ComponentInstanceDescriptor<ProcessedExpression>>
             desc,
             src.1.to_owned(),
             component_props,
+            slots,
         ))
     }
 }
@@ -280,6 +336,10 @@ pub enum ElementOp<T> {
 
     ElementClose(String),
     WriteValue(ExpressionValue<T>, String),
+    /// Writes trusted HTML, without escaping
+    WriteRawValue(ExpressionValue<T>, String),
+    /// Writes the content passed to the current component for the named slot, if any
+    WriteSlot(String),
 
     SkipNode,
     SkipOuterElement(SkipElementOp<T>),
@@ -290,12 +350,27 @@ pub enum ElementOp<T> {
         ComponentInstanceDescriptor<T>,
         Option<String>,
         ExpressionValue<T>,
+        Option<ExpressionValue<T>>,
         PhantomData<T>,
     ),
 
     StartBlock(String),
     EndBlock(String),
-    MapCollection(String, Option<String>, ExpressionValue<T>, PhantomData<T>),
+    /// Repeats the following ops for each item, up to a matching `EndBlock` with the same key.
+    /// Items are keyed by the optional key expression, or by position.
+    MapCollection(
+        String,
+        Option<String>,
+        ExpressionValue<T>,
+        Option<ExpressionValue<T>>,
+        PhantomData<T>,
+    ),
+
+    /// Conditional content, closed by a matching `EndIf` with the same key
+    StartIf(ExpressionValue<T>, String),
+    ElseIf(ExpressionValue<T>, String),
+    Else(String),
+    EndIf(String),
 }
 
 impl TryProcessFrom<SkipElementOp<SourceExpression>> for SkipElementOp<ProcessedExpression> {
@@ -349,17 +424,35 @@ impl TryProcessFrom<ElementOp<SourceExpression>> for ElementOp<ProcessedExpressi
                 ))
             }
 
+            ElementOp::WriteRawValue(ref e, ref s) => Ok(ElementOp::WriteRawValue(
+                TryProcessFrom::try_process_from(e, ctx)?,
+                s.to_owned(),
+            )),
+
             ElementOp::SkipNode => Ok(ElementOp::SkipNode),
             ElementOp::SkipOuterElement(ref e) => Ok(ElementOp::SkipOuterElement(TryProcessFrom::try_process_from(e, ctx)?)),
             ElementOp::SkipElement(ref e) => Ok(ElementOp::SkipElement(TryProcessFrom::try_process_from(e, ctx)?)),
 
+            ElementOp::WriteSlot(ref s) => Ok(ElementOp::WriteSlot(s.to_owned())),
             ElementOp::StartBlock(ref s) => Ok(ElementOp::StartBlock(s.to_owned())),
             ElementOp::EndBlock(ref s) => Ok(ElementOp::EndBlock(s.to_owned())),
 
-            ElementOp::MapCollection(ref s, ref k, ref e, _) => Ok(ElementOp::MapCollection(
+            ElementOp::StartIf(ref e, ref s) => Ok(ElementOp::StartIf(
+                TryProcessFrom::try_process_from(e, ctx)?,
+                s.to_owned(),
+            )),
+            ElementOp::ElseIf(ref e, ref s) => Ok(ElementOp::ElseIf(
+                TryProcessFrom::try_process_from(e, ctx)?,
+                s.to_owned(),
+            )),
+            ElementOp::Else(ref s) => Ok(ElementOp::Else(s.to_owned())),
+            ElementOp::EndIf(ref s) => Ok(ElementOp::EndIf(s.to_owned())),
+
+            ElementOp::MapCollection(ref s, ref k, ref e, ref key, _) => Ok(ElementOp::MapCollection(
                 s.to_owned(),
                 k.as_ref().map(|s| s.to_owned()),
                 TryProcessFrom::try_process_from(e, ctx)?,
+                TryProcessFrom::try_process_from(key, ctx)?,
                 Default::default(),
             )),
 
@@ -368,7 +461,7 @@ impl TryProcessFrom<ElementOp<SourceExpression>> for ElementOp<ProcessedExpressi
                 Default::default(),
             )),
 
-            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, _) => {
+            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, ref key, _) => {
                 let comp_desc: ComponentInstanceDescriptor<ProcessedExpression> =
                     TryProcessFrom::try_process_from(comp_desc, ctx)?;
                 let coll: ExpressionValue<ProcessedExpression> =
@@ -378,6 +471,7 @@ impl TryProcessFrom<ElementOp<SourceExpression>> for ElementOp<ProcessedExpressi
                     comp_desc,
                     item_key.to_owned(),
                     coll,
+                    TryProcessFrom::try_process_from(key, ctx)?,
                     Default::default(),
                 ))
             } // _ => Ok(*src.to_owned())
@@ -439,17 +533,35 @@ impl TryEvalFrom<ElementOp<ProcessedExpression>> for ElementOp<OutputExpression>
                 ))
             }
 
+            ElementOp::WriteRawValue(ref e, ref s) => Ok(ElementOp::WriteRawValue(
+                TryEvalFrom::try_eval_from(e, ctx)?,
+                s.to_owned(),
+            )),
+
+            ElementOp::WriteSlot(ref s) => Ok(ElementOp::WriteSlot(s.to_owned())),
             ElementOp::StartBlock(ref s) => Ok(ElementOp::StartBlock(s.to_owned())),
             ElementOp::EndBlock(ref s) => Ok(ElementOp::EndBlock(s.to_owned())),
 
+            ElementOp::StartIf(ref e, ref s) => Ok(ElementOp::StartIf(
+                TryEvalFrom::try_eval_from(e, ctx)?,
+                s.to_owned(),
+            )),
+            ElementOp::ElseIf(ref e, ref s) => Ok(ElementOp::ElseIf(
+                TryEvalFrom::try_eval_from(e, ctx)?,
+                s.to_owned(),
+            )),
+            ElementOp::Else(ref s) => Ok(ElementOp::Else(s.to_owned())),
+            ElementOp::EndIf(ref s) => Ok(ElementOp::EndIf(s.to_owned())),
+
             ElementOp::SkipNode => Ok(ElementOp::SkipNode),
             ElementOp::SkipOuterElement(ref e) => Ok(ElementOp::SkipOuterElement(TryEvalFrom::try_eval_from(e, ctx)?)),
             ElementOp::SkipElement(ref e) => Ok(ElementOp::SkipElement(TryEvalFrom::try_eval_from(e, ctx)?)),
 
-            ElementOp::MapCollection(ref s, ref k, ref e, _) => Ok(ElementOp::MapCollection(
+            ElementOp::MapCollection(ref s, ref k, ref e, ref key, _) => Ok(ElementOp::MapCollection(
                 s.to_owned(),
                 k.as_ref().map(|s| s.to_owned()),
                 TryEvalFrom::try_eval_from(e, ctx)?,
+                TryEvalFrom::try_eval_from(key, ctx)?,
                 Default::default(),
             )),
 
@@ -458,7 +570,7 @@ impl TryEvalFrom<ElementOp<ProcessedExpression>> for ElementOp<OutputExpression>
                 Default::default(),
             )),
 
-            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, _) => {
+            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, ref key, _) => {
                 let comp_desc: ComponentInstanceDescriptor<OutputExpression> =
                     TryEvalFrom::try_eval_from(comp_desc, ctx)?;
                 let coll: ExpressionValue<OutputExpression> =
@@ -468,6 +580,7 @@ impl TryEvalFrom<ElementOp<ProcessedExpression>> for ElementOp<OutputExpression>
                     comp_desc,
                     item_key.to_owned(),
                     coll,
+                    TryEvalFrom::try_eval_from(key, ctx)?,
                     Default::default(),
                 ))
             } // _ => Ok(*src.to_owned())
@@ -4,6 +4,7 @@ use std::collections::HashSet;
 use error::*;
 use traits::*;
 use expressions::*;
+use ast::*;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Reducer<T>(
@@ -11,6 +12,7 @@ pub struct Reducer<T>(
     Option<Vec<ReducerAction<T>>>,
     Option<ExpressionValue<T>>,
     Option<OuterShape>,
+    Option<TypeAnnotation>,
 );
 
 impl<T> Reducer<T> {
@@ -20,7 +22,11 @@ impl<T> Reducer<T> {
         default_value: Option<ExpressionValue<T>>,
         shape: Option<OuterShape>,
     ) -> Self {
-        Reducer(name, actions, default_value, shape)
+        Reducer(name, actions, default_value, shape, None)
+    }
+
+    pub fn with_annotation(self, annotation: Option<TypeAnnotation>) -> Self {
+        Reducer(self.0, self.1, self.2, self.3, annotation)
     }
 
     pub fn key(&self) -> &str {
@@ -38,6 +44,11 @@ impl<T> Reducer<T> {
     pub fn shape(&self) -> Option<OuterShape> {
         self.3
     }
+
+    /// Declared type of the store value (`let todos: [Todo]`)
+    pub fn annotation(&self) -> Option<&TypeAnnotation> {
+        self.4.as_ref()
+    }
 }
 
 impl<I, O> TryProcessFrom<Reducer<I>> for Reducer<O>
@@ -62,16 +73,24 @@ where
         let default_value: Option<ExpressionValue<O>> =
             TryProcessFrom::try_process_from(&src.2, ctx)?;
 
-        Ok(Reducer(src.0.to_owned(), actions, default_value, src.3))
+        Ok(Reducer(src.0.to_owned(), actions, default_value, src.3, src.4.to_owned()))
     }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
-pub struct ReducerAction<T>(String, FormalParams<T>, Option<ExpressionValue<T>>);
+pub struct ReducerAction<T>(String, FormalParams<T>, Option<ExpressionValue<T>>, Option<String>, NodeSpan);
 
 impl<T> ReducerAction<T> {
     pub fn new(name: String, params: FormalParams<T>, expr: Option<ExpressionValue<T>>) -> Self {
-        ReducerAction(name, params, expr)
+        ReducerAction(name, params, expr, None, Default::default())
+    }
+
+    pub fn with_doc(self, doc: Option<String>) -> Self {
+        ReducerAction(self.0, self.1, self.2, doc, self.4)
+    }
+
+    pub fn with_span(self, span: NodeSpan) -> Self {
+        ReducerAction(self.0, self.1, self.2, self.3, span)
     }
 
     pub fn name(&self) -> &str {
@@ -82,9 +101,22 @@ impl<T> ReducerAction<T> {
         self.1.params()
     }
 
+    pub fn param_types<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a TypeAnnotation)> {
+        self.1.param_types()
+    }
+
     pub fn expr(&self) -> Option<&ExpressionValue<T>> {
         self.2.as_ref()
     }
+
+    /// Documentation from `///` comments preceding the action
+    pub fn doc(&self) -> Option<&str> {
+        self.3.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.4.span()
+    }
 }
 
 impl<T: Debug> MapIdents<T> for ReducerAction<T> {
@@ -107,7 +139,7 @@ impl<T: Debug> MapIdents<T> for ReducerAction<T> {
             _ => None,
         };
 
-        Ok(ReducerAction(self.0, self.1, expr))
+        Ok(ReducerAction(self.0, self.1, expr, self.3, self.4))
     }
 }
 
@@ -125,7 +157,7 @@ where
         let params: FormalParams<O> = TryProcessFrom::try_process_from(&src.1, ctx)?;
 
         let expr: Option<ExpressionValue<O>> = TryProcessFrom::try_process_from(&src.2, ctx)?;
-        let action = ReducerAction(src.0.to_owned(), params, expr);
+        let action = ReducerAction(src.0.to_owned(), params, expr, src.3.to_owned(), src.4.clone());
         eprintln!("TryProcess ReducerAction: action: {:?}", action);
 
         Ok(action)
@@ -15,23 +15,41 @@ fn function_key(name: &str) -> Cow<str> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Route<T>(String, FormalParams<T>, RouteActionValue<T>, PhantomData<T>);
+pub struct Route<T>(String, RoutePattern, FormalParams<T>, RouteActionValue<T>, PhantomData<T>);
 
 impl<T> Route<T> {
-    pub fn new(pattern: String, params: FormalParams<T>, action: RouteActionValue<T>) -> Self {
-        Route(pattern, params, action, Default::default())
+    pub fn new(
+        pattern: String,
+        route_pattern: RoutePattern,
+        params: FormalParams<T>,
+        action: RouteActionValue<T>,
+    ) -> Self {
+        Route(pattern, route_pattern, params, action, Default::default())
+    }
+
+    /// Replaces the action of this route with the processed content block
+    pub fn with_block(self, block: Block<T>) -> Self {
+        Route(self.0, self.1, self.2, RouteActionValue::Block(block, Default::default()), self.4)
     }
 
     pub fn pattern(&self) -> &str {
         &self.0
     }
 
+    pub fn route_pattern(&self) -> &RoutePattern {
+        &self.1
+    }
+
+    pub fn params<'a>(&'a self) -> Option<impl Iterator<Item = &'a str>> {
+        self.2.params()
+    }
+
     pub fn function_key(&self) -> Cow<str> {
         function_key(&self.0)
     }
 
     pub fn action(&self) -> &RouteActionValue<T> {
-        &self.2
+        &self.3
     }
 }
 
@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+use std::str;
+
+use error::*;
+
+/// Segment of a route pattern
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RouteSegment {
+    /// Literal segment (`todos`), matched exactly
+    Static(String),
+    /// Named parameter (`:id`), matching exactly one segment
+    Param(String),
+    /// Wildcard (`*rest`, or `*` when unnamed), matching the remaining segments (possibly none)
+    Wildcard(String),
+}
+
+impl RouteSegment {
+    /// Precedence of this segment when several patterns match the same path.
+    /// The end of a pattern ranks between a parameter and a wildcard, so that
+    /// `/products` is preferred over `/products/*rest`.
+    fn rank(&self) -> u8 {
+        match *self {
+            RouteSegment::Static(..) => 3,
+            RouteSegment::Param(..) => 2,
+            RouteSegment::Wildcard(..) => 0,
+        }
+    }
+}
+
+const END_RANK: u8 = 1;
+
+/// Parsed route pattern (`/`, `/todo/:id`, `/products/*rest`)
+///
+/// Paths and patterns are split on `/` with empty segments ignored, so leading,
+/// trailing and repeated slashes are not significant.  This algorithm is mirrored
+/// by `isymtope-app-router.js` and both must be kept in sync.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoutePattern(Vec<RouteSegment>);
+
+impl RoutePattern {
+    pub fn parse(pattern: &str) -> DocumentProcessingResult<Self> {
+        let parts: Vec<_> = split_path(pattern).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+
+        for (idx, part) in parts.iter().enumerate() {
+            let segment = match (part.chars().next(), part.get(1..).unwrap_or("")) {
+                (Some('*'), "") if idx + 1 == parts.len() => RouteSegment::Wildcard("".to_owned()),
+                (Some(':'), name) | (Some('*'), name) if !is_param_name(name) => {
+                    return Err(try_process_from_err!(format!(
+                        "Invalid parameter name [{}] in route [{}]",
+                        name, pattern
+                    )));
+                }
+                (Some(':'), name) => RouteSegment::Param(name.to_owned()),
+                (Some('*'), _) if idx + 1 < parts.len() => {
+                    return Err(try_process_from_err!(format!(
+                        "Wildcard [{}] must be the last segment in route [{}]",
+                        part, pattern
+                    )));
+                }
+                (Some('*'), name) => RouteSegment::Wildcard(name.to_owned()),
+                _ => RouteSegment::Static(part.to_string()),
+            };
+
+            if let Some(name) = segment_name(&segment) {
+                if segments.iter().any(|s| segment_name(s) == Some(name)) {
+                    return Err(try_process_from_err!(format!(
+                        "Duplicate parameter [{}] in route [{}]",
+                        name, pattern
+                    )));
+                }
+            }
+
+            segments.push(segment);
+        }
+
+        Ok(RoutePattern(segments))
+    }
+
+    pub fn segments<'a>(&'a self) -> impl Iterator<Item = &'a RouteSegment> {
+        self.0.iter()
+    }
+
+    /// Names of the parameters and wildcard in this pattern
+    pub fn param_names<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        self.0.iter().filter_map(segment_name)
+    }
+
+    /// Matches a path against this pattern, returning the bound parameters.
+    /// Parameter values are percent-decoded; a wildcard binds the remaining
+    /// segments joined by `/`.
+    pub fn match_path(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let parts: Vec<_> = split_path(strip_query(path)).collect();
+        let mut params = Vec::new();
+
+        for (idx, segment) in self.0.iter().enumerate() {
+            match *segment {
+                RouteSegment::Wildcard(ref name) => {
+                    let rest: Vec<_> = parts[idx.min(parts.len())..]
+                        .iter()
+                        .map(|s| decode_segment(s))
+                        .collect();
+                    if !name.is_empty() {
+                        params.push((name.to_owned(), rest.join("/")));
+                    };
+                    return Some(params);
+                }
+
+                _ if idx >= parts.len() => return None,
+
+                RouteSegment::Static(ref s) if s == parts[idx] => {}
+                RouteSegment::Static(..) => return None,
+
+                RouteSegment::Param(ref name) => {
+                    params.push((name.to_owned(), decode_segment(parts[idx])));
+                }
+            };
+        }
+
+        if parts.len() == self.0.len() {
+            Some(params)
+        } else {
+            None
+        }
+    }
+
+    /// Path matched by this pattern, if it has no parameters or wildcard
+    pub fn static_path(&self) -> Option<String> {
+        let mut path = String::new();
+        for segment in &self.0 {
+            match *segment {
+                RouteSegment::Static(ref s) => {
+                    path.push('/');
+                    path.push_str(s);
+                }
+                _ => return None,
+            };
+        }
+
+        if path.is_empty() {
+            path.push('/');
+        };
+        Some(path)
+    }
+
+    /// Whether this is the fallback route (`route fallback`, or `route "*"`), which
+    /// matches any path not matched by another route and is rendered as not found.
+    pub fn is_fallback(&self) -> bool {
+        match self.0.as_slice() {
+            &[RouteSegment::Wildcard(ref name)] => name.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Compares the precedence of two patterns: static segments win over
+    /// parameters, which win over wildcards, comparing from the left.
+    pub fn cmp_precedence(&self, other: &RoutePattern) -> Ordering {
+        let ranks = |p: &RoutePattern| -> Vec<u8> {
+            p.0.iter().map(|s| s.rank()).chain(Some(END_RANK)).collect()
+        };
+
+        ranks(self).cmp(&ranks(other))
+    }
+}
+
+/// Route matched for a path, with the values bound to its parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteMatch(String, Vec<(String, String)>, bool);
+
+impl RouteMatch {
+    pub fn new(pattern: String, params: Vec<(String, String)>, is_fallback: bool) -> Self {
+        RouteMatch(pattern, params, is_fallback)
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.0
+    }
+
+    pub fn params<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.1.iter().map(|&(ref k, ref v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        self.2
+    }
+}
+
+/// Finds the pattern with the highest precedence matching `path`, returning its
+/// index and bound parameters.  Ties are resolved in declaration order.
+pub fn match_route_patterns<'a, I>(patterns: I, path: &str) -> Option<(usize, Vec<(String, String)>)>
+where
+    I: IntoIterator<Item = &'a RoutePattern>,
+{
+    let mut best: Option<(usize, &RoutePattern, Vec<(String, String)>)> = None;
+
+    for (idx, pattern) in patterns.into_iter().enumerate() {
+        if let Some(params) = pattern.match_path(path) {
+            let is_better = match best {
+                Some((_, ref cur, _)) => pattern.cmp_precedence(cur) == Ordering::Greater,
+                None => true,
+            };
+
+            if is_better {
+                best = Some((idx, pattern, params));
+            };
+        };
+    }
+
+    best.map(|(idx, _, params)| (idx, params))
+}
+
+fn segment_name(segment: &RouteSegment) -> Option<&str> {
+    match *segment {
+        RouteSegment::Param(ref s) | RouteSegment::Wildcard(ref s) if !s.is_empty() => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+fn is_param_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn strip_query(path: &str) -> &str {
+    path.split(|c| c == '?' || c == '#').next().unwrap_or("")
+}
+
+fn split_path<'a>(path: &'a str) -> impl Iterator<Item = &'a str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+/// Percent-decodes a path segment, returning it unchanged if it is not valid
+/// (as with `decodeURIComponent` failing in the browser).
+fn decode_segment(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = s.get(idx + 1..idx + 3)
+                .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            match hex {
+                Some(b) => decoded.push(b),
+                None => return s.to_owned(),
+            };
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+
+    match str::from_utf8(&decoded) {
+        Ok(decoded) => decoded.to_owned(),
+        Err(_) => s.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(v: &[(&str, &str)]) -> Vec<(String, String)> {
+        v.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect()
+    }
+
+    #[test]
+    fn test_route_pattern_match() {
+        let root = RoutePattern::parse("/").unwrap();
+        let todo = RoutePattern::parse("/todo/:id").unwrap();
+        let rest = RoutePattern::parse("/products/*rest").unwrap();
+
+        assert_eq!(root.match_path(""), Some(vec![]));
+        assert_eq!(root.match_path("/"), Some(vec![]));
+        assert_eq!(todo.match_path("/todo/4/"), Some(params(&[("id", "4")])));
+        assert_eq!(todo.match_path("/todo/a%20b?x=1"), Some(params(&[("id", "a b")])));
+        assert_eq!(todo.match_path("/todo"), None);
+        assert_eq!(todo.match_path("/todo/4/edit"), None);
+        assert_eq!(rest.match_path("/products"), Some(params(&[("rest", "")])));
+        assert_eq!(rest.match_path("/products/a/b"), Some(params(&[("rest", "a/b")])));
+
+        assert_eq!(RoutePattern::parse("*").unwrap().match_path("/a/b"), Some(vec![]));
+        assert!(RoutePattern::parse("*").unwrap().is_fallback());
+        assert_eq!(root.static_path(), Some("/".to_owned()));
+        assert_eq!(RoutePattern::parse("/a//b/").unwrap().static_path(), Some("/a/b".to_owned()));
+        assert_eq!(todo.static_path(), None);
+        assert!(!rest.is_fallback());
+        assert!(RoutePattern::parse("/a/*rest/b").is_err());
+        assert!(RoutePattern::parse("/a/:").is_err());
+        assert!(RoutePattern::parse("/a/:id/:id").is_err());
+    }
+
+    #[test]
+    fn test_route_pattern_precedence() {
+        let patterns: Vec<_> = ["/products/*rest", "/products/:id", "/products/new", "/products", "/*all"]
+            .iter()
+            .map(|p| RoutePattern::parse(p).unwrap())
+            .collect();
+
+        let matched = |path: &str| match_route_patterns(&patterns, path).map(|(idx, _)| idx);
+        assert_eq!(matched("/products/new"), Some(2));
+        assert_eq!(matched("/products/4"), Some(1));
+        assert_eq!(matched("/products/4/reviews"), Some(0));
+        assert_eq!(matched("/products"), Some(3));
+        assert_eq!(matched("/"), Some(4));
+    }
+}
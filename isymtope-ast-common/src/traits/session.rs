@@ -12,6 +12,11 @@ pub trait Sessions {
     fn create(&mut self, session_id: &str, expires: Option<Duration>) -> SessionResult<()>;
     fn validate(&mut self, session_id: &str) -> SessionResult<()>;
     fn destroy(&mut self, session_id: &str) -> SessionResult<()>;
+    #[cfg(feature = "session_time")]
+    fn remove_expired(&mut self, now: &Timespec) -> SessionResult<()>;
+    /// Writes the state of a session to durable storage after it has changed, for
+    /// backends which keep sessions between restarts.
+    fn persist(&mut self, session_id: &str) -> SessionResult<()>;
     fn execute_action(
         &mut self,
         session_id: &str,
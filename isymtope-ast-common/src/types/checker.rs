@@ -0,0 +1,690 @@
+//! Static checking of a document against the template it was processed from.
+//!
+//! Store values are typed from their annotations or `let` initializers, and the
+//! params of each action and component from their annotations or the values
+//! passed to them. Each use is then checked against these types, so that mistakes
+//! are reported with their location in the template before any output is generated.
+
+use std::collections::HashMap;
+
+use linked_hash_map::LinkedHashMap;
+
+use error::*;
+use expressions::*;
+use objects::*;
+use ast::*;
+use types::*;
+
+/// Pipeline methods which operate on collections
+const COLLECTION_METHODS: &[&str] = &["map", "filter", "reduce", "min", "max", "count", "first"];
+
+/// Checks the types of values used within a document, returning every error found.
+pub fn check_document(ast: &Template, doc: &Document) -> Result<(), DocumentTypeError> {
+    let mut checker = TypeChecker::new(doc);
+    checker.resolve_declared();
+    checker.infer_store(ast);
+
+    // Values flow between components through their props, so collect the
+    // types passed to each until they no longer change.
+    for _ in 0..doc.components().count() + 2 {
+        checker.changed = false;
+        checker.walk_template(ast);
+
+        if !checker.changed {
+            break;
+        };
+    }
+
+    // Walk again, reporting errors against the types collected
+    checker.errors = Some(Default::default());
+    checker.infer_store(ast);
+    checker.walk_template(ast);
+    checker.check_actions(ast);
+
+    let mut errors = checker.errors.take().unwrap_or_default();
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(DocumentTypeError::Multiple(errors)),
+    }
+}
+
+#[derive(Debug)]
+struct TypeChecker<'doc> {
+    doc: &'doc Document,
+    store: LinkedHashMap<String, VarType>,
+    action_params: HashMap<String, LinkedHashMap<String, VarType>>,
+    component_props: HashMap<String, LinkedHashMap<String, VarType>>,
+    declared_action_params: HashMap<String, LinkedHashMap<String, VarType>>,
+    declared_component_props: HashMap<String, LinkedHashMap<String, VarType>>,
+    scopes: Vec<HashMap<String, VarType>>,
+    items: Vec<VarType>,
+    state: Option<VarType>,
+    /// Errors found, once types have been collected from the whole template
+    errors: Option<Vec<DocumentTypeError>>,
+    changed: bool,
+}
+
+impl<'doc> TypeChecker<'doc> {
+    fn new(doc: &'doc Document) -> Self {
+        TypeChecker {
+            doc: doc,
+            store: Default::default(),
+            action_params: Default::default(),
+            component_props: Default::default(),
+            declared_action_params: Default::default(),
+            declared_component_props: Default::default(),
+            scopes: Default::default(),
+            items: Default::default(),
+            state: None,
+            errors: None,
+            changed: false,
+        }
+    }
+
+    fn report(&mut self, err: DocumentTypeError) {
+        if let Some(ref mut errors) = self.errors {
+            errors.push(err);
+        };
+    }
+
+    fn push_scope<'a, I: IntoIterator<Item = (&'a str, VarType)>>(&mut self, bindings: I) {
+        let scope = bindings
+            .into_iter()
+            .map(|(k, ty)| (k.to_owned(), ty))
+            .collect();
+        self.scopes.push(scope);
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn lookup(&self, ident: &str) -> VarType {
+        self.scopes
+            .iter()
+            .rev()
+            .filter_map(|scope| scope.get(ident))
+            .nth(0)
+            .or_else(|| self.store.get(ident))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Resolves a type annotation, which was checked to name only declared types when processing the document.
+    fn resolve(&self, annotation: &TypeAnnotation) -> VarType {
+        self.doc.resolve_type(annotation).ok().unwrap_or_default()
+    }
+
+    /// Types of annotated action params and component props
+    fn resolve_declared(&mut self) {
+        let doc = self.doc;
+
+        for (reducer_key, reducer) in doc.reducers().into_iter().flat_map(|v| v) {
+            for action in reducer.actions().into_iter().flat_map(|v| v) {
+                let params: LinkedHashMap<_, _> = action.param_types()
+                    .map(|(name, annotation)| (name.to_owned(), self.resolve(annotation)))
+                    .collect();
+                let complete_key = format!("{}.{}", reducer_key, action.name());
+                self.declared_action_params.insert(complete_key, params);
+            }
+        }
+
+        for component in doc.components() {
+            let props: LinkedHashMap<_, _> = component.param_types()
+                .map(|(name, annotation)| (name.to_owned(), self.resolve(annotation)))
+                .collect();
+            self.declared_component_props.insert(component.name().to_owned(), props);
+        }
+    }
+
+    /// Checks a value passed to an annotated param against its declared type.
+    fn check_declared(&mut self, declared: &VarType, ty: &VarType, desc: String, span: Option<SourceSpan>) {
+        if !declared.is_compatible(ty) {
+            self.report(DocumentTypeError::MismatchDeclaredType(desc, declared.to_owned(), ty.to_owned(), span));
+        };
+    }
+
+    /// Type of a param, from its annotation or the values previously passed to it
+    fn param_ty(
+        declared: &HashMap<String, LinkedHashMap<String, VarType>>,
+        passed: &HashMap<String, LinkedHashMap<String, VarType>>,
+        key: &str,
+        name: &str,
+    ) -> VarType {
+        declared.get(key).and_then(|params| params.get(name))
+            .or_else(|| passed.get(key).and_then(|params| params.get(name)))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Store values, typed from their annotations or initial values
+    fn infer_store(&mut self, ast: &Template) {
+        let lets = ast.children()
+            .filter_map(|n| match *n {
+                TemplateNode::StoreDefinition(ref s, _) => s.children(),
+                _ => None,
+            })
+            .flat_map(|v| v)
+            .filter_map(|n| match *n {
+                StoreRootScopeNode::Common(StoreCommonNode::LetNode(ref key, ref e, ref annotation, _), _) => Some((key, e, annotation)),
+                _ => None,
+            });
+
+        for (key, e, annotation) in lets {
+            let ty = e.as_ref().map(|e| self.infer(e)).unwrap_or_default();
+
+            let ty = match *annotation {
+                Some(ref annotation) => {
+                    let declared = self.resolve(annotation);
+                    let desc = format!("store value ({})", key);
+                    self.check_declared(&declared, &ty, desc, annotation.span().cloned());
+                    declared
+                }
+                None => ty,
+            };
+
+            self.store.insert(key.to_owned(), ty);
+        }
+    }
+
+    /// Merges the type of a value passed as `name` into the types previously passed,
+    /// returning the previous type if they are not compatible.
+    fn merge_param(
+        changed: &mut bool,
+        params: &mut LinkedHashMap<String, VarType>,
+        name: &str,
+        ty: VarType,
+    ) -> Option<VarType> {
+        let existing = params.get(name).cloned();
+
+        match existing {
+            None => {
+                params.insert(name.to_owned(), ty);
+                *changed = true;
+                None
+            }
+
+            Some(existing) => match existing.join(&ty) {
+                Some(ref joined) if *joined == existing => None,
+                Some(joined) => {
+                    params.insert(name.to_owned(), joined);
+                    *changed = true;
+                    None
+                }
+                None => Some(existing),
+            },
+        }
+    }
+
+    fn walk_template(&mut self, ast: &Template) {
+        for node in ast.children() {
+            match *node {
+                TemplateNode::ComponentDefinition(ref c, _) => {
+                    let params: Vec<_> = c.params()
+                        .map(|v| v.map(|p| (p, TypeChecker::param_ty(&self.declared_component_props, &self.component_props, c.name(), p))).collect())
+                        .unwrap_or_default();
+
+                    self.push_scope(params);
+                    if let Some(children) = c.children() {
+                        self.walk_content(children);
+                    };
+                    self.pop_scope();
+                }
+
+                TemplateNode::RouteDefinition(ref r, _) => {
+                    // Route params are bound to segments of the path
+                    let params: Vec<_> = r.params()
+                        .map(|v| v.map(|p| (p, VarType::String)).collect())
+                        .unwrap_or_default();
+
+                    self.push_scope(params);
+                    match *r.action() {
+                        RouteAction::Content(Some(ref children), _) => self.walk_content(children),
+                        RouteAction::Actions(Some(ref ops), _) => self.walk_action_ops(ops),
+                        _ => {}
+                    };
+                    self.pop_scope();
+                }
+
+                TemplateNode::QueryDefinition(ref q, _) => {
+                    let params: Vec<_> = q.params()
+                        .map(|v| v.map(|p| (p, VarType::Unknown)).collect())
+                        .unwrap_or_default();
+
+                    self.push_scope(params);
+                    for component in q.components() {
+                        let QueryComponent::CaseWhere(box ref e, box ref cond, _) = *component;
+                        self.infer(e);
+                        self.infer(cond);
+                    }
+                    self.pop_scope();
+                }
+
+                TemplateNode::Content(ref n, _) => self.walk_content_node(n),
+
+                _ => {}
+            };
+        }
+    }
+
+    fn walk_content<'a, I: IntoIterator<Item = &'a ContentNode<SourceExpression>>>(&mut self, nodes: I) {
+        for node in nodes {
+            self.walk_content_node(node);
+        }
+    }
+
+    fn walk_content_node(&mut self, node: &ContentNode<SourceExpression>) {
+        match *node {
+            ContentNode::Element(ref e, _) => self.walk_element(e),
+
+            ContentNode::Extern(ref e, _) => {
+                if let Some(children) = e.children() {
+                    self.walk_content(children);
+                };
+            }
+
+            ContentNode::ForNode(ref item_alias, ref index_alias, box ref coll, ref key, ref children, _) => {
+                let item_ty = self.infer(coll).item_ty();
+
+                let mut bindings: Vec<(&str, VarType)> = Default::default();
+                if let Some(ref item_alias) = *item_alias {
+                    bindings.push((item_alias, item_ty.to_owned()));
+                };
+                if let Some(ref index_alias) = *index_alias {
+                    bindings.push((index_alias, VarType::Int));
+                };
+
+                self.push_scope(bindings);
+                self.items.push(item_ty);
+                if let Some(box ref key) = *key {
+                    self.infer(key);
+                };
+                if let Some(box ref children) = *children {
+                    self.walk_content(children);
+                };
+                self.items.pop();
+                self.pop_scope();
+            }
+
+            ContentNode::IfNode(box ref cond, box ref children, ref else_children, _) => {
+                self.infer(cond);
+                self.walk_content(children);
+                if let Some(box ref else_children) = *else_children {
+                    self.walk_content(else_children);
+                };
+            }
+
+            ContentNode::Slot(_, Some(box ref children), _) => self.walk_content(children),
+
+            ContentNode::ExpressionValue(box ref e, ..) | ContentNode::RawValue(box ref e, ..) => {
+                self.infer(e);
+            }
+
+            _ => {}
+        };
+    }
+
+    fn walk_element(&mut self, element: &ElementNode<SourceExpression>) {
+        let attrs: Vec<_> = element.attrs().map(|v| v.collect()).unwrap_or_default();
+
+        if self.doc.component(element.tag()).is_some() {
+            self.walk_component_call(element.tag(), &attrs);
+        } else {
+            for attr in attrs {
+                match *attr {
+                    ElementAttrValue::Prop(ref p) => self.infer(p.expr()),
+                    ElementAttrValue::Positional(ref e) => self.infer(e),
+                };
+            }
+        };
+
+        if let Some(bindings) = element.bindings() {
+            for binding in bindings {
+                if let ElementBinding::Event(ref event, _) = *binding {
+                    let params: Vec<_> = event.params()
+                        .map(|v| v.map(|p| (p, VarType::Unknown)).collect())
+                        .unwrap_or_default();
+
+                    self.push_scope(params);
+                    if let Some(ops) = event.actions() {
+                        let ops: Vec<_> = ops.cloned().collect();
+                        self.walk_action_ops(&ops);
+                    };
+                    self.pop_scope();
+                };
+            }
+        };
+
+        if let Some(children) = element.children() {
+            self.walk_content(children);
+        };
+    }
+
+    /// Checks the props passed to a component, and merges their types into those previously passed.
+    fn walk_component_call(&mut self, tag: &str, attrs: &[&ElementAttrValue<SourceExpression>]) {
+        let params: Vec<String> = self.doc
+            .component(tag)
+            .and_then(|c| c.params().map(|v| v.map(|s| s.to_owned()).collect()))
+            .unwrap_or_default();
+
+        let mut props: Vec<(String, VarType, Option<SourceSpan>)> = Default::default();
+        for attr in attrs {
+            match **attr {
+                ElementAttrValue::Prop(ref p) => {
+                    let ty = self.infer(p.expr());
+                    props.push((p.name().to_owned(), ty, p.span().cloned()));
+                }
+
+                ElementAttrValue::Positional(ExpressionValue::SourceLens(SourceLensValue::ForLens(ref alias, box ref coll, _, _), _)) => {
+                    let item_ty = self.infer(coll).item_ty();
+                    if let Some(ref alias) = *alias {
+                        props.push((alias.to_owned(), item_ty, None));
+                    };
+                }
+
+                ElementAttrValue::Positional(ExpressionValue::SourceLens(ref lens, _)) => {
+                    let ty = match *lens {
+                        SourceLensValue::GetLens(_, box ref e, _) => self.infer(e),
+                        _ => VarType::Unknown,
+                    };
+                    if let Ok(alias) = lens.default_alias() {
+                        props.push((alias, ty, None));
+                    };
+                }
+
+                ElementAttrValue::Positional(ref e) => {
+                    self.infer(e);
+                }
+            };
+        }
+
+        for (name, ty, span) in props {
+            if !params.contains(&name) {
+                self.report(DocumentTypeError::UnknownComponentProp(tag.to_owned(), name, span));
+                continue;
+            };
+
+            let declared = self.declared_component_props.get(tag).and_then(|props| props.get(&name)).cloned();
+            if let Some(declared) = declared {
+                let desc = format!("component ({}) prop ({})", tag, name);
+                self.check_declared(&declared, &ty, desc, span);
+                continue;
+            };
+
+            let existing = {
+                let changed = &mut self.changed;
+                let component_props = self.component_props.entry(tag.to_owned()).or_insert_with(Default::default);
+                TypeChecker::merge_param(changed, component_props, &name, ty.to_owned())
+            };
+
+            if let Some(existing) = existing {
+                self.report(DocumentTypeError::MismatchComponentProp(tag.to_owned(), name, existing, ty, span));
+            };
+        }
+    }
+
+    /// Finds the reducer handling an action, which must be unique if no target is given.
+    fn resolve_action(&self, action: &str, target: Option<&str>) -> Option<(String, Option<Vec<String>>)> {
+        let reducers = self.doc.reducers().map(|v| v.collect::<Vec<_>>()).unwrap_or_default();
+
+        let mut candidates = reducers
+            .into_iter()
+            .filter(|&(key, _)| target.map_or(true, |target| target == key))
+            .filter_map(|(key, reducer)| {
+                reducer.actions()
+                    .and_then(|mut actions| actions.find(|a| a.name() == action))
+                    .map(|a| (key.to_owned(), a.params().map(|v| v.map(|s| s.to_owned()).collect())))
+            });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(found), None) => Some(found),
+            _ => None,
+        }
+    }
+
+    fn walk_action_ops(&mut self, ops: &[ActionOp<SourceExpression>]) {
+        for op in ops {
+            match *op {
+                ActionOp::DispatchAction(ref action, ref props, _) => {
+                    self.walk_dispatch(action, None, props.as_ref().map(|v| v.as_slice()));
+                }
+
+                ActionOp::DispatchActionTo(ref action, ref props, ref target, _) => {
+                    self.walk_dispatch(action, Some(target), props.as_ref().map(|v| v.as_slice()));
+                }
+
+                ActionOp::Navigate(ref e, _) => {
+                    self.infer(e);
+                }
+            };
+        }
+    }
+
+    /// Checks the params passed to an action, and merges their types into those previously passed.
+    fn walk_dispatch(&mut self, action: &str, target: Option<&str>, props: Option<&[PropValue<SourceExpression>]>) {
+        let props: Vec<_> = props
+            .map(|v| v.iter().map(|p| (p, self.infer(p.value()))).collect())
+            .unwrap_or_default();
+
+        // Actions of extern reducers are not known
+        let (reducer_key, params) = match self.resolve_action(action, target) {
+            Some(resolved) => resolved,
+            None => return,
+        };
+        let complete_key = format!("{}.{}", reducer_key, action);
+        let params = params.unwrap_or_default();
+
+        for (prop, ty) in props {
+            if !params.iter().any(|p| p == prop.key()) {
+                self.report(DocumentTypeError::UnknownActionParam(complete_key.to_owned(), prop.key().to_owned(), prop.span().cloned()));
+                continue;
+            };
+
+            let declared = self.declared_action_params.get(&complete_key).and_then(|params| params.get(prop.key())).cloned();
+            if let Some(declared) = declared {
+                let desc = format!("action ({}) param ({})", complete_key, prop.key());
+                self.check_declared(&declared, &ty, desc, prop.span().cloned());
+                continue;
+            };
+
+            let existing = {
+                let changed = &mut self.changed;
+                let action_params = self.action_params.entry(complete_key.to_owned()).or_insert_with(Default::default);
+                TypeChecker::merge_param(changed, action_params, prop.key(), ty.to_owned())
+            };
+
+            if let Some(existing) = existing {
+                let err = DocumentTypeError::mismatch_action_param(&complete_key, prop.key(), &existing, &ty, prop.span());
+                self.report(err);
+            };
+        }
+    }
+
+    /// Checks that each action results in a value of the same shape as its store value.
+    fn check_actions(&mut self, ast: &Template) {
+        let scopes = ast.children()
+            .filter_map(|n| match *n {
+                TemplateNode::StoreDefinition(ref s, _) => s.children(),
+                _ => None,
+            })
+            .flat_map(|v| v)
+            .filter_map(|n| match *n {
+                StoreRootScopeNode::Common(StoreCommonNode::ChildScopeNode(ref key, Some(ref children)), _) => Some((key, children)),
+                _ => None,
+            });
+
+        for (reducer_key, children) in scopes {
+            let store_ty = self.store.get(reducer_key).cloned().unwrap_or_default();
+
+            let actions = children.iter().filter_map(|n| match *n {
+                StoreChildScopeNode::Action(ref a, _) => Some(a),
+                _ => None,
+            });
+
+            for action in actions {
+                let complete_key = format!("{}.{}", reducer_key, action.name());
+                let params: Vec<_> = action.params()
+                    .map(|v| v.map(|p| (p, TypeChecker::param_ty(&self.declared_action_params, &self.action_params, &complete_key, p))).collect())
+                    .unwrap_or_default();
+
+                self.push_scope(params);
+                self.state = Some(store_ty.to_owned());
+                let ty = action.expr().map(|e| self.infer(e)).unwrap_or_default();
+                self.state = None;
+                self.pop_scope();
+
+                match (store_ty.shape(), ty.shape()) {
+                    (Some(a), Some(b)) if a != b => {
+                        let err = DocumentTypeError::MismatchActionResult(complete_key, store_ty.to_owned(), ty, action.span().cloned());
+                        self.report(err);
+                    }
+                    _ => {}
+                };
+            }
+        }
+    }
+
+    /// Infers the type of an expression, checking any pipelines within it.
+    fn infer(&mut self, e: &ExpressionValue<SourceExpression>) -> VarType {
+        match *e {
+            ExpressionValue::Primitive(ref p) => VarType::of_primitive(p),
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(ref a))) => {
+                let items: Vec<_> = a.0.iter().flat_map(|v| v.iter()).map(|p| self.infer(p.value())).collect();
+                VarType::Array(Box::new(join_all(items)))
+            }
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ObjectValue(ref o))) => {
+                object_ty(o, |e| self.infer(e))
+            }
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::MapValue(ref m))) => {
+                let entries: Vec<_> = m.1.iter().flat_map(|v| v.iter()).map(|o| object_ty(o, |e| self.infer(e))).collect();
+                VarType::Map(Box::new(join_all(entries)))
+            }
+
+            ExpressionValue::Expression(Expression::Ident(ref s, _)) => self.lookup(s),
+
+            ExpressionValue::Expression(Expression::Path(ref p, _)) => {
+                let head = self.infer(p.head());
+                p.components()
+                    .map(|v| v.fold(head.to_owned(), |ty, member| ty.member_ty(member)))
+                    .unwrap_or(head)
+            }
+
+            ExpressionValue::Expression(Expression::Pipeline(ref p, _)) => self.infer_pipeline(p),
+
+            ExpressionValue::Expression(Expression::Group(Some(box ref e))) => self.infer(e),
+
+            ExpressionValue::Expression(Expression::UnaryOp(ref op, box ref a)) => {
+                let a = self.infer(a);
+                match *op {
+                    UnaryOpType::Negate => VarType::Bool,
+                    UnaryOpType::Minus if a.is_numeric() => a,
+                    UnaryOpType::Minus => VarType::Unknown,
+                }
+            }
+
+            ExpressionValue::Expression(Expression::BinaryOp(ref op, box ref a, box ref b)) => {
+                let a = self.infer(a);
+                let b = self.infer(b);
+                binary_op_ty(op, &a, &b)
+            }
+
+            ExpressionValue::Expression(Expression::ApplyOp(ApplyOpType::JoinString(_), box ref a)) => {
+                self.infer(a);
+                VarType::String
+            }
+
+            ExpressionValue::Binding(CommonBindings::CurrentReducerState(_), _) => {
+                self.state.to_owned().unwrap_or_default()
+            }
+            ExpressionValue::Binding(CommonBindings::CurrentItem(_), _) => {
+                self.items.last().cloned().unwrap_or_default()
+            }
+            ExpressionValue::Binding(CommonBindings::CurrentItemIndex, _) => VarType::Int,
+
+            ExpressionValue::SourceLens(SourceLensValue::GetLens(_, box ref e, _), _) => self.infer(e),
+            ExpressionValue::SourceLens(SourceLensValue::ForLens(_, box ref e, _, _), _) => {
+                self.infer(e).item_ty()
+            }
+
+            _ => VarType::Unknown,
+        }
+    }
+
+    fn infer_pipeline(&mut self, p: &PipelineValue<SourceExpression>) -> VarType {
+        let mut ty = self.infer(p.head());
+
+        for component in p.components() {
+            ty = match *component {
+                PipelineComponentValue::Member(ref s) => ty.member_ty(s),
+
+                PipelineComponentValue::MethodCall(ref mth, ref params, _) => {
+                    let is_collection_method = COLLECTION_METHODS.contains(&mth.as_str());
+                    if is_collection_method && ty.shape() == Some(OuterShape::Singleton) {
+                        self.report(DocumentTypeError::MismatchMethodShape(mth.to_owned(), ty.to_owned(), p.span().cloned()));
+                    };
+
+                    let item_ty = ty.item_ty();
+                    self.items.push(item_ty.to_owned());
+                    let param_tys: Vec<_> = params
+                        .iter()
+                        .flat_map(|v| v.iter())
+                        .map(|param| self.infer(param.value()))
+                        .collect();
+                    self.items.pop();
+
+                    match mth.as_str() {
+                        "map" => VarType::Array(Box::new(param_tys.get(0).cloned().unwrap_or_default())),
+                        "filter" => ty,
+                        "reduce" => param_tys.get(1).cloned().unwrap_or_default(),
+                        "min" | "max" | "first" => item_ty,
+                        "count" => VarType::Int,
+                        _ => VarType::Unknown,
+                    }
+                }
+            };
+        }
+
+        ty
+    }
+}
+
+/// Result of a binary operation, following the client's semantics for `+`
+/// on strings, arrays and objects.
+fn binary_op_ty(op: &BinaryOpType, a: &VarType, b: &VarType) -> VarType {
+    match *op {
+        BinaryOpType::Add => match (a, b) {
+            (&VarType::Array(_), &VarType::Array(_)) => a.join(b).unwrap_or_default(),
+            (&VarType::Array(box ref item), other) | (other, &VarType::Array(box ref item)) => {
+                VarType::Array(Box::new(item.join(other).unwrap_or_default()))
+            }
+            (&VarType::String, _) | (_, &VarType::String) => VarType::String,
+            (&VarType::Object(_), &VarType::Object(_)) => a.join(b).unwrap_or_default(),
+            _ => numeric_ty(a, b),
+        },
+
+        BinaryOpType::Sub | BinaryOpType::Mul | BinaryOpType::Mod => numeric_ty(a, b),
+
+        // Division isn't truncated, so an int divided by an int may be a float
+        BinaryOpType::Div => match numeric_ty(a, b) {
+            VarType::Unknown => VarType::Unknown,
+            _ => VarType::Float,
+        },
+
+        // `&&` and `||` give one of their operands, as in the client
+        BinaryOpType::And | BinaryOpType::Or => a.join(b).unwrap_or_default(),
+
+        _ => VarType::Bool,
+    }
+}
+
+fn numeric_ty(a: &VarType, b: &VarType) -> VarType {
+    match (a, b) {
+        (&VarType::Int, &VarType::Int) => VarType::Int,
+        (a, b) if a.is_numeric() && b.is_numeric() => VarType::Float,
+        _ => VarType::Unknown,
+    }
+}
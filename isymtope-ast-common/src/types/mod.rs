@@ -0,0 +1,7 @@
+pub mod var_type;
+#[cfg(feature = "types")]
+pub mod checker;
+
+pub use self::var_type::*;
+#[cfg(feature = "types")]
+pub use self::checker::*;
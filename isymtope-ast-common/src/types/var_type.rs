@@ -0,0 +1,214 @@
+use std::fmt;
+
+use expressions::*;
+
+/// Type of a value in a template, as inferred from literals and the values
+/// passed to actions and components.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VarType {
+    Unknown,
+    Null,
+    Bool,
+    Int,
+    Float,
+    String,
+    Array(Box<VarType>),
+    /// Record, with fields in the order they were first seen
+    Object(Vec<(String, VarType)>),
+    Map(Box<VarType>),
+}
+
+impl Default for VarType {
+    fn default() -> Self {
+        VarType::Unknown
+    }
+}
+
+impl VarType {
+    pub fn of_primitive(p: &Primitive) -> Self {
+        match *p {
+            Primitive::CharVal(_) | Primitive::StringVal(_) => VarType::String,
+            Primitive::Int32Val(_) => VarType::Int,
+            Primitive::FloatVal(_) => VarType::Float,
+            Primitive::BoolVal(_) => VarType::Bool,
+            Primitive::NullVal => VarType::Null,
+            Primitive::Undefined => VarType::Unknown,
+        }
+    }
+
+    /// Whether anything is known about values of this type; `null` may be replaced by any value.
+    pub fn is_known(&self) -> bool {
+        match *self {
+            VarType::Unknown | VarType::Null => false,
+            _ => true,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        match *self {
+            VarType::Int | VarType::Float => true,
+            _ => false,
+        }
+    }
+
+    /// Outer shape of values of this type, if known.
+    pub fn shape(&self) -> Option<OuterShape> {
+        match *self {
+            VarType::Unknown | VarType::Null => None,
+            VarType::Array(_) => Some(OuterShape::Array),
+            VarType::Object(_) => Some(OuterShape::Object),
+            VarType::Map(_) => Some(OuterShape::Map),
+            _ => Some(OuterShape::Singleton),
+        }
+    }
+
+    /// Type of the items of a collection.
+    pub fn item_ty(&self) -> VarType {
+        match *self {
+            VarType::Array(box ref ty) | VarType::Map(box ref ty) => ty.to_owned(),
+            _ => VarType::Unknown,
+        }
+    }
+
+    /// Type of a member of a record.
+    pub fn member_ty(&self, name: &str) -> VarType {
+        match *self {
+            VarType::Object(ref fields) => fields
+                .iter()
+                .find(|f| f.0 == name)
+                .map(|f| f.1.to_owned())
+                .unwrap_or_default(),
+            _ => VarType::Unknown,
+        }
+    }
+
+    /// Most specific type that values of both types have, if they are compatible.
+    pub fn join(&self, other: &VarType) -> Option<VarType> {
+        match (self, other) {
+            (&VarType::Unknown, ty) | (ty, &VarType::Unknown) => Some(ty.to_owned()),
+            (&VarType::Null, ty) | (ty, &VarType::Null) => Some(ty.to_owned()),
+
+            (&VarType::Int, &VarType::Float) | (&VarType::Float, &VarType::Int) => {
+                Some(VarType::Float)
+            }
+
+            (&VarType::Array(box ref a), &VarType::Array(box ref b)) => {
+                a.join(b).map(|ty| VarType::Array(Box::new(ty)))
+            }
+
+            (&VarType::Map(box ref a), &VarType::Map(box ref b)) => {
+                a.join(b).map(|ty| VarType::Map(Box::new(ty)))
+            }
+
+            (&VarType::Object(ref a), &VarType::Object(ref b)) => {
+                let mut fields = a.to_owned();
+                for &(ref name, ref ty) in b {
+                    match fields.iter().position(|f| f.0 == *name) {
+                        Some(idx) => {
+                            let joined = fields[idx].1.join(ty)?;
+                            fields[idx].1 = joined;
+                        }
+                        None => fields.push((name.to_owned(), ty.to_owned())),
+                    };
+                }
+                Some(VarType::Object(fields))
+            }
+
+            (a, b) if a == b => Some(a.to_owned()),
+            _ => None,
+        }
+    }
+
+    pub fn is_compatible(&self, other: &VarType) -> bool {
+        self.join(other).is_some()
+    }
+}
+
+impl fmt::Display for VarType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VarType::Unknown => write!(f, "unknown"),
+            VarType::Null => write!(f, "null"),
+            VarType::Bool => write!(f, "bool"),
+            VarType::Int => write!(f, "int"),
+            VarType::Float => write!(f, "float"),
+            VarType::String => write!(f, "string"),
+            VarType::Array(ref ty) => write!(f, "[{}]", ty),
+            VarType::Map(ref ty) => write!(f, "map [{}]", ty),
+            VarType::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (idx, &(ref name, ref ty)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, ty)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl<T> ExpressionValue<T> {
+    /// Type of a literal value, or `None` if the value is not a literal.
+    pub fn peek_ty(&self) -> Option<VarType> {
+        match *self {
+            ExpressionValue::Primitive(ref p) => Some(VarType::of_primitive(p)),
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(ref a))) => {
+                Some(VarType::Array(Box::new(join_all(a.0.iter().flat_map(|v| v.iter()).map(|p| p.value().peek_ty().unwrap_or_default())))))
+            }
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ObjectValue(ref o))) => {
+                Some(object_ty(o, |e| e.peek_ty().unwrap_or_default()))
+            }
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::MapValue(ref m))) => {
+                let entries = m.1.iter().flat_map(|v| v.iter()).map(|o| object_ty(o, |e| e.peek_ty().unwrap_or_default()));
+                Some(VarType::Map(Box::new(join_all(entries))))
+            }
+
+            ExpressionValue::Expression(Expression::Group(Some(box ref e))) => e.peek_ty(),
+
+            _ => None,
+        }
+    }
+}
+
+/// Joins the types of the items of a collection, which is unknown if they are not compatible.
+pub fn join_all<I: IntoIterator<Item = VarType>>(types: I) -> VarType {
+    types
+        .into_iter()
+        .fold(Some(VarType::Unknown), |acc, ty| acc.and_then(|acc| acc.join(&ty)))
+        .unwrap_or_default()
+}
+
+/// Record type of an object value, with the type of each member given by `member_ty`.
+pub fn object_ty<T, F: FnMut(&ExpressionValue<T>) -> VarType>(o: &ObjectValue<T>, mut member_ty: F) -> VarType {
+    let fields = o.0
+        .iter()
+        .flat_map(|v| v.iter())
+        .map(|p| (p.key().to_owned(), member_ty(p.value())))
+        .collect();
+
+    VarType::Object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_var_type_join() {
+        let todo = VarType::Object(vec![("id".to_owned(), VarType::Int), ("text".to_owned(), VarType::String)]);
+        let todos = VarType::Array(Box::new(todo.clone()));
+
+        assert_eq!(VarType::Int.join(&VarType::Float), Some(VarType::Float));
+        assert_eq!(VarType::Unknown.join(&todos), Some(todos.clone()));
+        assert_eq!(VarType::Array(Box::new(VarType::Unknown)).join(&todos), Some(todos.clone()));
+        assert_eq!(todos.join(&VarType::String), None);
+        assert_eq!(todos.item_ty(), todo);
+        assert_eq!(todo.member_ty("text"), VarType::String);
+        assert_eq!(format!("{}", todos), "[{id: int, text: string}]");
+    }
+}
@@ -0,0 +1,81 @@
+//! Element keys are derived from a path through the template rather than
+//! generated randomly, so compiling the same source always yields the same
+//! keys (and byte-identical output).
+//!
+//! Nodes in the template AST are keyed by their position (see
+//! `Template::new`). Keys allocated while processing are numbered in order
+//! within the innermost `ElementKeyScope`, such as the component being processed.
+
+use std::cell::RefCell;
+
+thread_local!(
+    static KEY_SCOPES: RefCell<Vec<(String, usize)>> = RefCell::new(vec![("".to_owned(), 0)]);
+);
+
+/// FNV-1a, which is stable across platforms and compiler versions.
+fn hash_path(path: &str) -> u32 {
+    path.bytes().fold(0x811c_9dc5, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Key for the node at the given path.
+pub fn element_key_for_path(path: &str) -> String {
+    format!("{:08x}", hash_path(path))
+}
+
+/// Allocates the next key within the current scope.
+pub fn allocate_element_key() -> String {
+    let path = KEY_SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let scope = scopes.last_mut().expect("element key scopes");
+        scope.1 += 1;
+        format!("{}#{}", scope.0, scope.1)
+    });
+
+    element_key_for_path(&path)
+}
+
+/// While alive, keys are allocated in order under the given path, starting from one.
+#[derive(Debug)]
+pub struct ElementKeyScope(());
+
+impl ElementKeyScope {
+    pub fn enter(path: &str) -> Self {
+        KEY_SCOPES.with(|scopes| scopes.borrow_mut().push((path.to_owned(), 0)));
+        ElementKeyScope(())
+    }
+}
+
+impl Drop for ElementKeyScope {
+    fn drop(&mut self) {
+        KEY_SCOPES.with(|scopes| {
+            scopes.borrow_mut().pop();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_keys_are_deterministic() {
+        assert_eq!(element_key_for_path(""), "811c9dc5");
+        assert_eq!(element_key_for_path("component:todo/0"), element_key_for_path("component:todo/0"));
+        assert!(element_key_for_path("component:todo/0") != element_key_for_path("component:todo/1"));
+
+        let first: Vec<_> = {
+            let _scope = ElementKeyScope::enter("component:todo");
+            (0..3).map(|_| allocate_element_key()).collect()
+        };
+
+        let second: Vec<_> = {
+            let _scope = ElementKeyScope::enter("component:todo");
+            (0..3).map(|_| allocate_element_key()).collect()
+        };
+
+        assert_eq!(first, second);
+        assert_eq!(first[0], element_key_for_path("component:todo#1"));
+    }
+}
@@ -2,6 +2,7 @@
 #![allow(unused_variables)]
 #![allow(unused_macros)]
 
+use std::collections::VecDeque;
 use std::str::{CharIndices, FromStr};
 
 use isymtope_ast_common::*;
@@ -79,6 +80,14 @@ pub struct Lexer<'input> {
     param_list_mode: bool,
     ref_mode: bool,
     ref_buffer: String,
+    pending: VecDeque<TemplateParseResult<(usize, Token, usize)>>,
+    /// Open interpolations, with the position of the opening brace and the depth of nested braces
+    interpolations: Vec<(usize, usize)>,
+    /// Closed interpolations, from the opening brace to after the closing brace
+    interpolation_spans: Vec<(usize, usize)>,
+    /// Whether the last token ends an operand, so that a following `-` is subtraction
+    after_operand: bool,
+    errors: Vec<TemplateParseError>,
 }
 
 impl<'input> Lexer<'input> {
@@ -134,6 +143,8 @@ impl<'input> Lexer<'input> {
             "let" => Token::LetKeyword,
             "for" => Token::ForKeyword,
             "in" => Token::InKeyword,
+            "if" => Token::IfKeyword,
+            "else" => Token::ElseKeyword,
             "bind" => Token::BindKeyword,
             "as" => Token::AsKeyword,
             "where" => Token::WhereKeyword,
@@ -163,6 +174,9 @@ impl<'input> Lexer<'input> {
             "event" => Token::EventKeyword,
             "dispatch" => Token::DispatchKeyword,
             "navigate" => Token::NavigateKeyword,
+            "raw" => Token::RawKeyword,
+            "slot" => Token::SlotKeyword,
+            "type" => Token::TypeKeyword,
 
             // TODO: Make these optional so that value can be used as a prop name
             "state" => Token::StateKeyword,
@@ -181,35 +195,336 @@ impl<'input> Lexer<'input> {
         return Ok((start, token, end));
     }
 
-    fn string(&mut self, start: usize) -> TemplateParseResult<(usize, Token, usize)> {
+    /// Lexes a string literal (or the remainder of one following an interpolated
+    /// expression) into the pending token queue.
+    ///
+    /// Interpolations are lowered to concatenation, so `"Hello {name}!"` produces
+    /// the same tokens as `("Hello " + (name) + "!")`.  A brace only starts an
+    /// interpolation when followed by an identifier or `(`, so that CSS such as
+    /// `"body { margin: 0; }"` and doubled braces (`{{w}}`) are kept literally.
+    fn string(&mut self, start: usize, interpolated: bool) {
         self.buffer.clear();
-        self.step();
+        let mut error: Option<TemplateParseError> = None;
+        let mut after_brace = false;
 
         while let Some((pos, c)) = self.one() {
-            if c == '"' {
-                let end = self.step_n(1);
-                return Ok((start, Token::LiteralString(self.buffer.clone()), end));
+            let at_brace = c == '{';
+            let starts_interpolation = at_brace && !after_brace && match self.n1 {
+                Some((_, 'a'...'z')) | Some((_, 'A'...'Z')) | Some((_, '_')) | Some((_, '(')) => true,
+                _ => false,
+            };
+            after_brace = at_brace;
+
+            match c {
+                '"' => {
+                    let end = self.step_n(1);
+                    let token = match error {
+                        Some(err) => Err(err),
+                        None => Ok((start, Token::LiteralString(self.buffer.clone()), end)),
+                    };
+                    self.pending.push_back(token);
+
+                    if interpolated {
+                        self.pending.push_back(Ok((pos, Token::CloseParen, end)));
+                    };
+                    return;
+                }
+
+                '{' if starts_interpolation => {
+                    let end = self.step_n(1);
+                    if !interpolated {
+                        self.pending.push_back(Ok((start, Token::OpenParen, start + 1)));
+                    };
+
+                    let token = match error {
+                        Some(err) => Err(err),
+                        None => Ok((start, Token::LiteralString(self.buffer.clone()), pos)),
+                    };
+                    self.pending.push_back(token);
+                    self.pending.push_back(Ok((pos, Token::Plus, end)));
+                    self.pending.push_back(Ok((pos, Token::OpenParen, end)));
+
+                    // Track nested braces until the closing brace of the interpolation
+                    self.interpolations.push((pos, 0));
+                    return;
+                }
+
+                '\\' => {
+                    self.step();
+                    if self.one().is_none() {
+                        break;
+                    };
+
+                    match self.escape(pos) {
+                        Ok(c) => self.buffer.push(c),
+                        Err(err) => {
+                            error = error.or(Some(err));
+                        }
+                    };
+                }
+
+                _ => {
+                    self.buffer.push(c);
+                    self.step();
+                }
             };
+        }
+
+        // A quote within an unterminated interpolation starts a string running to the end
+        let err = match self.interpolations.first() {
+            Some(&(interpolation_start, _)) => {
+                self.interpolations.clear();
+                TemplateParseError::UnterminatedInterpolation(SourceSpan::new(interpolation_start, self.source_len))
+            }
+            None => TemplateParseError::UnterminatedString(SourceSpan::new(start, self.source_len)),
+        };
+        self.pending.push_back(Err(err));
+    }
 
-            self.buffer.push(c);
+    fn escape(&mut self, start: usize) -> TemplateParseResult<char> {
+        let c = self.one().map(|(_, c)| c).unwrap_or('\\');
+        self.step();
+
+        let escaped = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '{' => '{',
+            '}' => '}',
+
+            'u' => {
+                let source_str = self.source_str;
+                let mut digits: Option<&str> = None;
+
+                if let Some((_, '{')) = self.one() {
+                    self.step();
+                    let digits_start = self.pos();
+                    while let Some((_, '0'...'9')) | Some((_, 'a'...'f')) | Some((_, 'A'...'F')) = self.one() {
+                        self.step();
+                    }
+                    let digits_end = self.pos();
+
+                    if let Some((_, '}')) = self.one() {
+                        self.step();
+                        digits = Some(&source_str[digits_start..digits_end]);
+                    };
+                };
+
+                let c = digits
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(::std::char::from_u32);
+
+                match c {
+                    Some(c) => c,
+                    None => return Err(TemplateParseError::InvalidEscape(SourceSpan::new(start, self.pos()))),
+                }
+            }
+
+            _ => return Err(TemplateParseError::InvalidEscape(SourceSpan::new(start, self.pos()))),
+        };
+
+        Ok(escaped)
+    }
+
+    fn at_raw_string(&self) -> bool {
+        let rest = self.rest();
+        rest.starts_with("r\"") || (rest.starts_with("r#") && rest[1..].trim_left_matches('#').starts_with('"'))
+    }
+
+    fn at_digit(&self) -> bool {
+        match self.n1 {
+            Some((_, '0'...'9')) => true,
+            _ => false,
+        }
+    }
+
+    /// Raw strings (`r"..."`, `r#"..."#`) have no escapes or interpolation and may span lines.
+    fn raw_string(&mut self, start: usize) {
+        let source_str = self.source_str;
+        self.step();
+
+        let mut terminator = String::from("\"");
+        while let Some((_, '#')) = self.one() {
+            terminator.push('#');
             self.step();
         }
+        self.step();
+
+        let content_start = self.pos();
+        match source_str[content_start..].find(terminator.as_str()) {
+            Some(n) => {
+                let content_end = content_start + n;
+                while self.pos() < content_end + terminator.len() {
+                    self.step();
+                }
 
-        Err(TemplateParseError::UnterminatedString(start).into())
+                let content = source_str[content_start..content_end].to_owned();
+                self.pending.push_back(Ok((start, Token::LiteralString(content), self.pos())));
+            }
+
+            None => {
+                while self.one().is_some() {
+                    self.step();
+                }
+
+                self.pending.push_back(Err(TemplateParseError::UnterminatedString(SourceSpan::new(start, self.source_len))));
+            }
+        };
     }
 
+    /// Lexes an integer or decimal literal, including a leading minus sign where
+    /// it cannot be subtraction, so that `-2147483648` is in range.
     fn numeric(&mut self, start: usize) -> TemplateParseResult<(usize, Token, usize)> {
-        // TODO: Support negative numbers
         let (end, content) = take!(self, start, '0'...'9');
+
+        if let Some((_, '.', '0'...'9')) = self.two() {
+            self.step();
+            let (end, content) = take!(self, start, '0'...'9');
+            if let Ok(num) = f64::from_str(content) {
+                return Ok((start, Token::LiteralFloat(num), end));
+            }
+
+            return Err(TemplateParseError::InvalidNumber(SourceSpan::new(start, end)));
+        }
+
         if let Ok(num) = i32::from_str(content) {
             return Ok((start, Token::LiteralNumber(num), end));
         }
 
-        Err(TemplateParseError::InvalidNumber(start).into())
+        Err(TemplateParseError::InvalidNumber(SourceSpan::new(start, end)))
+    }
+
+    #[inline]
+    fn rest(&self) -> &'input str {
+        let source_str = self.source_str;
+        &source_str[self.pos()..]
+    }
+
+    fn rest_of_line(&mut self) -> &'input str {
+        let source_str = self.source_str;
+        let start = self.pos();
+
+        while let Some((_, c)) = self.one() {
+            if c == '\n' {
+                break;
+            }
+            self.step();
+        }
+
+        &source_str[start..self.pos()]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, ' ')) | Some((_, '\n')) | Some((_, '\r')) | Some((_, '\t')) = self.one() {
+            self.step();
+        }
+    }
+
+    fn at_doc_comment(&self) -> bool {
+        let rest = self.rest();
+        rest.starts_with("///") && !rest.starts_with("////")
+    }
+
+    /// Doc comments are only kept when they precede a definition which can carry them.
+    fn at_documented_definition(&self) -> bool {
+        let rest = self.rest();
+
+        ["component", "query", "action", "route", "type"].iter().any(|keyword| {
+            rest.starts_with(keyword) && !rest[keyword.len()..]
+                .chars()
+                .next()
+                .map_or(false, |c| c.is_alphanumeric() || c == '_')
+        })
+    }
+
+    fn doc_comment(&mut self, start: usize) -> Option<(usize, Token, usize)> {
+        let mut lines: Vec<&str> = Vec::new();
+        let mut end;
+
+        loop {
+            self.step_n(3);
+            let line = self.rest_of_line().trim_right_matches('\r');
+            lines.push(if line.starts_with(' ') { &line[1..] } else { line });
+            end = self.pos();
+
+            self.skip_whitespace();
+            if !self.at_doc_comment() {
+                break;
+            }
+        }
+
+        if self.at_documented_definition() {
+            return Some((start, Token::DocComment(lines.join("\n")), end));
+        };
+
+        None
+    }
+
+    fn block_comment(&mut self, start: usize) -> TemplateParseResult<()> {
+        let mut depth = 0;
+
+        while let Some((_, a, b)) = self.two() {
+            match (a, b) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.step_n(2);
+                }
+
+                ('*', '/') => {
+                    depth -= 1;
+                    self.step_n(2);
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+
+                _ => self.step(),
+            };
+        }
+
+        while self.one().is_some() {
+            self.step();
+        }
+
+        Err(TemplateParseError::UnterminatedComment(SourceSpan::new(start, self.source_len)))
     }
 
     fn normal(&mut self) -> Option<TemplateParseResult<(usize, Token, usize)>> {
         loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            };
+
+            // Comments
+            if let Some((start, '/', b)) = self.two() {
+                match b {
+                    '/' if self.at_doc_comment() => {
+                        if let Some(token) = self.doc_comment(start) {
+                            return Some(Ok(token));
+                        };
+                        continue;
+                    }
+
+                    '/' => {
+                        self.rest_of_line();
+                        continue;
+                    }
+
+                    '*' => {
+                        if let Err(err) = self.block_comment(start) {
+                            return Some(Err(err));
+                        };
+                        continue;
+                    }
+
+                    _ => {}
+                };
+            };
+
             if let Some((start, a, b)) = self.two() {
                 let token = match (a, b) {
                     ('=', '>') => Some(Token::HashRocket),
@@ -217,6 +532,9 @@ impl<'input> Lexer<'input> {
                     ('!', '=') => Some(Token::NotEqualTo),
                     ('>', '=') => Some(Token::GreaterThanOrEqualTo),
                     ('<', '=') => Some(Token::LessThanOrEqualTo),
+                    ('|', '|') => Some(Token::LogicalOr),
+                    ('&', '&') => Some(Token::LogicalAnd),
+                    (':', ':') => Some(Token::PathSep),
                     _ => None,
                 };
 
@@ -234,8 +552,33 @@ impl<'input> Lexer<'input> {
                     '<' => Token::LessThan,
 
                     '|' => Token::Pipe,
-                    '{' => Token::OpenBrace,
-                    '}' => Token::CloseBrace,
+                    '{' => {
+                        if let Some(&mut (_, ref mut depth)) = self.interpolations.last_mut() {
+                            *depth += 1;
+                        };
+                        Token::OpenBrace
+                    }
+                    '}' => {
+                        match self.interpolations.last().cloned() {
+                            // End of an interpolated expression, resume the string
+                            Some((interpolation_start, 0)) => {
+                                self.interpolations.pop();
+                                let end = self.step_n(1);
+                                self.interpolation_spans.push((interpolation_start, end));
+                                self.pending.push_back(Ok((start, Token::Plus, end)));
+                                self.string(end, true);
+                                return Some(Ok((start, Token::CloseParen, end)));
+                            }
+
+                            Some((interpolation_start, depth)) => {
+                                self.interpolations.pop();
+                                self.interpolations.push((interpolation_start, depth - 1));
+                            }
+
+                            None => {}
+                        };
+                        Token::CloseBrace
+                    }
                     '[' => Token::OpenBracket,
                     ']' => Token::CloseBracket,
                     '(' => {
@@ -246,7 +589,11 @@ impl<'input> Lexer<'input> {
                         self.param_list_mode = false;
                         Token::CloseParen
                     }
-                    '"' => return Some(self.string(start)),
+                    '"' => {
+                        self.step();
+                        self.string(start, false);
+                        continue;
+                    }
                     '.' => Token::Dot,
                     ',' => Token::Comma,
                     '=' => Token::Equals,
@@ -255,17 +602,25 @@ impl<'input> Lexer<'input> {
 
                     '!' => Token::Bang,
 
-                    // TODO: Support uniary minus (two char match)
                     '+' => Token::Plus,
+                    '-' if !self.after_operand && self.at_digit() => {
+                        self.step();
+                        return Some(self.numeric(start));
+                    }
                     '-' => Token::Minus,
                     '*' => Token::Mul,
                     '/' => Token::Div,
+                    '%' => Token::Mod,
 
-                    // TODO: Support negative numbers
                     '0'...'9' => {
                         return Some(self.numeric(start));
                     }
 
+                    'r' if self.at_raw_string() => {
+                        self.raw_string(start);
+                        continue;
+                    }
+
                     'A'...'Z' | 'a'...'z' => {
                         return Some(self.identifier(start));
                     }
@@ -275,17 +630,25 @@ impl<'input> Lexer<'input> {
                         continue;
                     }
 
-                    _ => break,
+                    _ => {
+                        // Skip the offending character so lexing can resume after the error
+                        let end = self.step_n(1);
+                        return Some(Err(TemplateParseError::UnexpectedToken(SourceSpan::new(start, end))));
+                    }
                 };
 
                 let end = self.step_n(1);
                 return Some(Ok((start, token, end)));
             } else {
+                // The input ended within an interpolation
+                if let Some(&(interpolation_start, _)) = self.interpolations.first() {
+                    self.interpolations.clear();
+                    let span = SourceSpan::new(interpolation_start, self.source_len);
+                    return Some(Err(TemplateParseError::UnterminatedInterpolation(span)));
+                };
                 return None;
             }
         }
-
-        Some(Err(TemplateParseError::UnexpectedToken(self.pos())))
     }
 }
 
@@ -293,12 +656,54 @@ impl<'input> Iterator for Lexer<'input> {
     type Item = TemplateParseResult<(usize, Token, usize)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(Ok((start, token, end))) = self.normal() {
-            println!("Token: {:?} ({:?}, {:?})", token, start, end);
-            Some(Ok((start, token, end)))
-        } else {
-            None
-        }
+        let next = self.normal();
+        match next {
+            Some(Ok((_, ref token, _))) => {
+                self.after_operand = match *token {
+                    Token::Identifier(_)
+                    | Token::LiteralNumber(_)
+                    | Token::LiteralFloat(_)
+                    | Token::LiteralString(_)
+                    | Token::LiteralBool(_)
+                    | Token::VariableReference(_)
+                    | Token::ItemKeyword
+                    | Token::ValueKeyword
+                    | Token::StateKeyword
+                    | Token::CloseParen
+                    | Token::CloseBracket => true,
+                    _ => false,
+                };
+            }
+            Some(Err(ref err)) => self.errors.push(err.clone()),
+            None => {}
+        };
+        next
+    }
+}
+
+impl<'input> Lexer<'input> {
+    /// Start of the string interpolation containing `pos`, if any, from those lexed so far
+    pub fn interpolation_at(&self, pos: usize) -> Option<usize> {
+        let closed = self.interpolation_spans
+            .iter()
+            .rev()
+            .find(|&&(start, end)| start <= pos && pos < end)
+            .map(|&(start, _)| start);
+
+        closed.or_else(|| {
+            self.interpolations
+                .iter()
+                .rev()
+                .find(|&&(start, _)| start <= pos)
+                .map(|&(start, _)| start)
+        })
+    }
+
+    /// Lexes the rest of the input, returning every lexical error encountered,
+    /// including those already returned as tokens.
+    pub fn into_errors(mut self) -> Vec<TemplateParseError> {
+        while let Some(_) = self.next() {}
+        self.errors
     }
 }
 
@@ -323,7 +728,156 @@ pub fn lex<'input>(input: &'input str) -> Lexer<'input> {
         element_close: None,
         ref_mode: false,
         ref_buffer: String::new(),
+        pending: VecDeque::new(),
+        interpolations: Vec::new(),
+        interpolation_spans: Vec::new(),
+        after_operand: false,
+        errors: Vec::new(),
         block_name_mode: false,
         param_list_mode: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use isymtope_ast_common::*;
+    use input::parser::token::Token;
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        lex(input).map(|res| res.unwrap().1).collect()
+    }
+
+    fn errors(input: &str) -> Vec<(usize, usize)> {
+        lex(input)
+            .into_errors()
+            .iter()
+            .map(|err| (err.span().start(), err.span().end()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lexer_unexpected_character_spans() {
+        let input = "div { @ } span { # }";
+        assert_eq!(errors(input), vec![(6, 7), (17, 18)]);
+
+        // Lexing resumes after the offending character
+        let results: Vec<_> = lex("a @ b").collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().1, Token::Identifier("b".to_owned()));
+    }
+
+    #[test]
+    fn test_lexer_unterminated_spans() {
+        assert_eq!(errors("let a = \"abc"), vec![(8, 12)]);
+        assert_eq!(errors("div { } /* comment"), vec![(8, 18)]);
+    }
+
+    #[test]
+    fn test_lexer_into_errors_after_first_error() {
+        let mut lexer = lex("a @ b # c");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+
+        // Errors already returned are reported with the rest
+        assert_eq!(lexer.into_errors().len(), 2);
+    }
+
+    #[test]
+    fn test_lexer_interpolation() {
+        assert_eq!(
+            tokens(r#""Hello {name}!""#),
+            vec![
+                Token::OpenParen,
+                Token::LiteralString("Hello ".to_owned()),
+                Token::Plus,
+                Token::OpenParen,
+                Token::Identifier("name".to_owned()),
+                Token::CloseParen,
+                Token::Plus,
+                Token::LiteralString("!".to_owned()),
+                Token::CloseParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_interpolation_nested_braces() {
+        assert_eq!(
+            tokens(r#""{f({a: 1})}""#),
+            vec![
+                Token::OpenParen,
+                Token::LiteralString("".to_owned()),
+                Token::Plus,
+                Token::OpenParen,
+                Token::Identifier("f".to_owned()),
+                Token::OpenParen,
+                Token::OpenBrace,
+                Token::Identifier("a".to_owned()),
+                Token::Colon,
+                Token::LiteralNumber(1),
+                Token::CloseBrace,
+                Token::CloseParen,
+                Token::CloseParen,
+                Token::Plus,
+                Token::LiteralString("".to_owned()),
+                Token::CloseParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_literal_braces() {
+        assert_eq!(tokens(r#""\{name\}""#), vec![Token::LiteralString("{name}".to_owned())]);
+        assert_eq!(
+            tokens(r#""body { margin: 0; }""#),
+            vec![Token::LiteralString("body { margin: 0; }".to_owned())]
+        );
+        assert_eq!(tokens(r#""{{w}} {{h}}""#), vec![Token::LiteralString("{{w}} {{h}}".to_owned())]);
+    }
+
+    #[test]
+    fn test_lexer_unterminated_interpolation() {
+        let input = r#"let a = "Hello {name"#;
+        let errors: Vec<_> = lex(input).into_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].span().start(), errors[0].span().end()), (15, 20));
+        assert!(format!("{}", errors[0]).contains("\\{"));
+    }
+
+    #[test]
+    fn test_parse_reports_invalid_interpolation() {
+        let err = ::input::parser::parse_str("div { (\"Hello {name name}!\") }\n").unwrap_err();
+        match err.errors().first().map(|err| *err) {
+            Some(&TemplateParseError::InvalidInterpolation(ref span)) => assert_eq!(span.start(), 14),
+            other => panic!("expected an invalid interpolation, got {:?}", other),
+        };
+    }
+
+    #[test]
+    fn test_lexer_negative_numbers() {
+        assert_eq!(tokens("-2147483648"), vec![Token::LiteralNumber(-2147483648)]);
+        assert_eq!(tokens("-1.5"), vec![Token::LiteralFloat(-1.5)]);
+        assert_eq!(
+            tokens("(a -1) - -2"),
+            vec![
+                Token::OpenParen,
+                Token::Identifier("a".to_owned()),
+                Token::Minus,
+                Token::LiteralNumber(1),
+                Token::CloseParen,
+                Token::Minus,
+                Token::LiteralNumber(-2),
+            ]
+        );
+        assert_eq!(errors("2147483648"), vec![(0, 10)]);
+    }
+
+    #[test]
+    fn test_parse_reports_all_lexical_errors() {
+        let err = ::input::parser::parse_str("div { @ }\nspan { # }\n").unwrap_err();
+        let lines: Vec<_> = err.errors().iter().map(|err| err.span().line()).collect();
+        assert_eq!(lines, vec![1, 2]);
+    }
+}
@@ -14,16 +14,103 @@ pub fn lex(input: &'static str) -> TokenIter {
 }
 
 pub fn parse_str<'input>(input: &str) -> TemplateParseResult<Template> {
-    let lexer = lexer::lex(&input);
-
-    match parser::parse_Template(lexer) {
-        Ok(template) => Ok(template),
-        Err(parse_err) => {
-            match parse_err {
-                ParseError::UnrecognizedToken { ref token, .. } => Err(TemplateParseError::UnrecognizedToken(token.as_ref().map_or(0, |t| t.0))),
-                ParseError::ExtraToken { ref token } => Err(TemplateParseError::UnexpectedToken(token.0)),
-                _ => Err(TemplateParseError::Other)
+    parse_source(input, None)
+}
+
+/// Parses template source, resolving any error location against `input` and
+/// reporting it as belonging to `file_name`.
+pub fn parse_source<'input>(input: &str, file_name: Option<&str>) -> TemplateParseResult<Template> {
+    let _element_keys = ElementKeyScope::enter(file_name.unwrap_or("template"));
+    let mut lexer = lexer::lex(&input);
+
+    parser::parse_Template(input, file_name, &mut lexer)
+        .map_err(move |parse_err| {
+            let err = match parse_err {
+                // Expressions within strings are reported as interpolations, as the
+                // brace may have been meant literally
+                ParseError::UnrecognizedToken { token: Some((start, _, end)), .. } if lexer.interpolation_at(start).is_some() => {
+                    let interpolation_start = lexer.interpolation_at(start).unwrap();
+                    TemplateParseError::InvalidInterpolation(SourceSpan::new(interpolation_start, end))
+                }
+                ParseError::UnrecognizedToken { token: Some((start, _, end)), expected } => {
+                    TemplateParseError::UnrecognizedToken(SourceSpan::new(start, end), input[start..end].to_owned(), expected)
+                }
+                ParseError::UnrecognizedToken { token: None, expected } => {
+                    TemplateParseError::UnexpectedEof(SourceSpan::at(input.len()), expected)
+                }
+                ParseError::ExtraToken { token: (start, _, end) } => {
+                    TemplateParseError::ExtraToken(SourceSpan::new(start, end), input[start..end].to_owned())
+                }
+                ParseError::InvalidToken { location } => {
+                    TemplateParseError::UnexpectedToken(SourceSpan::at(location))
+                }
+                ParseError::User { error } => {
+                    // Report all lexical errors in the template, not just the first one
+                    let mut errors = lexer.into_errors();
+                    match errors.len() {
+                        0 => error,
+                        1 => errors.remove(0),
+                        _ => TemplateParseError::Multiple(errors),
+                    }
+                }
+            };
+
+            err.with_source(input, file_name)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use isymtope_ast_common::*;
+    use output::*;
+    use super::*;
+
+    #[test]
+    fn test_comments() {
+        let src = r#"
+            use html;
+
+            // Line comment
+            /* Block comment /* with a nested */ comment */
+
+            /// Shows a greeting
+            component greeting(name) {
+                h1 { ("Hello " + name) } // after an element
+            }
+
+            div(/* inside attributes */ class = "main") {
+                /* inside content */
+                greeting(name = "world") {}
             }
-        }
+        "#;
+
+        let template = parse_str(src).unwrap();
+        let docs: Vec<_> = template
+            .children()
+            .filter_map(|n| match *n {
+                TemplateNode::ComponentDefinition(ref c, _) => Some(c.doc()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(docs, vec![Some("Shows a greeting")]);
+
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            r#"<div key="0d776765" class="main"><h1 key="07c655ca.5e75a992">Hello world</h1></div>"#
+        );
+    }
+
+    #[test]
+    fn test_negated_int_min() {
+        // Negating the smallest int overflows, and is reported at the expression
+        let err = parse_str("div { (- -2147483648) }\n").unwrap_err();
+        match err.errors().first().map(|err| *err) {
+            Some(&TemplateParseError::InvalidNumber(ref span)) => assert_eq!((span.start(), span.end()), (7, 20)),
+            other => panic!("expected an invalid number, got {:?}", other),
+        };
+
+        let data = template_data("use html;\ndiv { (- 2147483647) }\n");
+        assert_eq!(data.page_body_html, r#"<div key="0d776765">-2147483647</div>"#);
     }
 }
@@ -4,6 +4,8 @@ pub enum Token {
     LetKeyword,
     ForKeyword,
     InKeyword,
+    IfKeyword,
+    ElseKeyword,
     BindKeyword,
     AsKeyword,
     WhereKeyword,
@@ -35,6 +37,9 @@ pub enum Token {
     EventKeyword,
     DispatchKeyword,
     NavigateKeyword,
+    RawKeyword,
+    SlotKeyword,
+    TypeKeyword,
 
     StateKeyword,
     ValueKeyword,
@@ -52,6 +57,8 @@ pub enum Token {
     GreaterThanOrEqualTo,
 
     Pipe,
+    LogicalOr,
+    LogicalAnd,
     OpenBrace,
     CloseBrace,
     OpenBracket,
@@ -62,15 +69,19 @@ pub enum Token {
     Comma,
     Equals,
     Colon,
+    PathSep,
     Semi,
     Bang,
     Plus,
     Minus,
     Mul,
     Div,
+    Mod,
     Identifier(String),
     LiteralNumber(i32),
+    LiteralFloat(f64),
     LiteralString(String),
     LiteralBool(bool),
     VariableReference(String),
+    DocComment(String),
 }
@@ -2,7 +2,8 @@ use std::env;
 use std::fs;
 use std::io::{self,  Read};
 use std::rc::Rc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
 
 use isymtope_ast_common::*;
 use input::*;
@@ -91,13 +92,144 @@ fn read_file_as_string(path: &Path) -> io::Result<String> {
     Ok(content)
 }
 
+/// Loads a template file along with the template files it imports.
+///
+/// Imports are resolved relative to the app root (the directory containing the
+/// root template) and merged ahead of the importing template. Components defined
+/// in an imported file are qualified with the file's name, so `todo_item` from
+/// `components/todo.ism` is called as `todo::todo_item`.  Importing two files
+/// with the same name is an error, as their components would share a namespace.
+#[derive(Debug)]
+struct TemplateLoader {
+    root: PathBuf,
+    loading: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+    namespaces: HashMap<String, PathBuf>,
+}
+
+impl TemplateLoader {
+    fn new(source_path: &Path) -> Self {
+        let root = source_path
+            .parent()
+            .map(|p| p.to_owned())
+            .unwrap_or_default();
+
+        TemplateLoader {
+            root: root,
+            loading: Default::default(),
+            loaded: Default::default(),
+            namespaces: Default::default(),
+        }
+    }
+
+    fn load_root(&mut self, source_path: &Path) -> DocumentProcessingResult<Template> {
+        let children = self.load(source_path, None)?;
+
+        Ok(Template::new(children))
+    }
+
+    /// Path of a template relative to the app root, as shown in errors.
+    fn display_path(&self, path: &Path) -> String {
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        path.strip_prefix(&root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn resolve(&self, import: &str, importer: &str) -> DocumentProcessingResult<PathBuf> {
+        self.root.join(import).canonicalize().map_err(|err| {
+            DocumentProcessingError::ImportError(format!(
+                "cannot read template [{}] imported from [{}]: {}",
+                import, importer, err
+            ))
+        })
+    }
+
+    fn load(&mut self, path: &Path, ns: Option<&str>) -> DocumentProcessingResult<Vec<TemplateNode<SourceExpression>>> {
+        let path = path.canonicalize()?;
+
+        if self.loading.contains(&path) {
+            let chain: Vec<_> = self.loading
+                .iter()
+                .skip_while(|p| **p != path)
+                .chain(Some(&path))
+                .map(|p| self.display_path(p))
+                .collect();
+
+            return Err(DocumentProcessingError::ImportError(format!(
+                "import cycle: {}",
+                chain.join(" -> ")
+            )));
+        };
+
+        // Each file is merged once, even when imported from several templates
+        if self.loaded.contains(&path) {
+            return Ok(Default::default());
+        };
+
+        let file_name = self.display_path(&path);
+        let src = read_file_as_string(&path)?;
+        let mut template = parser::parse_source(&src, Some(&file_name))?;
+
+        if let Some(ns) = ns {
+            template.qualify_components(ns);
+        };
+
+        self.loading.push(path.clone());
+
+        let mut imported: Vec<TemplateNode<SourceExpression>> = Default::default();
+        let mut children: Vec<TemplateNode<SourceExpression>> = Default::default();
+
+        for child in template.into_children() {
+            match child {
+                TemplateNode::UseFile(ref import) => {
+                    let import_path = self.resolve(import, &file_name)?;
+                    let import_ns = import_path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+
+                    if let Some(other_path) = self.namespaces.get(&import_ns) {
+                        if *other_path != import_path {
+                            return Err(DocumentProcessingError::ImportError(format!(
+                                "templates [{}] and [{}] are both imported as [{}]",
+                                self.display_path(other_path),
+                                self.display_path(&import_path),
+                                import_ns
+                            )));
+                        };
+                    };
+                    self.namespaces.insert(import_ns.clone(), import_path.clone());
+
+                    imported.extend(self.load(&import_path, Some(&import_ns))?);
+                }
+
+                TemplateNode::Content(..) if ns.is_some() => {
+                    return Err(DocumentProcessingError::ImportError(format!(
+                        "imported template [{}] cannot contain top-level content",
+                        file_name
+                    )));
+                }
+
+                child => children.push(child),
+            };
+        }
+
+        self.loading.pop();
+        self.loaded.insert(path);
+
+        imported.extend(children);
+        Ok(imported)
+    }
+}
+
 fn from_source<'a, S: Into<TemplateSource<'a>>>(source: S) -> DocumentProcessingResult<Document> {
     let source = source.into();
 
     match source {
         TemplateSource::TemplatePathSource(ref source_path) => {
-            let src = read_file_as_string(source_path)?;
-            let template = parser::parse_str(&src)?;
+            let template = TemplateLoader::new(source_path).load_root(source_path)?;
             let mut ctx: DefaultProcessingContext<ProcessedExpression> =
                 DefaultProcessingContext::for_template(Rc::new(template.clone()));
             TryProcessFrom::try_process_from(&template, &mut ctx)
@@ -119,3 +251,168 @@ fn from_source<'a, S: Into<TemplateSource<'a>>>(source: S) -> DocumentProcessing
         TemplateSource::DocumentSource(document) => Ok(document),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process;
+    use super::*;
+
+    /// Writes the given files into a new app directory, returning the path of its root template.
+    fn app_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("isymtope-reader-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for &(path, src) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, src).unwrap();
+        }
+
+        dir.join("app.ism")
+    }
+
+    fn import_error(root: &Path) -> String {
+        match TemplateLoader::new(root).load_root(root) {
+            Err(DocumentProcessingError::ImportError(msg)) => msg,
+            other => panic!("expected an import error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_import_cycle() {
+        let root = app_dir(
+            "cycle",
+            &[
+                ("app.ism", "use \"a.ism\";\ndiv {}\n"),
+                ("a.ism", "use \"b.ism\";\ncomponent x() { p {} }\n"),
+                ("b.ism", "use \"a.ism\";\ncomponent y() { p {} }\n"),
+            ],
+        );
+
+        assert_eq!(import_error(&root), "import cycle: a.ism -> b.ism -> a.ism");
+    }
+
+    #[test]
+    fn test_import_duplicate_namespace() {
+        let root = app_dir(
+            "namespace",
+            &[
+                ("app.ism", "use widgets::todo;\nuse components::todo;\ndiv {}\n"),
+                ("widgets/todo.ism", "component entry() { p {} }\n"),
+                ("components/todo.ism", "component entry() { li {} }\n"),
+            ],
+        );
+
+        assert_eq!(
+            import_error(&root),
+            "templates [widgets/todo.ism] and [components/todo.ism] are both imported as [todo]"
+        );
+    }
+
+    /// Type errors found when processing a template, in the order reported
+    #[cfg(feature = "types")]
+    fn type_errors(src: &str) -> Vec<String> {
+        let err = DocumentProvider::create(src).expect_err("template should have type errors");
+        let err = err.type_error().unwrap_or_else(|| panic!("expected a type error, got {:?}", err));
+        err.errors().iter().map(|err| err.to_string()).collect()
+    }
+
+    #[cfg(feature = "types")]
+    #[test]
+    fn test_types_filter_on_int() {
+        let errors = type_errors(
+            r#"
+            use html;
+            component big(values) {
+                (values.filter(item > 1))
+            }
+            div {
+                big(values = 3) {}
+            }
+        "#,
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("pipeline method (filter) requires a collection, but is applied to type int"));
+    }
+
+    #[cfg(feature = "types")]
+    #[test]
+    fn test_types_mismatched_dispatch_param() {
+        let errors = type_errors(
+            r#"
+            use html;
+            store {
+                let count = 0;
+                count {
+                    action add(n) => state + n;
+                }
+            }
+            div {
+                button click || { dispatch add(n: 1) to count; } {}
+                button click || { dispatch add(n: "one") to count; } {}
+            }
+        "#,
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("param (n) was previously dispatched with type int, but is dispatched here with type string"));
+    }
+
+    #[cfg(feature = "types")]
+    #[test]
+    fn test_types_declared_type_conflicts() {
+        let errors = type_errors(
+            r#"
+            use html;
+            store {
+                let name: int = "abc";
+                let half: string = (1 / 2);
+                let ratio: float = (1 / 2);
+            }
+            div {}
+        "#,
+        );
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("is declared with type int, but is given type string"));
+        assert!(errors[1].contains("store value (half) is declared with type string, but is given type float"));
+    }
+
+    #[cfg(feature = "types")]
+    #[test]
+    fn test_types_logical_operators() {
+        // `&&` and `||` give one of their operands, so a string default is a string
+        let src = r#"
+            use html;
+            store {
+                let name = "";
+                name {
+                    action clear => "";
+                }
+            }
+            component heading(title: string) {
+                h1 { (title) }
+            }
+            div {
+                heading(title = (get name || "untitled")) {}
+            }
+        "#;
+        assert!(DocumentProvider::create(src).is_ok());
+
+        let errors = type_errors(
+            r#"
+            use html;
+            store {
+                let label: bool = ("a" || "b");
+                let done: bool = (1 < 2);
+            }
+            div {}
+        "#,
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("store value (label) is declared with type bool, but is given type string"));
+    }
+}
@@ -1,15 +1,112 @@
 use std::str;
 use std::io;
+use std::collections::HashMap;
 
 use itertools::join;
+use serde_json::{Map, Number, Value};
 
 use error::*;
 use traits::*;
 use objects::*;
 use output::*;
 
+/// Name of the form field identifying the submit button which dispatches actions
+/// when a form is submitted without scripts.  Its value is the key of the button,
+/// which prefixes the hidden fields describing its actions.
+pub const FORM_DISPATCH_FIELD: &str = "_dispatch";
+
+/// Key of the object standing in for the value of a form field in the params of
+/// an action, as `{"$field": "<name>"}`.
+pub const FORM_FIELD_REFERENCE: &str = "$field";
+
 #[derive(Debug, Default, Clone)]
-pub struct DefaultHtmlWriter {}
+pub struct DefaultHtmlWriter {
+    /// Slot content of the enclosing component instances, innermost last
+    slots: Vec<HashMap<String, String>>,
+    /// Url forms without an action are posted to
+    form_action: Option<String>,
+    /// Whether each enclosing form is posted to `form_action`, innermost last
+    forms: Vec<bool>,
+    /// Names of value bound fields with a name given by the template, by element key
+    form_fields: HashMap<String, String>,
+    /// Whether the enclosing element is `script` or `style`, whose text is not escaped
+    in_raw_text: bool,
+}
+
+impl DefaultHtmlWriter {
+    /// Writer rendering forms which dispatch actions when posted to `form_action`,
+    /// so that they work without scripts.
+    pub fn with_form_action(form_action: &str) -> Self {
+        DefaultHtmlWriter {
+            form_action: Some(form_action.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn in_posted_form(&self) -> bool {
+        self.forms.last().cloned().unwrap_or(false)
+    }
+
+    /// Escaping for text content of the enclosing element
+    fn text_escape(&self) -> fn(&str) -> String {
+        if self.in_raw_text {
+            escape_raw_text
+        } else {
+            escape_html_text
+        }
+    }
+}
+
+/// Escapes a string for use as text content.
+pub fn escape_html_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes a string for use as the text of a `script` or `style` element, which
+/// is not decoded, so that only the end tag of the element is neutralized.
+pub fn escape_raw_text(s: &str) -> String {
+    s.replace("</", "<\\/")
+}
+
+/// Escapes a string for use inside a double-quoted attribute value.
+pub fn escape_html_attr(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes a value through the given escaping function.
+fn write_escaped(
+    _self: &mut DefaultHtmlWriter,
+    w: &mut io::Write,
+    ctx: &mut OutputContext,
+    expr: &ExpressionValue<OutputExpression>,
+    escape: fn(&str) -> String,
+) -> DocumentProcessingResult<()> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(256);
+    _self.write_object(&mut bytes, ctx, expr)?;
+
+    write!(w, "{}", escape(str::from_utf8(bytes.as_slice())?))?;
+    Ok(())
+}
 
 ///
 /// Basic and compound expressions
@@ -75,6 +172,7 @@ impl ObjectWriter<Primitive, HtmlOutput> for DefaultHtmlWriter {
 
         match *obj {
             Primitive::Int32Val(n) => write!(w, "{}", n),
+            Primitive::FloatVal(f) => write!(w, "{}", float_to_string(f)),
 
             Primitive::BoolVal(b) if b => write!(w, "true"),
             Primitive::BoolVal(_) => write!(w, "false"),
@@ -113,6 +211,159 @@ impl ObjectWriter<ExpressionValue<OutputExpression>, HtmlOutput> for DefaultHtml
 /// Content expressions and elements
 ///
 
+fn eval_condition(ctx: &mut OutputContext, cond: &ExpressionValue<ProcessedExpression>) -> DocumentProcessingResult<bool> {
+    let cond: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(cond, ctx)?;
+    let cond: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(&cond, ctx)?;
+
+    cond.truthiness()
+        .ok_or_else(|| try_eval_from_err!(format!("Cannot evaluate condition: {:?}", cond)))
+}
+
+/// Position of the `EndBlock` closing the loop body which starts at `ops[0]`.
+fn find_end_block(ops: &[&ElementOp<ProcessedExpression>], key: &str) -> DocumentProcessingResult<usize> {
+    ops.iter()
+        .position(|op| match **op {
+            ElementOp::EndBlock(ref s) => s == key,
+            _ => false,
+        })
+        .ok_or_else(|| try_eval_from_err!(format!("Missing end of block: {}", key)))
+}
+
+fn write_ops(
+    _self: &mut DefaultHtmlWriter,
+    w: &mut io::Write,
+    ctx: &mut OutputContext,
+    ops: &[&ElementOp<ProcessedExpression>],
+) -> DocumentProcessingResult<()> {
+    // For each open conditional: whether a branch has been taken, and whether
+    // the current branch is being written.
+    let mut conditionals: Vec<(bool, bool)> = Vec::new();
+    let mut pos = 0;
+
+    while pos < ops.len() {
+        let op = ops[pos];
+        let is_writing = conditionals.iter().all(|&(_, active)| active);
+        pos += 1;
+
+        match *op {
+            ElementOp::StartIf(ref cond, _) => {
+                let taken = is_writing && eval_condition(ctx, cond)?;
+                conditionals.push((taken || !is_writing, taken));
+            }
+
+            ElementOp::ElseIf(ref cond, _) => {
+                let (taken, _) = conditionals.pop().unwrap_or_default();
+                let is_writing = conditionals.iter().all(|&(_, active)| active);
+                let active = is_writing && !taken && eval_condition(ctx, cond)?;
+                conditionals.push((taken || active, active));
+            }
+
+            ElementOp::Else(_) => {
+                let (taken, _) = conditionals.pop().unwrap_or_default();
+                conditionals.push((true, !taken));
+            }
+
+            ElementOp::EndIf(_) => {
+                conditionals.pop();
+            }
+
+            ElementOp::MapCollection(ref key, _, ref coll, ref item_key, _) => {
+                let len = find_end_block(&ops[pos..], key)?;
+                let body = &ops[pos..pos + len];
+                pos += len + 1;
+
+                if is_writing {
+                    write_map_collection(_self, w, ctx, key, coll, item_key.as_ref(), body)?;
+                }
+            }
+
+            _ if is_writing => _self.write_object(w, ctx, op)?,
+            _ => {}
+        };
+    }
+
+    Ok(())
+}
+
+/// Binds the element key for the current loop item, from the item key expression if
+/// given, otherwise from its position.
+fn bind_item_key(
+    ctx: &mut OutputContext,
+    key: &str,
+    item_key: Option<&ExpressionValue<ProcessedExpression>>,
+    idx: i32,
+) -> DocumentProcessingResult<()> {
+    let item_key = match item_key {
+        Some(item_key) => item_key,
+        None => return ctx.bind_element_key(key, Some(idx)),
+    };
+
+    let item_key: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(item_key, ctx)?;
+    let item_key: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(&item_key, ctx)?;
+
+    match item_key {
+        ExpressionValue::Primitive(ref p) => {
+            ctx.bind_element_key(&format!("{}.{}", key, p.to_concat_string()), None)
+        }
+        _ => Err(try_eval_from_err!(format!("Loop item key must be a primitive value: {:?}", item_key))),
+    }
+}
+
+/// Writes the content passed to a component for each of its slots, in the scope of the call site.
+fn write_slots(
+    _self: &mut DefaultHtmlWriter,
+    ctx: &mut OutputContext,
+    comp_desc: &ComponentInstanceDescriptor<ProcessedExpression>,
+) -> DocumentProcessingResult<HashMap<String, String>> {
+    let mut slots: HashMap<String, String> = Default::default();
+
+    if let Some(comp_slots) = comp_desc.slots() {
+        for slot in comp_slots {
+            let mut bytes: Vec<u8> = Vec::with_capacity(1024);
+            _self.write_object(&mut bytes, ctx, slot.block())?;
+
+            slots.insert(slot.name().to_owned(), str::from_utf8(bytes.as_slice())?.to_owned());
+        }
+    };
+
+    Ok(slots)
+}
+
+/// Writes the body of a loop once for each item in the collection.
+fn write_map_collection(
+    _self: &mut DefaultHtmlWriter,
+    w: &mut io::Write,
+    ctx: &mut OutputContext,
+    key: &str,
+    coll: &ExpressionValue<ProcessedExpression>,
+    item_key: Option<&ExpressionValue<ProcessedExpression>>,
+    body: &[&ElementOp<ProcessedExpression>],
+) -> DocumentProcessingResult<()> {
+    let coll: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(coll, ctx)?;
+    let coll: Option<Vec<ExpressionValue<OutputExpression>>> =
+        TryEvalFrom::try_eval_from(&coll, ctx)?;
+
+    if let Some(coll) = coll {
+        for (idx, item) in (0i32..).zip(coll.iter()) {
+            ctx.push_child_scope();
+
+            // Item and index of this loop, which remain visible within nested loops
+            let binding = CommonBindings::LoopItem(key.to_owned(), Default::default());
+            ctx.bind_value(binding, item.to_owned())?;
+
+            let idx_value = ExpressionValue::Primitive(Primitive::Int32Val(idx));
+            ctx.bind_value(CommonBindings::LoopItemIndex(key.to_owned()), idx_value)?;
+
+            bind_item_key(ctx, key, item_key, idx)?;
+
+            write_ops(_self, w, ctx, body)?;
+            ctx.pop_scope();
+        }
+    }
+
+    Ok(())
+}
+
 impl ObjectWriter<Block<ProcessedExpression>, HtmlOutput> for DefaultHtmlWriter {
     fn write_object(
         &mut self,
@@ -126,9 +377,8 @@ impl ObjectWriter<Block<ProcessedExpression>, HtmlOutput> for DefaultHtmlWriter
         );
 
         if let Some(ops) = obj.ops() {
-            for op in ops {
-                self.write_object(w, ctx, op)?;
-            }
+            let ops: Vec<_> = ops.into_iter().collect();
+            write_ops(self, w, ctx, &ops)?;
         }
 
         Ok(())
@@ -158,8 +408,7 @@ impl ObjectWriter<ElementEventBindingOutput<ProcessedExpression>, HtmlOutput> fo
         let mut js = DefaultJsWriter::default();
         js.write_object(&mut bytes, ctx, &props)?;
 
-        let props_str = str::from_utf8(bytes.as_slice())?
-            .replace("\"", "&quot;")
+        let props_str = escape_html_attr(str::from_utf8(bytes.as_slice())?)
             .replace("_event", "event");
 
         write!(
@@ -174,6 +423,142 @@ impl ObjectWriter<ElementEventBindingOutput<ProcessedExpression>, HtmlOutput> fo
     }
 }
 
+///
+/// Forms posted without scripts
+///
+
+/// Whether submitting a form with this element dispatches the actions bound to it
+fn is_submit_control(desc: &ElementDescriptor<ProcessedExpression>, string_props: &HashMap<String, String>) -> bool {
+    let ty = string_props.get("type").map(|s| s.as_str());
+    match desc.tag() {
+        "button" => ty != Some("button") && ty != Some("reset"),
+        "input" => ty == Some("submit") || ty == Some("image"),
+        _ => false,
+    }
+}
+
+fn has_prop(desc: &ElementDescriptor<ProcessedExpression>, name: &str) -> bool {
+    desc.props().any(|prop| prop.name() == name)
+}
+
+/// Actions bound to the click event of an element.  Actions bound to `enterkey` are
+/// not dispatched without scripts: pressing enter in a field submits the form with
+/// the actions of its first submit button, as browsers do.
+fn click_actions(desc: &ElementDescriptor<ProcessedExpression>) -> Vec<&ActionOp<ProcessedExpression>> {
+    desc.events()
+        .into_iter()
+        .flat_map(|events| events)
+        .filter(|event_binding| event_binding.event_name() == "click")
+        .flat_map(|event_binding| event_binding.event().actions().into_iter().flat_map(|actions| actions))
+        .collect()
+}
+
+/// Converts an action param into JSON, with values bound to form fields replaced by
+/// a reference to the field, which the server reads from the posted form.
+fn form_param_value(
+    _self: &DefaultHtmlWriter,
+    ctx: &mut OutputContext,
+    expr: &ExpressionValue<OutputExpression>,
+) -> DocumentProcessingResult<Value> {
+    match *expr {
+        ExpressionValue::Primitive(ref p) => Ok(match *p {
+            Primitive::CharVal(c) => Value::String(c.to_string()),
+            Primitive::StringVal(ref s) => Value::String(s.to_owned()),
+            Primitive::Int32Val(n) => Value::Number(n.into()),
+            Primitive::FloatVal(f) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+            Primitive::BoolVal(b) => Value::Bool(b),
+            Primitive::NullVal | Primitive::Undefined => Value::Null,
+        }),
+
+        ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(ArrayValue(ref items)))) => {
+            let mut values = Vec::new();
+            for item in items.iter().flat_map(|items| items.iter()) {
+                values.push(form_param_value(_self, ctx, item.value())?);
+            }
+            Ok(Value::Array(values))
+        }
+
+        ExpressionValue::Expression(Expression::Composite(CompositeValue::ObjectValue(ObjectValue(ref props)))) => {
+            let mut map = Map::new();
+            for prop in props.iter().flat_map(|props| props.iter()) {
+                map.insert(prop.key().to_owned(), form_param_value(_self, ctx, prop.value())?);
+            }
+            Ok(Value::Object(map))
+        }
+
+        ExpressionValue::Binding(CommonBindings::NamedElementBoundValue(ref key, _), _) => {
+            let element_key = ctx.get_element_key()?
+                .map(|s| format!("{}.{}", s, key))
+                .unwrap_or_else(|| key.to_owned());
+            let name = _self.form_fields.get(&element_key).cloned().unwrap_or(element_key);
+
+            let mut map = Map::new();
+            map.insert(FORM_FIELD_REFERENCE.to_owned(), Value::String(name));
+            Ok(Value::Object(map))
+        }
+
+        _ => Err(try_eval_from_err!(format!(
+            "Unsupported action param in form: {:?}",
+            expr
+        ))),
+    }
+}
+
+/// Writes hidden fields describing the actions dispatched by a submit button, named
+/// `<key>.<n>.type` and `<key>.<n>.params`, and `<key>.navigate` for navigation.
+fn write_form_actions(
+    _self: &mut DefaultHtmlWriter,
+    w: &mut io::Write,
+    ctx: &mut OutputContext,
+    element_key: &str,
+    actions: &[&ActionOp<ProcessedExpression>],
+) -> DocumentProcessingResult<()> {
+    for (idx, action) in actions.iter().enumerate() {
+        match **action {
+            ActionOp::DispatchAction(ref name, ref props, _)
+            | ActionOp::DispatchActionTo(ref name, ref props, _, _) => {
+                let action_ty = match **action {
+                    ActionOp::DispatchActionTo(_, _, ref target, _) => {
+                        format!("{}.{}", target.to_uppercase(), name.to_uppercase())
+                    }
+                    _ => name.to_uppercase(),
+                };
+
+                let mut params = Map::new();
+                for prop in props.iter().flat_map(|props| props.iter()) {
+                    let expr: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(prop.value(), ctx)?;
+                    params.insert(prop.key().to_owned(), form_param_value(_self, ctx, &expr)?);
+                }
+
+                write!(
+                    w,
+                    "<input type=\"hidden\" name=\"{}.{}.type\" value=\"{}\" />",
+                    escape_html_attr(element_key),
+                    idx,
+                    escape_html_attr(&action_ty)
+                )?;
+                write!(
+                    w,
+                    "<input type=\"hidden\" name=\"{}.{}.params\" value=\"{}\" />",
+                    escape_html_attr(element_key),
+                    idx,
+                    escape_html_attr(&Value::Object(params).to_string())
+                )?;
+            }
+
+            ActionOp::Navigate(ref path, _) => {
+                let path: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(path, ctx)?;
+
+                write!(w, "<input type=\"hidden\" name=\"{}.navigate\" value=\"", escape_html_attr(element_key))?;
+                write_escaped(_self, w, ctx, &path, escape_html_attr)?;
+                write!(w, "\" />")?;
+            }
+        };
+    }
+
+    Ok(())
+}
+
 fn write_open<'s>(
     _self: &'s mut DefaultHtmlWriter,
     w: &mut io::Write,
@@ -187,13 +572,26 @@ fn write_open<'s>(
         .map(|s| format!("{}.{}", s, desc.key()))
         .unwrap_or_else(|| desc.key().to_owned());
 
+    let string_props = desc.string_props();
+
+    // Submit buttons in a posted form dispatch their actions through hidden fields,
+    // unless named by the template, as the name identifies the button.
+    let form_actions = if _self.in_posted_form() && is_submit_control(desc, &string_props) && !has_prop(desc, "name") {
+        Some(click_actions(desc)).filter(|actions| !actions.is_empty())
+    } else {
+        None
+    };
+
+    if let Some(ref actions) = form_actions {
+        write_form_actions(_self, w, ctx, &element_key, actions)?;
+    };
+
     write!(w, "<{}", desc.tag())?;
 
     // Key
-    write!(w, " key=\"{}\"", element_key)?;
+    write!(w, " key=\"{}\"", escape_html_attr(&element_key))?;
 
     // Props
-    let string_props = desc.string_props();
 
     for prop in desc.props() {
         let (name, expr) = (prop.name(), prop.expr());
@@ -217,7 +615,7 @@ fn write_open<'s>(
 
             if classes.len() > 0 {
                 let classes = join(classes.into_iter(), " ");
-                write!(w, " class=\"{}\"", classes)?;
+                write!(w, " class=\"{}\"", escape_html_attr(&classes))?;
             };
 
             continue;
@@ -233,7 +631,7 @@ fn write_open<'s>(
         };
 
         write!(w, " {}=\"", name)?;
-        _self.write_object(w, ctx, &expr)?;
+        write_escaped(_self, w, ctx, &expr, escape_html_attr)?;
         write!(w, "\"")?;
     }
 
@@ -266,7 +664,39 @@ fn write_open<'s>(
         }
     };
 
+    // Forms
+    if form_actions.is_some() {
+        write!(
+            w,
+            " name=\"{}\" value=\"{}\"",
+            FORM_DISPATCH_FIELD,
+            escape_html_attr(&element_key)
+        )?;
+    };
+
+    if _self.in_posted_form() && desc.value_binding().is_some() {
+        match string_props.get("name") {
+            Some(name) => {
+                _self.form_fields.insert(element_key.clone(), name.to_owned());
+            }
+            None if !has_prop(desc, "name") => {
+                write!(w, " name=\"{}\"", escape_html_attr(&element_key))?;
+            }
+            None => {}
+        };
+    };
+
+    if desc.tag() == "form" && !is_void {
+        let is_posted = _self.form_action.is_some() && !has_prop(desc, "action");
+        if is_posted {
+            let form_action = _self.form_action.as_ref().map(|s| escape_html_attr(s)).unwrap_or_default();
+            write!(w, " action=\"{}\" method=\"post\"", form_action)?;
+        };
+        _self.forms.push(is_posted);
+    };
+
     if !is_void {
+        _self.in_raw_text = desc.tag() == "script" || desc.tag() == "style";
         write!(w, ">")?;
     } else {
         write!(w, " />")?;
@@ -341,6 +771,10 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
 
             ElementOp::ElementClose(ref tag) => {
                 write!(w, "</{}>", tag)?;
+                if tag == "form" {
+                    self.forms.pop();
+                };
+                self.in_raw_text = false;
                 ctx.pop_scope();
                 Ok(())
             }
@@ -365,6 +799,10 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
 
                     SkipElementOp::ElementClose(ref tag) => {
                         write!(w, "</{}>", tag)?;
+                        if tag == "form" {
+                            self.forms.pop();
+                        };
+                        self.in_raw_text = false;
                         ctx.pop_scope();
                         Ok(())
                     }
@@ -384,7 +822,8 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
                             "ObjectWriter SkipElementOp<ProcessedExpression> (HTML) WriteValue expr: {:?}",
                             expr
                         );
-                        self.write_object(w, ctx, &expr)
+                        let escape = self.text_escape();
+                        write_escaped(self, w, ctx, &expr, escape)
                     }
                 }
             }
@@ -393,32 +832,66 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
 
             ElementOp::EndBlock(_) => Ok(()),
 
-            ElementOp::MapCollection(_, _, _, _) => Ok(()),
+            ElementOp::MapCollection(..) => Ok(()),
+
+            // Conditionals are resolved when writing the enclosing block
+            ElementOp::StartIf(..) | ElementOp::ElseIf(..) | ElementOp::Else(_) | ElementOp::EndIf(_) => Ok(()),
 
             ElementOp::WriteValue(ref expr, _) => {
                 let expr: ExpressionValue<OutputExpression> =
                     TryEvalFrom::try_eval_from(expr, ctx)?;
+                let expr: ExpressionValue<OutputExpression> =
+                    TryEvalFrom::try_eval_from(&expr, ctx)?;
 
                 eprintln!(
                     "ObjectWriter ElementOp<ProcessedExpression> (HTML) WriteValue expr: {:?}",
                     expr
                 );
-                self.write_object(w, ctx, &expr)
+                let escape = self.text_escape();
+                write_escaped(self, w, ctx, &expr, escape)
+            }
+
+            // Raw values are wrapped in an element so the client can replace its contents
+            ElementOp::WriteRawValue(ref expr, ref key) => {
+                let expr: ExpressionValue<OutputExpression> =
+                    TryEvalFrom::try_eval_from(expr, ctx)?;
+                let expr: ExpressionValue<OutputExpression> =
+                    TryEvalFrom::try_eval_from(&expr, ctx)?;
+
+                let element_key = ctx.get_element_key()?
+                    .map(|s| format!("{}.{}", s, key))
+                    .unwrap_or_else(|| key.to_owned());
+
+                write!(w, "<span key=\"{}\">", escape_html_attr(&element_key))?;
+                self.write_object(w, ctx, &expr)?;
+                write!(w, "</span>")?;
+                Ok(())
             }
 
             ElementOp::InstanceComponent(ref comp_desc, _) => {
                 eprintln!("ObjectWriter ElementOp<ProcessedExpression> (HTML) InstanceComponent comp_desc: {:?}", comp_desc);
+                let slots = write_slots(self, ctx, comp_desc)?;
+
                 ctx.push_child_scope();
                 ctx.bind_element_key(comp_desc.desc().key(), None)?;
 
                 // write_component(self, w, ctx, comp_desc, None, None)
                 // write_comp_desc(self, w, ctx, comp_desc, None)?;
+                self.slots.push(slots);
                 self.write_object(w, ctx, comp_desc)?;
+                self.slots.pop();
                 ctx.pop_scope();
                 Ok(())
             }
 
-            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, _) => {
+            ElementOp::WriteSlot(ref name) => {
+                if let Some(content) = self.slots.last().and_then(|slots| slots.get(name)) {
+                    write!(w, "{}", content)?;
+                };
+                Ok(())
+            }
+
+            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, ref item_key_expr, _) => {
                 eprintln!("ObjectWriter ElementOp<ProcessedExpression> (HTML) MapInstanceComponent comp_desc: {:?}", comp_desc);
                 eprintln!("ObjectWriter ElementOp<ProcessedExpression> (HTML) MapInstanceComponent item_key: {:?}", item_key);
                 eprintln!("ObjectWriter ElementOp<ProcessedExpression> (HTML) MapInstanceComponent coll: {:?}", coll);
@@ -435,8 +908,6 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
                             OutputScopeEnvironment::MappedComponentInstance,
                         );
 
-                        ctx.bind_element_key(comp_desc.key(), Some(idx))?;
-
                         // CurrentItem
                         let binding = CommonBindings::CurrentItem(Default::default());
                         ctx.bind_loop_value(binding, item.to_owned())?;
@@ -448,9 +919,15 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
                             ExpressionValue::Primitive(Primitive::Int32Val(idx)),
                         )?;
 
+                        // Slot content is keyed by the instance, so that it is unique in each iteration
+                        bind_item_key(ctx, comp_desc.key(), item_key_expr.as_ref(), idx)?;
+                        let slots = write_slots(self, ctx, comp_desc)?;
+
                         // write_component(self, w, ctx, comp_desc, item_key.as_ref().map(|s| s.as_str()), Some(idx))?;
                         // write_comp_desc(self, w, ctx, comp_desc, None)?;
+                        self.slots.push(slots);
                         self.write_object(w, ctx, comp_desc)?;
+                        self.slots.pop();
 
                         ctx.pop_scope();
                     }
@@ -463,3 +940,332 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, HtmlOutput> for DefaultHtmlWri
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use output::*;
+
+    #[test]
+    fn test_if_else() {
+        let src = r#"
+            use html;
+
+            component badge(n) {
+                if (n > 2) {
+                    span { ("many") }
+                } else if (n > 0) {
+                    span { ("some") }
+                } else {
+                    span { ("none") }
+                }
+            }
+
+            div {
+                badge(n = 1) {}
+                badge(n = 5) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            r#"<div key="0d776765"><span key="07c655ca.81784896">some</span><span key="08c6575d.0ec65dad">many</span></div>"#
+        );
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        let src = r#"
+            use html;
+
+            store {
+                let rows = [{ name: "a", cells: [1, 2] }, { name: "b", cells: [3] }];
+
+                rows {
+                    action clear => [];
+                }
+            }
+
+            component grid(rows) {
+                for row in rows {
+                    tr {
+                        for cell, i in row.cells {
+                            td { (row.name + cell + i) }
+                        }
+                    }
+                }
+            }
+
+            table {
+                grid(get rows) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        let cells: Vec<_> = data.page_body_html
+            .split("</td>")
+            .filter_map(|s| s.rfind('>').map(|pos| &s[pos + 1..]))
+            .filter(|s| !s.is_empty())
+            .collect();
+        assert_eq!(cells, vec!["a10", "a21", "b30"]);
+    }
+
+    #[test]
+    fn test_keyed_loop() {
+        let src = r#"
+            use html;
+
+            store {
+                let todos = [{ id: 7, text: "a" }, { id: 9, text: "b" }];
+
+                todos {
+                    action clear => [];
+                }
+            }
+
+            component todo_list(todos) {
+                ul {
+                    for todo in todos key = todo.id {
+                        li { (todo.text) }
+                    }
+                }
+            }
+
+            div {
+                todo_list(get todos) {}
+            }
+        "#;
+
+        // Items are keyed by their id rather than their position
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            concat!(
+                r#"<div key="0d776765"><ul key="07c655ca.1a793614">"#,
+                r#"<li key="07c655ca.9682f3a4.7.b083d09c">a</li><li key="07c655ca.9682f3a4.9.b083d09c">b</li>"#,
+                r#"</ul></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let src = r#"
+            use html;
+
+            component note(text) {
+                p(title = text) { (text) }
+                raw(text)
+            }
+
+            div {
+                note(text = "3<b>\"&'") {}
+            }
+        "#;
+
+        // Text and attribute values are escaped, and raw values are written as they are
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            concat!(
+                r#"<div key="0d776765">"#,
+                r#"<p key="07c655ca.7164405d" title="3&lt;b&gt;&quot;&amp;&#39;">3&lt;b&gt;"&amp;'</p>"#,
+                r#"<span key="07c655ca.70643eca">3<b>"&'</span>"#,
+                r#"</div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_raw_text_elements() {
+        let src = r#"
+            use html;
+
+            div {
+                style { ("ul > li { color: red }") }
+                script { ("if (a < b && c) { x = \"</script>\"; }") }
+                p { ("a > b") }
+            }
+        "#;
+
+        // Script and style text is not decoded, so only their end tags are neutralized
+        let data = template_data(src);
+        let html = &data.page_body_html;
+        assert!(html.contains(">ul > li { color: red }</style>"), "{}", html);
+        assert!(html.contains(r#">if (a < b && c) { x = "<\/script>"; }</script>"#), "{}", html);
+        assert!(html.contains(">a &gt; b</p>"), "{}", html);
+    }
+
+    #[test]
+    fn test_slots() {
+        let src = r#"
+            use html;
+
+            component card(title) {
+                section {
+                    header { slot header; }
+                    h2 { (title) }
+                    (children)
+                }
+            }
+
+            div {
+                card(title = "Hi") {
+                    slot header { b { ("Top") } }
+                    p { ("Body") }
+                }
+            }
+        "#;
+
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            concat!(
+                r#"<div key="0d776765"><section key="07c655ca.6d3e0e9b">"#,
+                r#"<header key="07c655ca.46dde904"><b key="c99d3032">Top</b></header>"#,
+                r#"<h2 key="07c655ca.47ddea97">Hi</h2><p key="2e120fca">Body</p>"#,
+                r#"</section></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_form_actions() {
+        let src = r#"
+            use html;
+
+            store {
+                let todos = [];
+                let text = "";
+
+                todos {
+                    action add(entry) => value + [entry];
+                }
+
+                text {
+                    action clear => "";
+                }
+            }
+
+            component add_form(text) {
+                form {
+                    input(type = "text") bind text as entry {}
+                    button(type = "submit") click || {
+                        dispatch add(entry: entry) to todos;
+                        navigate "/done";
+                    } { ("Add") }
+                }
+            }
+
+            div {
+                add_form(get text) {}
+            }
+        "#;
+
+        // The submit button names its key in `_dispatch`, which prefixes the hidden
+        // fields describing its actions
+        let html = form_template_data(src).page_body_html;
+        let button_key = "07c655ca.26f8cd1b";
+        let expected = vec![
+            r#"<form key="07c655ca.aedff7d7" action="/app/test/_actions" method="post">"#.to_owned(),
+            format!(r#"name="_dispatch" value="{}""#, button_key),
+            format!(r#"<input type="hidden" name="{}.0.type" value="TODOS.ADD" />"#, button_key),
+            format!(
+                r#"<input type="hidden" name="{}.0.params" value="{{&quot;entry&quot;:{{&quot;$field&quot;:&quot;07c655ca.25f8cb88&quot;}}}}" />"#,
+                button_key
+            ),
+            format!(r#"<input type="hidden" name="{}.navigate" value="/done" />"#, button_key),
+            r#"<input key="07c655ca.25f8cb88" type="text" name="07c655ca.25f8cb88" />"#.to_owned(),
+        ];
+        for part in expected {
+            assert!(html.contains(&part), "expected {} in {}", part, html);
+        }
+
+        // Forms are left to scripts unless the page is rendered by the server
+        let html = template_data(src).page_body_html;
+        assert!(html.contains(r#"<form key="07c655ca.aedff7d7">"#), "{}", html);
+        assert!(!html.contains("_dispatch") && !html.contains("type=\"hidden\""), "{}", html);
+    }
+
+    #[test]
+    fn test_looped_component_slots() {
+        let src = r#"
+            use html;
+
+            store {
+                let xs = [{ n: "a" }, { n: "b" }];
+
+                xs {
+                    action clear => [];
+                }
+            }
+
+            component row(x) {
+                (children)
+            }
+
+            component rows(xs) {
+                ul {
+                    row(for x in xs) {
+                        li { (x.n) }
+                    }
+                }
+            }
+
+            div {
+                rows(get xs) {}
+            }
+        "#;
+
+        // Slot content is keyed by the instance it is passed to, so that each item has its own key
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            concat!(
+                r#"<div key="0d776765"><ul key="07c655ca.139aeb9c">"#,
+                r#"<li key="07c655ca.784445fb.0.b0ef6064">a</li><li key="07c655ca.784445fb.1.b0ef6064">b</li>"#,
+                r#"</ul></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_keyed_component_loop() {
+        let src = r#"
+            use html;
+
+            store {
+                let todos = [{ id: 7, text: "a" }, { id: 9, text: "b" }];
+
+                todos {
+                    action clear => [];
+                }
+            }
+
+            component todo_item(todo) {
+                li { (todo.text) }
+            }
+
+            component todo_list(todos) {
+                ul {
+                    todo_item(for todo in todos key = todo.id) {}
+                }
+            }
+
+            div {
+                todo_list(get todos) {}
+            }
+        "#;
+
+        // Instances are keyed by their id rather than their position
+        let data = template_data(src);
+        assert_eq!(
+            data.page_body_html,
+            concat!(
+                r#"<div key="0d776765"><ul key="07c655ca.1a793614">"#,
+                r#"<li key="07c655ca.be054303.7.b1b465b3">a</li><li key="07c655ca.be054303.9.b1b465b3">b</li>"#,
+                r#"</ul></div>"#
+            )
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::io;
+use std::str;
 use std::fmt::Debug;
 
 use error::*;
@@ -7,7 +8,83 @@ use objects::*;
 use output::*;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
-pub struct DefaultJsWriter {}
+pub struct DefaultJsWriter {
+    /// Keys of the enclosing `for` loops, innermost last
+    loop_keys: Vec<String>,
+}
+
+impl DefaultJsWriter {
+    /// Expression for the key of an element or component instance, unique within
+    /// each iteration of the enclosing loops.
+    fn key_string(&self, ctx: &mut OutputContext, key: &str) -> DocumentProcessingResult<String> {
+        if let Some(loop_key) = self.loop_keys.last() {
+            return Ok(format!("_key_{} + \".{}\"", loop_key, key));
+        };
+
+        Ok(match ctx.environment()? {
+            Some(OutputScopeEnvironment::Component) => format!("props.key + \".{}\"", key),
+            _ => format!("\"{}\"", key),
+        })
+    }
+
+    /// Expression identifying the current loop item, from the item key expression
+    /// if given, otherwise from its position.
+    fn item_key_string(
+        &mut self,
+        ctx: &mut OutputContext,
+        item_key: Option<&ExpressionValue<ProcessedExpression>>,
+        idx: &str,
+    ) -> DocumentProcessingResult<String> {
+        let item_key = match item_key {
+            Some(item_key) => item_key,
+            None => return Ok(idx.to_owned()),
+        };
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(64);
+        self.write_object(&mut bytes, ctx, item_key)?;
+        Ok(format!("({})", str::from_utf8(bytes.as_slice())?))
+    }
+}
+
+/// Escapes a string for use inside a quoted JS string literal within a script block.
+pub fn escape_js_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            '<' => out.push_str("\\x3C"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Name of the JS render function (without the `Component` suffix) for a component,
+/// as imported components are namespaced (`todo::item` becomes `todo__item`).
+pub fn js_component_name(name: &str) -> String {
+    name.replace("::", "__")
+}
+
+/// String concatenation (including lowered interpolation) is written as a plain `+`.
+fn is_string_operand(e: &ExpressionValue<ProcessedExpression>) -> bool {
+    match *e {
+        ExpressionValue::Primitive(Primitive::StringVal(_))
+        | ExpressionValue::Primitive(Primitive::CharVal(_)) => true,
+        ExpressionValue::Expression(Expression::BinaryOp(BinaryOpType::Add, box ref a, box ref b)) => {
+            is_string_operand(a) || is_string_operand(b)
+        }
+        ExpressionValue::Expression(Expression::Group(Some(box ref e))) => is_string_operand(e),
+        _ => false,
+    }
+}
 
 impl ObjectWriter<Primitive, JsOutput> for DefaultJsWriter {
     fn write_object(
@@ -18,12 +95,13 @@ impl ObjectWriter<Primitive, JsOutput> for DefaultJsWriter {
     ) -> DocumentProcessingResult<()> {
         match *obj {
             Primitive::Int32Val(n) => write!(w, "{}", n),
+            Primitive::FloatVal(f) => write!(w, "{}", float_to_string(f)),
 
             Primitive::BoolVal(b) if b => write!(w, "true"),
             Primitive::BoolVal(_) => write!(w, "false"),
 
-            Primitive::CharVal(c) => write!(w, "'{}'", c),
-            Primitive::StringVal(ref s) => write!(w, "\"{}\"", s),
+            Primitive::CharVal(c) => write!(w, "'{}'", escape_js_string(&c.to_string())),
+            Primitive::StringVal(ref s) => write!(w, "\"{}\"", escape_js_string(s)),
             Primitive::NullVal => write!(w, "null"),
             Primitive::Undefined => write!(w, "undefined"),
         }?;
@@ -116,9 +194,12 @@ impl<T: Clone + Debug> ObjectWriter<CommonBindings<T>, JsOutput> for DefaultJsWr
             CommonBindings::CurrentReducerState(_) => write!(w, "state"),
             CommonBindings::CurrentItem(_) => write!(w, "_item"),
             CommonBindings::CurrentItemIndex => write!(w, "_idx"),
+            CommonBindings::LoopItem(ref key, _) => write!(w, "_item_{}", key),
+            CommonBindings::LoopItemIndex(ref key) => write!(w, "_idx_{}", key),
             CommonBindings::NamedReducerKey(ref key, _) => write!(w, "store.getState().{}", key),
             CommonBindings::NamedReducerActionParam(ref ident, _) => write!(w, "action.{}", ident),
             CommonBindings::NamedQueryParam(ref ident, _) => write!(w, "{}", ident),
+            CommonBindings::NamedRouteParam(ref ident, _) => write!(w, "params.{}", ident),
             CommonBindings::NamedComponentProp(ref ident, _) => write!(w, "props.{}", ident),
             CommonBindings::ComponentPropsObject(_) => write!(w, "props"),
             CommonBindings::NamedEventBoundValue(_, _) => write!(w, "_event.target.value"),
@@ -706,10 +787,18 @@ impl ObjectWriter<Expression<ProcessedExpression>, JsOutput> for DefaultJsWriter
 
             Expression::UnaryOp(ref op, box ref a) => {
                 match *op {
-                    UnaryOpType::Negate => write!(w, "!"),
-                }?;
+                    UnaryOpType::Negate => {
+                        write!(w, "!")?;
+                        self.write_object(w, ctx, a)
+                    }
 
-                self.write_object(w, ctx, a)
+                    UnaryOpType::Minus => {
+                        write!(w, "checkedNeg(")?;
+                        self.write_object(w, ctx, a)?;
+                        write!(w, ")")?;
+                        Ok(())
+                    }
+                }
             }
 
             Expression::BinaryOp(ref op, box ref a, box ref b) => {
@@ -764,6 +853,27 @@ impl ObjectWriter<Expression<ProcessedExpression>, JsOutput> for DefaultJsWriter
                     };
                 }
 
+                // Arithmetic goes through the runtime helpers so that overflow and division
+                // by zero fail the same way as on the server.
+                let checked_fn = match *op {
+                    BinaryOpType::Add if !is_string_operand(a) && !is_string_operand(b) => Some("checkedAdd"),
+                    BinaryOpType::Sub => Some("checkedSub"),
+                    BinaryOpType::Mul => Some("checkedMul"),
+                    BinaryOpType::Div => Some("checkedDiv"),
+                    BinaryOpType::Mod => Some("checkedMod"),
+                    _ => None,
+                };
+
+                if let Some(checked_fn) = checked_fn {
+                    write!(w, "{}(", checked_fn)?;
+                    self.write_object(w, ctx, a)?;
+                    write!(w, ", ")?;
+                    self.write_object(w, ctx, b)?;
+                    write!(w, ")")?;
+
+                    return Ok(());
+                }
+
                 self.write_object(w, ctx, a)?;
 
                 match *op {
@@ -771,12 +881,15 @@ impl ObjectWriter<Expression<ProcessedExpression>, JsOutput> for DefaultJsWriter
                     BinaryOpType::Sub => write!(w, " - "),
                     BinaryOpType::Mul => write!(w, " * "),
                     BinaryOpType::Div => write!(w, " / "),
+                    BinaryOpType::Mod => write!(w, " % "),
                     BinaryOpType::EqualTo => write!(w, " == "),
                     BinaryOpType::NotEqualTo => write!(w, " != "),
                     BinaryOpType::LessThan => write!(w, " < "),
                     BinaryOpType::LessThanOrEqualTo => write!(w, " <= "),
                     BinaryOpType::GreaterThan => write!(w, " > "),
                     BinaryOpType::GreaterThanOrEqualTo => write!(w, " >= "),
+                    BinaryOpType::And => write!(w, " && "),
+                    BinaryOpType::Or => write!(w, " || "),
                 }?;
 
                 self.write_object(w, ctx, b)?;
@@ -1229,11 +1342,8 @@ fn write_open<'s>(
         write!(w, "IncrementalDOM.elementVoid(\"{}\", ", tag)?;
     };
 
-    // Is this element being emitted within a component definition (function)?
-    let key_string = match ctx.environment()? {
-        Some(OutputScopeEnvironment::Component) => format!("props.key + \".{}\"", element_key),
-        _ => format!("\"{}\"", element_key),
-    };
+    // Is this element being emitted within a component definition (function) or loop?
+    let key_string = _self.key_string(ctx, &element_key)?;
 
     write!(w, "{}, [", key_string)?;
 
@@ -1347,7 +1457,7 @@ fn write_comp_desc<'s>(
     ctx: &mut OutputContext,
     comp_desc: &ComponentInstanceDescriptor<ProcessedExpression>,
     item_key: Option<&str>,
-    is_map: bool,
+    instance_key_string: Option<&str>,
 ) -> DocumentProcessingResult<()> {
     let component_key = comp_desc.key();
     let tag = comp_desc.tag();
@@ -1356,15 +1466,12 @@ fn write_comp_desc<'s>(
     let component_props = comp_desc.props();
 
     // Is this component instance (function call) being emitted within a component definition (function)?
-    let key_string = match ctx.environment()? {
-        Some(OutputScopeEnvironment::Component) if is_map => {
-            format!("props.key + \".{}.\" + _item[0]", component_key)
-        }
-        Some(OutputScopeEnvironment::Component) => format!("props.key + \".{}\"", component_key),
-        _ => format!("\"{}\"", component_key),
+    let key_string = match instance_key_string {
+        Some(instance_key_string) => instance_key_string.to_owned(),
+        _ => _self.key_string(ctx, component_key)?,
     };
 
-    write!(w, "{}Component(", tag)?;
+    write!(w, "{}Component(", js_component_name(tag))?;
     if let Some(component_props) = component_props {
         write!(w, "{{\"key\": {}", key_string)?;
 
@@ -1394,7 +1501,7 @@ fn write_comp_desc<'s>(
 
             write!(w, ", {}: ", prop.name())?;
             if item_key == Some(key) {
-                write!(w, "_item[1]")?;
+                write!(w, "_item")?;
             } else {
                 _self.write_object(w, ctx, prop.expr())?;
             };
@@ -1409,6 +1516,21 @@ fn write_comp_desc<'s>(
         //     first = false;
         // }
 
+        // Slot content is written as functions within the scope of the call site
+        let slots: Vec<_> = comp_desc.slots().map(|v| v.collect()).unwrap_or_default();
+        if !slots.is_empty() {
+            write!(w, ", \"slots\": {{")?;
+            for (idx, slot) in slots.into_iter().enumerate() {
+                if idx > 0 {
+                    write!(w, ", ")?;
+                };
+                writeln!(w, "\"{}\": () => {{", slot.name())?;
+                _self.write_object(w, ctx, slot.block())?;
+                write!(w, "}}")?;
+            }
+            write!(w, "}}")?;
+        };
+
         write!(w, "}}")?;
     };
     writeln!(w, ");")?;
@@ -1475,9 +1597,53 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, JsOutput> for DefaultJsWriter
 
             ElementOp::StartBlock(_) => Ok(()),
 
-            ElementOp::EndBlock(_) => Ok(()),
+            ElementOp::EndBlock(ref key) => {
+                if self.loop_keys.last() == Some(key) {
+                    self.loop_keys.pop();
+                    writeln!(w, "}}")?;
+                };
+                Ok(())
+            }
+
+            ElementOp::MapCollection(ref key, _, ref coll, ref item_key, _) => {
+                let key_string = self.key_string(ctx, &format!("{}.", key))?;
+                let item_key_string = self.item_key_string(ctx, item_key.as_ref(), &format!("_idx_{}", key))?;
+
+                // Each loop has its own item and index variables, so that the body
+                // of a nested loop can refer to the items of the enclosing loops.
+                write!(w, "const _coll_{} = values(", key)?;
+                self.write_object(w, ctx, coll)?;
+                writeln!(w, ");")?;
+                writeln!(w, "for (const [_idx_{}, _item_{}] of enumerate(_coll_{})) {{", key, key, key)?;
+                writeln!(w, "const _key_{} = {} + {};", key, key_string, item_key_string)?;
+
+                self.loop_keys.push(key.to_owned());
+                Ok(())
+            }
+
+            ElementOp::StartIf(ref cond, _) => {
+                write!(w, "if (")?;
+                self.write_object(w, ctx, cond)?;
+                writeln!(w, ") {{")?;
+                Ok(())
+            }
+
+            ElementOp::ElseIf(ref cond, _) => {
+                write!(w, "}} else if (")?;
+                self.write_object(w, ctx, cond)?;
+                writeln!(w, ") {{")?;
+                Ok(())
+            }
 
-            ElementOp::MapCollection(_, _, _, _) => Ok(()),
+            ElementOp::Else(_) => {
+                writeln!(w, "}} else {{")?;
+                Ok(())
+            }
+
+            ElementOp::EndIf(_) => {
+                writeln!(w, "}}")?;
+                Ok(())
+            }
 
             ElementOp::WriteValue(ref e, _) => {
                 write!(w, "IncrementalDOM.text(")?;
@@ -1487,17 +1653,53 @@ impl ObjectWriter<ElementOp<ProcessedExpression>, JsOutput> for DefaultJsWriter
                 Ok(())
             }
 
+            ElementOp::WriteSlot(ref name) => {
+                writeln!(w, "if (props.slots && props.slots[\"{}\"]) {{ props.slots[\"{}\"](); }}", name, name)?;
+                Ok(())
+            }
+
+            // Raw values replace the contents of a wrapping element, matching the HTML writer
+            ElementOp::WriteRawValue(ref e, ref key) => {
+                let element_key = ctx.get_element_key()?
+                    .map(|s| format!("{}.{}", s, key))
+                    .unwrap_or_else(|| key.to_owned());
+                let key_string = self.key_string(ctx, &element_key)?;
+
+                writeln!(w, "{{")?;
+                writeln!(w, "const _raw = IncrementalDOM.elementOpen(\"span\", {}, [\"key\", {}]);", key_string, key_string)?;
+                write!(w, "const _html = String(")?;
+                self.write_object(w, ctx, e)?;
+                writeln!(w, ");")?;
+                writeln!(w, "if (_raw.innerHTML !== _html) {{ _raw.innerHTML = _html; }}")?;
+                writeln!(w, "IncrementalDOM.skip();")?;
+                writeln!(w, "IncrementalDOM.elementClose(\"span\");")?;
+                writeln!(w, "}}")?;
+
+                Ok(())
+            }
+
             ElementOp::InstanceComponent(ref comp_desc, _) => {
-                write_comp_desc(self, w, ctx, comp_desc, None, false)
+                write_comp_desc(self, w, ctx, comp_desc, None, None)
             }
 
-            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, _) => {
-                write!(w, "for (const _item of enumerate(values(")?;
+            ElementOp::MapInstanceComponent(ref comp_desc, ref item_key, ref coll, ref item_key_expr, _) => {
+                let component_key = comp_desc.key();
+
+                write!(w, "const _coll_{} = values(", component_key)?;
                 self.write_object(w, ctx, coll)?;
-                writeln!(w, "))) {{")?;
+                writeln!(w, ");")?;
+                writeln!(w, "for (const [_idx, _item] of enumerate(_coll_{})) {{", component_key)?;
 
                 let item_key = item_key.as_ref().map(|s| s.as_str());
-                write_comp_desc(self, w, ctx, comp_desc, item_key, true)?;
+                let key_string = self.key_string(ctx, &format!("{}.", component_key))?;
+                let item_key_string = self.item_key_string(ctx, item_key_expr.as_ref(), "_idx")?;
+                writeln!(w, "const _key_{} = {} + {};", component_key, key_string, item_key_string)?;
+
+                // Slot content is keyed by the instance, so that it is unique in each iteration
+                self.loop_keys.push(component_key.to_owned());
+                let result = write_comp_desc(self, w, ctx, comp_desc, item_key, Some(&format!("_key_{}", component_key)));
+                self.loop_keys.pop();
+                result?;
                 writeln!(w, "}}")?;
 
                 Ok(())
@@ -1637,3 +1839,238 @@ impl ObjectWriter<RouteActionValue<ProcessedExpression>, JsOutput> for DefaultJs
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use output::*;
+
+    #[test]
+    fn test_if_else() {
+        let src = r#"
+            use html;
+
+            component badge(n) {
+                if (n > 2) {
+                    span { ("many") }
+                } else if (n > 0) {
+                    span { ("some") }
+                } else {
+                    span { ("none") }
+                }
+            }
+
+            div {
+                badge(n = 1) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        let body = &data.component_bodies["badge"];
+        let branches: Vec<_> = body.lines().filter(|line| line.contains('{') || line.starts_with('}')).collect();
+        assert_eq!(branches, vec!["if (props.n > 2) {", "} else if (props.n > 0) {", "} else {", "}"]);
+        assert!(body.contains(r#"IncrementalDOM.text("none");"#));
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        let src = r#"
+            use html;
+
+            store {
+                let rows = [{ name: "a", cells: [1, 2] }, { name: "b", cells: [3] }];
+
+                rows {
+                    action clear => [];
+                }
+            }
+
+            component grid(rows) {
+                for row in rows {
+                    tr {
+                        for cell, i in row.cells {
+                            td { (row.name + cell + i) }
+                        }
+                    }
+                }
+            }
+
+            table {
+                grid(get rows) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        let body = &data.component_bodies["grid"];
+        let loops: Vec<_> = body.lines().filter(|line| line.starts_with("for ")).collect();
+        assert_eq!(loops.len(), 2);
+
+        // Each loop has its own item and index, and the inner loop refers to both
+        let keys: Vec<_> = loops.iter().map(|line| &line[line.find("_coll_").unwrap() + 6..line.find(')').unwrap()]).collect();
+        assert_eq!(loops[0], format!("for (const [_idx_{0}, _item_{0}] of enumerate(_coll_{0})) {{", keys[0]));
+        assert_eq!(loops[1], format!("for (const [_idx_{0}, _item_{0}] of enumerate(_coll_{0})) {{", keys[1]));
+        assert!(body.contains(&format!("const _coll_{} = values(_item_{}.cells);", keys[1], keys[0])));
+        assert!(body.contains(&format!(
+            "IncrementalDOM.text(checkedAdd(checkedAdd(_item_{}.name, _item_{}), _idx_{}));",
+            keys[0], keys[1], keys[1]
+        )));
+    }
+
+    #[test]
+    fn test_keyed_loop() {
+        let src = r#"
+            use html;
+
+            store {
+                let todos = [{ id: 7, text: "a" }, { id: 9, text: "b" }];
+
+                todos {
+                    action clear => [];
+                }
+            }
+
+            component todo_list(todos) {
+                ul {
+                    for todo in todos key = todo.id {
+                        li { (todo.text) }
+                    }
+                }
+            }
+
+            div {
+                todo_list(get todos) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        let body = &data.component_bodies["todo_list"];
+        assert!(body.contains(r#"const _key_9682f3a4 = props.key + ".9682f3a4." + (_item_9682f3a4.id);"#));
+        assert!(body.contains(r#"IncrementalDOM.elementOpen("li", _key_9682f3a4 + ".b083d09c", ["key", _key_9682f3a4 + ".b083d09c"]);"#));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let src = r#"
+            use html;
+
+            component note(text) {
+                p(title = text) { (text) }
+                raw(text)
+            }
+
+            div {
+                note(text = "3<b>\"&'") {}
+            }
+        "#;
+
+        // Strings can't close the script block, and raw values are set as inner HTML
+        let data = template_data(src);
+        assert!(data.page_render_func_body.contains(r#"noteComponent({"key": "07c655ca", text: "3\x3Cb>\"&\'"});"#));
+        let body = &data.component_bodies["note"];
+        assert!(body.contains("IncrementalDOM.text(props.text);"));
+        assert!(body.contains("if (_raw.innerHTML !== _html) { _raw.innerHTML = _html; }"));
+    }
+
+    #[test]
+    fn test_slots() {
+        let src = r#"
+            use html;
+
+            component card(title) {
+                section {
+                    header { slot header; }
+                    h2 { (title) }
+                    (children)
+                }
+            }
+
+            div {
+                card(title = "Hi") {
+                    slot header { b { ("Top") } }
+                    p { ("Body") }
+                }
+            }
+        "#;
+
+        // Slot content is passed as functions rendering it in the scope of the call site
+        let data = template_data(src);
+        assert!(data.page_render_func_body.contains(r#"cardComponent({"key": "07c655ca", title: "Hi", "slots": {"children": () => {"#));
+        assert!(data.page_render_func_body.contains(r#"}, "header": () => {"#));
+
+        let body = &data.component_bodies["card"];
+        assert!(body.contains(r#"if (props.slots && props.slots["header"]) { props.slots["header"](); }"#));
+        assert!(body.contains(r#"if (props.slots && props.slots["children"]) { props.slots["children"](); }"#));
+    }
+
+    #[test]
+    fn test_looped_component_slots() {
+        let src = r#"
+            use html;
+
+            store {
+                let xs = [{ n: "a" }, { n: "b" }];
+
+                xs {
+                    action clear => [];
+                }
+            }
+
+            component row(x) {
+                (children)
+            }
+
+            component rows(xs) {
+                ul {
+                    row(for x in xs) {
+                        li { (x.n) }
+                    }
+                }
+            }
+
+            div {
+                rows(get xs) {}
+            }
+        "#;
+
+        // Slot content is keyed by the instance it is passed to, as in the HTML
+        let data = template_data(src);
+        let body = &data.component_bodies["rows"];
+        assert!(body.contains(r#"const _key_784445fb = props.key + ".784445fb." + _idx;"#));
+        assert!(body.contains(r#"rowComponent({"key": _key_784445fb, x: _item, "slots": {"children": () => {"#));
+        assert!(body.contains(r#"IncrementalDOM.elementOpen("li", _key_784445fb + ".b0ef6064", ["key", _key_784445fb + ".b0ef6064"]);"#));
+    }
+
+    #[test]
+    fn test_keyed_component_loop() {
+        let src = r#"
+            use html;
+
+            store {
+                let todos = [{ id: 7, text: "a" }, { id: 9, text: "b" }];
+
+                todos {
+                    action clear => [];
+                }
+            }
+
+            component todo_item(todo) {
+                li { (todo.text) }
+            }
+
+            component todo_list(todos) {
+                ul {
+                    todo_item(for todo in todos key = todo.id) {}
+                }
+            }
+
+            div {
+                todo_list(get todos) {}
+            }
+        "#;
+
+        let data = template_data(src);
+        let body = &data.component_bodies["todo_list"];
+        assert!(body.contains(r#"const _key_be054303 = props.key + ".be054303." + (_item.id);"#));
+        assert!(body.contains(r#"todo_itemComponent({"key": _key_be054303, todo: _item});"#));
+        assert!(!body.contains("_idx;"));
+    }
+}
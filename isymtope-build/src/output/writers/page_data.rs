@@ -1,4 +1,5 @@
 use std::str;
+use std::io::Write;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
@@ -12,6 +13,8 @@ pub struct InternalTemplateDataBuilder {
     document_provider: Rc<DocumentProvider>,
     state_provider: Option<Rc<ReducerStateProvider>>,
     base_url: String,
+    route_match: Option<RouteMatch>,
+    form_actions: bool,
 }
 
 impl InternalTemplateDataBuilder {
@@ -19,15 +22,26 @@ impl InternalTemplateDataBuilder {
         document_provider: Rc<DocumentProvider>,
         state_provider: Option<Rc<ReducerStateProvider>>,
         base_url: &str,
+        route_match: Option<RouteMatch>,
     ) -> Self {
         InternalTemplateDataBuilder {
             document_provider: document_provider,
             state_provider: state_provider,
             base_url: base_url.to_owned(),
+            route_match: route_match,
+            form_actions: false,
         }
     }
 
+    /// Posts forms to the `_actions` url of the app, so that their actions are
+    /// dispatched by the server when the page runs without scripts
+    pub fn with_form_actions(mut self) -> Self {
+        self.form_actions = true;
+        self
+    }
+
     pub fn build(&self) -> DocumentProcessingResult<InternalTemplateData> {
+        let _element_keys = ElementKeyScope::enter("page");
         let ref document_provider = self.document_provider;
         let base_url = self.base_url.clone();
 
@@ -45,7 +59,11 @@ impl InternalTemplateDataBuilder {
         // Writers
 
         let mut js_writer = DefaultJsWriter::default();
-        let mut html_writer = DefaultHtmlWriter::default();
+        let mut html_writer = if self.form_actions {
+            DefaultHtmlWriter::with_form_action(&format!("{}_actions", base_url))
+        } else {
+            DefaultHtmlWriter::default()
+        };
         eprintln!("[page_templates] writers created");
 
         // let template_src = self::template_source()?;
@@ -165,16 +183,39 @@ impl InternalTemplateDataBuilder {
         let route_func_keys: HashMap<String, String> = doc.routes()
             .map(|r| (r.pattern().to_owned(), r.function_key().to_owned().into()))
             .collect();
-        let route_keys: Vec<String> = route_func_keys.keys().map(|s| s.to_owned()).collect();
+        let route_keys: Vec<String> = doc.routes().map(|r| r.pattern().to_owned()).collect();
         let mut route_bodies: HashMap<String, String> = Default::default();
+        let mut route_content_keys: Vec<String> = Default::default();
+        let mut route_content_bodies: HashMap<String, String> = Default::default();
 
         for route in doc.routes() {
             eprintln!("[page_templates] route: {:?}", route);
 
+            let pattern = route.pattern().to_owned();
             bytes.truncate(0);
-            js_writer.write_object(&mut bytes, &mut ctx, route.action())?;
 
-            let pattern = route.pattern().to_owned();
+            match *route.action() {
+                // Content routes select the block rendered in place of the root block
+                RouteActionValue::Block(ref block, _) => {
+                    writeln!(bytes, "window._contentRoute = \"{}\"", escape_js_string(&pattern))?;
+                    writeln!(bytes, "window._routeParams = params")?;
+                    writeln!(bytes, "store.dispatch({{type: \"@@isymtope/route\"}})")?;
+
+                    let mut content_bytes: Vec<u8> = Vec::with_capacity(8192);
+                    ctx.push_child_scope();
+                    js_writer.write_object(&mut content_bytes, &mut ctx, block)?;
+                    ctx.pop_scope();
+
+                    route_content_keys.push(pattern.clone());
+                    route_content_bodies.insert(pattern.clone(), str::from_utf8(content_bytes.as_slice())?.to_owned());
+                }
+
+                ref action => {
+                    writeln!(bytes, "window._contentRoute = null")?;
+                    js_writer.write_object(&mut bytes, &mut ctx, action)?;
+                }
+            };
+
             let body = str::from_utf8(bytes.as_slice())?.to_owned();
 
             route_bodies.insert(pattern, body);
@@ -189,6 +230,7 @@ impl InternalTemplateDataBuilder {
         let mut reducer_bodies: HashMap<String, HashMap<String, String>> = Default::default();
         let mut reducer_defaults: HashMap<String, String> = Default::default();
         let mut reducer_action_keys: HashMap<String, Vec<String>> = Default::default();
+        let mut reducer_docs: HashMap<String, String> = Default::default();
 
         // eprintln!("Document: {:?}", doc);
 
@@ -242,6 +284,10 @@ impl InternalTemplateDataBuilder {
                     }
                 };
 
+                if let Some(reducer_doc) = reducer_js_doc(reducer_key, reducer) {
+                    reducer_docs.insert(reducer_key.to_owned(), reducer_doc);
+                };
+
                 if let Some(expr) = reducer.default_value() {
                     bytes.truncate(0);
                     js_writer.write_object(&mut bytes, &mut ctx, expr)?;
@@ -289,19 +335,27 @@ impl InternalTemplateDataBuilder {
 
         // Component bodies
 
-        let component_names: Vec<_> = doc.components().map(|n| n.name().to_owned()).collect();
+        // Components are keyed by their function name, as imported components are namespaced (`todo::item`)
+        let component_names: Vec<_> = doc.components().map(|n| js_component_name(n.name())).collect();
         let mut component_bodies: HashMap<String, String> = Default::default();
+        let mut component_docs: HashMap<String, String> = Default::default();
 
         for component in doc.components() {
+            if let Some(component_doc) = component_js_doc(component) {
+                component_docs.insert(js_component_name(component.name()), component_doc);
+            };
+
             bytes.truncate(0);
             js_writer.write_object(&mut bytes, &mut ctx, component)?;
 
-            let name = component.name().to_owned();
+            let name = js_component_name(component.name());
             let body = str::from_utf8(bytes.as_slice())?.to_owned();
 
             component_bodies.insert(name, body);
         }
 
+        let type_docs: Vec<_> = doc.types().map(type_js_doc).collect();
+
         // Render root block body
         bytes.truncate(0);
 
@@ -311,9 +365,33 @@ impl InternalTemplateDataBuilder {
         // Render HTML body
         bytes.truncate(0);
 
-        // Write root_block
-        let root_block = doc.root_block();
-        html_writer.write_object(&mut bytes, &mut ctx, root_block)?;
+        // Write the block of the matched content route, or root_block
+        let content_route = self.route_match
+            .as_ref()
+            .and_then(|m| doc.routes().find(|r| r.pattern() == m.pattern()))
+            .and_then(|r| match *r.action() {
+                RouteActionValue::Block(ref block, _) => Some((r.pattern(), block)),
+                _ => None,
+            });
+
+        let mut content_route_params: Vec<String> = Default::default();
+        if let Some((_, block)) = content_route {
+            let route_match = self.route_match.as_ref().unwrap();
+
+            ctx.push_child_scope();
+            for (key, value) in route_match.params() {
+                content_route_params.push(format!("\"{}\": \"{}\"", escape_js_string(key), escape_js_string(value)));
+
+                let binding = CommonBindings::NamedRouteParam(key.to_owned(), Default::default());
+                let value = ExpressionValue::Primitive(Primitive::StringVal(value.to_owned()));
+                ctx.bind_value(binding, value)?;
+            }
+            html_writer.write_object(&mut bytes, &mut ctx, block)?;
+            ctx.pop_scope();
+        } else {
+            let root_block = doc.root_block();
+            html_writer.write_object(&mut bytes, &mut ctx, root_block)?;
+        };
 
         // TODO: Add this to Template as generationId
         let page_body_key = allocate_element_key();
@@ -331,13 +409,20 @@ impl InternalTemplateDataBuilder {
             route_keys: route_keys,
             route_func_keys: route_func_keys,
             route_bodies: route_bodies,
+            route_content_keys: route_content_keys,
+            route_content_bodies: route_content_bodies,
+            content_route: content_route.map(|(pattern, _)| escape_js_string(pattern)),
+            content_route_params: format!("{{{}}}", content_route_params.join(", ")),
             reducer_keys: reducer_keys,
             reducer_action_keys: reducer_action_keys,
             reducer_bodies: reducer_bodies,
             reducer_defaults: reducer_defaults,
+            reducer_docs: reducer_docs,
             extern_reducer_keys: extern_reducer_keys,
             component_names: component_names,
             component_bodies: component_bodies,
+            component_docs: component_docs,
+            type_docs: type_docs,
             query_names: query_names,
             query_params: query_params,
             query_bodies: query_bodies,
@@ -347,3 +432,112 @@ impl InternalTemplateDataBuilder {
         })
     }
 }
+
+//
+// JSDoc comments, from type annotations
+//
+
+fn js_doc_comment<'a, I: IntoIterator<Item = String>>(doc: Option<&'a str>, tags: I) -> String {
+    let lines = doc.into_iter()
+        .flat_map(|doc| doc.lines())
+        .map(|line| line.to_owned())
+        .chain(tags);
+
+    let mut comment = "/**\n".to_owned();
+    for line in lines {
+        comment.push_str(&format!(" * {}\n", line));
+    }
+    comment.push_str(" */");
+    comment
+}
+
+fn type_js_doc(def: &TypeDefinition) -> String {
+    let tags = Some(format!("@typedef {{Object}} {}", def.name()))
+        .into_iter()
+        .chain(def.fields().map(|&(ref name, ref ty)| format!("@property {{{}}} {}", ty.js_doc(), name)));
+
+    js_doc_comment(def.doc(), tags)
+}
+
+/// Types of the state and actions of a reducer, if any are annotated
+fn reducer_js_doc(reducer_key: &str, reducer: &Reducer<ProcessedExpression>) -> Option<String> {
+    let actions: Vec<_> = reducer.actions()
+        .map(|v| v.filter(|a| a.expr().is_some()).collect())
+        .unwrap_or_default();
+
+    let annotated = reducer.annotation().is_some() || actions.iter().any(|a| a.param_types().next().is_some());
+    if !annotated {
+        return None;
+    };
+
+    let state_ty = match (reducer.annotation().map(|a| a.ty()), reducer.default_value()) {
+        // Arrays in the store are kept as maps keyed by id
+        (
+            Some(&TypeNode::Array(box ref item_ty)),
+            Some(&ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(..)))),
+        ) => format!("Map<*, {}>", item_ty.js_doc()),
+        (Some(ty), _) => ty.js_doc(),
+        _ => "*".to_owned(),
+    };
+
+    let action_tys: Vec<_> = actions
+        .iter()
+        .map(|action| {
+            let action_type = format!("{}.{}", reducer_key.to_uppercase(), action.name().to_uppercase());
+            let param_tys = action.params().into_iter().flat_map(|v| v).map(|param| {
+                let ty = action.param_types()
+                    .find(|&(name, _)| name == param)
+                    .map(|(_, annotation)| annotation.ty().js_doc())
+                    .unwrap_or_else(|| "*".to_owned());
+                format!(", {}: {}", param, ty)
+            });
+
+            format!("{{type: \"{}\"{}}}", action_type, param_tys.collect::<String>())
+        })
+        .collect();
+
+    let mut tags = vec![format!("@param {{{}}} state", state_ty)];
+    if !action_tys.is_empty() {
+        tags.push(format!("@param {{{}}} action", action_tys.join("|")));
+    };
+
+    Some(js_doc_comment(None, tags))
+}
+
+/// Types of the props of a component, if any are annotated
+fn component_js_doc(component: &Component<ProcessedExpression>) -> Option<String> {
+    if component.param_types().next().is_none() {
+        return None;
+    };
+
+    let prop_tys: Vec<_> = component.params().into_iter().flat_map(|v| v).map(|param| {
+        let ty = component.param_types()
+            .find(|&(name, _)| name == param)
+            .map(|(_, annotation)| annotation.ty().js_doc())
+            .unwrap_or_else(|| "*".to_owned());
+        format!("{}: {}", param, ty)
+    }).collect();
+
+    let tags = Some(format!("@param {{{{{}}}}} props", prop_tys.join(", ")));
+    Some(js_doc_comment(None, tags))
+}
+
+/// Page data for a template, for tests of the output of the writers
+#[cfg(test)]
+pub fn template_data(src: &str) -> InternalTemplateData {
+    let document_provider = DocumentProvider::create(src).expect("template should be processed");
+    InternalTemplateDataBuilder::new(Rc::new(document_provider), None, "/", None)
+        .build()
+        .expect("template should be rendered")
+}
+
+/// Page data for a template with forms posted to the server, for tests of the output
+/// of the writers
+#[cfg(test)]
+pub fn form_template_data(src: &str) -> InternalTemplateData {
+    let document_provider = DocumentProvider::create(src).expect("template should be processed");
+    InternalTemplateDataBuilder::new(Rc::new(document_provider), None, "/app/test/", None)
+        .with_form_actions()
+        .build()
+        .expect("template should be rendered")
+}
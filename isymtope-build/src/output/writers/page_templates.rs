@@ -3,13 +3,16 @@ use std::rc::Rc;
 
 use error::*;
 use traits::*;
+use objects::*;
 use input::*;
 use output::*;
 
 use isymtope_data::*;
 
-#[derive(Debug, Default)]
-pub struct InternalTemplateRendererFactory;
+#[derive(Debug, Default, Clone)]
+pub struct InternalTemplateRendererFactory {
+    form_actions: bool,
+}
 
 #[derive(Debug)]
 pub struct InternalTemplateRenderer {
@@ -17,14 +20,21 @@ pub struct InternalTemplateRenderer {
 }
 
 impl InternalTemplateRendererFactory {
+    /// Factory for renderers whose forms dispatch their actions when posted to the
+    /// server, for pages served by `isymtope-server`
+    pub fn with_form_actions() -> Self {
+        InternalTemplateRendererFactory { form_actions: true }
+    }
+
     pub fn build(
         &self,
         document_provider: Rc<DocumentProvider>,
         state_provider: Option<Rc<ReducerStateProvider>>,
         base_url: &str,
+        route_match: Option<&RouteMatch>,
     ) -> DocumentProcessingResult<InternalTemplateRenderer> {
         let renderer =
-            InternalTemplateRenderer::build(document_provider, state_provider, base_url)?;
+            InternalTemplateRenderer::build(document_provider, state_provider, base_url, route_match, self.form_actions)?;
 
         eprintln!("[page_template_factory] created renderer");
         Ok(renderer)
@@ -36,12 +46,20 @@ impl InternalTemplateRenderer {
         document_provider: Rc<DocumentProvider>,
         state_provider: Option<Rc<ReducerStateProvider>>,
         base_url: &str,
+        route_match: Option<&RouteMatch>,
+        form_actions: bool,
     ) -> DocumentProcessingResult<InternalTemplateRenderer> {
         let page_data_builder = InternalTemplateDataBuilder::new(
             document_provider.clone(),
             state_provider.map(|s| s.clone()),
             base_url,
+            route_match.cloned(),
         );
+        let page_data_builder = if form_actions {
+            page_data_builder.with_form_actions()
+        } else {
+            page_data_builder
+        };
         let page_data = page_data_builder.build()?;
 
         Ok(InternalTemplateRenderer { data: page_data })
@@ -54,4 +72,9 @@ impl InternalTemplateRenderer {
         let out_buf = str::from_utf8(bytes.as_slice())?.to_owned();
         Ok(out_buf)
     }
+
+    /// Contents of the page's body element, without the scripts that run the page
+    pub fn body_html(&self) -> &str {
+        &self.data.page_body_html
+    }
 }
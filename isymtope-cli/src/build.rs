@@ -0,0 +1,289 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quicli::prelude::*;
+
+use isymtope_generate::*;
+
+use AppSource;
+
+/// File written by a static build
+#[derive(Debug)]
+pub struct BuiltFile {
+    route: String,
+    file: String,
+    status: u16,
+    location: Option<String>,
+}
+
+/// Files written by a static build, written to `manifest.json` in the output directory
+#[derive(Debug, Default)]
+pub struct Manifest {
+    routes: Vec<BuiltFile>,
+    resources: Vec<String>,
+    skipped: Vec<String>,
+}
+
+impl Manifest {
+    fn to_json(&self) -> String {
+        let routes: Vec<_> = self.routes
+            .iter()
+            .map(|f| {
+                let location = f.location
+                    .as_ref()
+                    .map(|l| format!(", \"location\": {}", json_string(l)))
+                    .unwrap_or_default();
+                format!(
+                    "    {{\"route\": {}, \"file\": {}, \"status\": {}{}}}",
+                    json_string(&f.route),
+                    json_string(&f.file),
+                    f.status,
+                    location
+                )
+            })
+            .collect();
+        let strings = |v: &[String]| -> String {
+            v.iter().map(|s| format!("    {}", json_string(s))).collect::<Vec<_>>().join(",\n")
+        };
+
+        format!(
+            "{{\n  \"routes\": [\n{}\n  ],\n  \"resources\": [\n{}\n  ],\n  \"skipped\": [\n{}\n  ]\n}}\n",
+            routes.join(",\n"),
+            strings(&self.resources),
+            strings(&self.skipped)
+        )
+    }
+}
+
+/// Renders every route with a static path to `<path>/index.html` in `out_dir`, along
+/// with `404.html` for paths not matched by a route, then copies the app's resources
+/// and writes `manifest.json`.  The page at `/` is always written, from the root block
+/// when no route matches it.  The output directory cannot be the app directory, or
+/// contain it, as resources would be copied onto themselves.
+pub fn build_app(
+    template_context: &mut DefaultTemplateContext,
+    source: &AppSource,
+    out_dir: &Path,
+    base_url: &str,
+) -> Result<Manifest> {
+    let app_root = source.app_root().canonicalize()?;
+    if out_dir.exists() && app_root.starts_with(out_dir.canonicalize()?) {
+        bail!(
+            "output directory [{}] contains the app directory, choose a directory such as [{}]",
+            out_dir.display(),
+            app_root.join("dist").display()
+        );
+    };
+
+    let mut manifest = Manifest::default();
+    fs::create_dir_all(out_dir)?;
+
+    let mut paths: Vec<String> = vec!["/".to_owned()];
+    for route in template_context.doc().routes() {
+        match route.route_pattern().static_path() {
+            Some(path) => if !paths.iter().any(|p| p.trim_matches('/') == path.trim_matches('/')) {
+                paths.push(path);
+            },
+            None if route.route_pattern().is_fallback() => {}
+            None => {
+                eprintln!("[build] skipping route [{}], which has parameters", route.pattern());
+                manifest.skipped.push(route.pattern().to_owned());
+            }
+        };
+    }
+
+    for path in paths {
+        let file = match path.trim_matches('/') {
+            "" => "index.html".to_owned(),
+            trimmed => format!("{}/index.html", trimmed),
+        };
+
+        let response = render(template_context, base_url, source, &path)?;
+        let built = write_response(response, out_dir, base_url, path, file)?;
+        manifest.routes.push(built);
+    }
+
+    // Static hosts serve 404.html for paths without a file, which is also used
+    // to start the client router on routes with parameters.
+    let not_found_path = "/404.html";
+    let response = render(template_context, base_url, source, not_found_path)?;
+    if response.is_not_found() {
+        let built = write_response(response, out_dir, base_url, "*".to_owned(), "404.html".to_owned())?;
+        manifest.routes.push(built);
+    };
+
+    copy_resources(&app_root, &app_root, out_dir, &mut manifest.resources)?;
+
+    write_to_file(out_dir.join("manifest.json"), &manifest.to_json())?;
+    Ok(manifest)
+}
+
+/// Renders the route matching `path`
+pub fn render(
+    template_context: &mut DefaultTemplateContext,
+    base_url: &str,
+    source: &AppSource,
+    path: &str,
+) -> IsymtopeGenerateResult<RenderResponse> {
+    eprintln!("[render] rendering route [{}]", path);
+    let req = TemplateRequestMsg::RenderAppRoute(
+        base_url.to_owned(),
+        source.app_name().to_owned(),
+        source.template_path().to_owned(),
+        path.to_owned(),
+    );
+
+    let TemplateResponseMsg::RenderComplete(response) = template_context.handle_msg(req)?;
+    Ok(response)
+}
+
+fn write_response(
+    response: RenderResponse,
+    out_dir: &Path,
+    base_url: &str,
+    route: String,
+    file: String,
+) -> Result<BuiltFile> {
+    let (status, location) = match *response.status() {
+        RenderStatus::Ok => (200, None),
+        RenderStatus::NotFound => (404, None),
+        RenderStatus::Redirect(ref location) => (302, Some(location.to_owned())),
+    };
+
+    // Navigation is written as a page redirecting to the location
+    let body = match location {
+        Some(ref location) => {
+            let url = format!("{}{}", base_url, location.trim_left_matches('/'));
+            format!(
+                "<!doctype HTML>\n<html>\n<head>\n  <meta http-equiv=\"refresh\" content=\"0; url={}\" />\n</head>\n</html>\n",
+                url.replace('&', "&amp;").replace('"', "&quot;")
+            )
+        }
+        None => response.into_inner(),
+    };
+
+    let dest = out_dir.join(&file);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    };
+    write_to_file(&dest, &body)?;
+
+    Ok(BuiltFile {
+        route: route,
+        file: file,
+        status: status,
+        location: location,
+    })
+}
+
+/// Copies files from the app directory other than templates, skipping hidden files
+/// and the output directory.
+fn copy_resources(
+    app_root: &Path,
+    dir: &Path,
+    out_dir: &Path,
+    resources: &mut Vec<String>,
+) -> Result<()> {
+    let out_dir = out_dir.canonicalize()?;
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<::std::io::Result<_>>()?;
+    entries.sort();
+
+    for path in entries {
+        let is_hidden = path.file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(true);
+        if is_hidden || path.canonicalize()? == out_dir {
+            continue;
+        };
+
+        if path.is_dir() {
+            copy_resources(app_root, &path, &out_dir, resources)?;
+            continue;
+        };
+
+        if path.extension().map(|ext| ext == "ism").unwrap_or(false) {
+            continue;
+        };
+
+        let relative = path.strip_prefix(app_root)?.to_owned();
+        let dest = out_dir.join(&relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        };
+        fs::copy(&path, &dest)?;
+
+        resources.push(relative.to_string_lossy().replace('\\', "/"));
+    }
+
+    Ok(())
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+    use super::*;
+
+    #[test]
+    fn test_build_app_without_routes() {
+        let dir = env::temp_dir().join(format!("isymtope-build-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.ism"), "use html;\n\ndiv { (\"Hello\") }\n").unwrap();
+
+        let source = AppSource::new(&dir.join("app.ism"));
+        let mut template_context = source.create_context().unwrap();
+        let out_dir = dir.join("dist");
+        let manifest = build_app(&mut template_context, &source, &out_dir, "/").unwrap();
+
+        let files: Vec<_> = manifest.routes.iter().map(|f| (f.file.as_str(), f.status)).collect();
+        assert_eq!(files, vec![("index.html", 200), ("404.html", 404)]);
+
+        let index = fs::read_to_string(out_dir.join("index.html")).unwrap();
+        assert!(index.contains("Hello"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_app_into_app_dir() {
+        let dir = env::temp_dir().join(format!("isymtope-build-app-dir-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.ism"), "use html;\n\ndiv { (\"Hello\") }\n").unwrap();
+        fs::write(dir.join("style.css"), "div { color: red }\n").unwrap();
+
+        let source = AppSource::new(&dir.join("app.ism"));
+        let mut template_context = source.create_context().unwrap();
+
+        // Building into the app directory, or a directory containing it, is refused
+        // before anything is written
+        assert!(build_app(&mut template_context, &source, &dir, "/").is_err());
+        assert!(build_app(&mut template_context, &source, &dir.join(".."), "/").is_err());
+        assert_eq!(fs::read_to_string(dir.join("style.css")).unwrap(), "div { color: red }\n");
+        assert!(!dir.join("index.html").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
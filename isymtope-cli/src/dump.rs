@@ -0,0 +1,106 @@
+use std::fs;
+use std::io::{self, Write};
+
+use isymtope_ast_common::*;
+use isymtope_build::{DocumentProvider, TemplateSource};
+use isymtope_build::input::parser::{lexer, parse_source};
+use isymtope_generate::*;
+
+use build;
+use {AppSource, Emit};
+
+/// Prints a stage of compiling the app's main template.  Tokens and the AST are
+/// printed for the main template only, without the templates it imports.
+pub fn dump_app<W: Write>(w: &mut W, source: &AppSource, emit: Emit) -> IsymtopeGenerateResult<()> {
+    let file_name = source.template_path().trim_left_matches('/');
+
+    match emit {
+        Emit::Tokens => {
+            let src = fs::read_to_string(source.file())?;
+            let mut errors: Vec<TemplateParseError> = Default::default();
+
+            for token in lexer::lex(&src) {
+                match token {
+                    Ok((start, token, end)) => writeln!(w, "{}..{}\t{:?}", start, end, token)?,
+                    Err(err) => {
+                        writeln!(w, "error\t{}", err)?;
+                        errors.push(err);
+                    }
+                };
+            }
+
+            let err = match errors.len() {
+                0 => return Ok(()),
+                1 => errors.remove(0),
+                _ => TemplateParseError::Multiple(errors),
+            };
+            Err(err.with_source(&src, Some(file_name)).into())
+        }
+
+        Emit::Ast => {
+            let src = fs::read_to_string(source.file())?;
+            let template = parse_source(&src, Some(file_name))?;
+
+            writeln!(w, "{:#?}", template)?;
+            Ok(())
+        }
+
+        Emit::Document => {
+            let document_provider = DocumentProvider::create(TemplateSource::TemplatePathSource(source.file()))?;
+
+            writeln!(w, "{:#?}", document_provider.doc())?;
+            Ok(())
+        }
+
+        Emit::Ops => {
+            let document_provider = DocumentProvider::create(TemplateSource::TemplatePathSource(source.file()))?;
+            write_ops(w, document_provider.doc())?;
+            Ok(())
+        }
+
+        Emit::Js => {
+            let mut template_context = source.create_context()?;
+            let response = build::render(&mut template_context, "/", source, "/")?;
+            let body = response.into_inner();
+
+            let script = main_script(&body).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "rendered page has no main script")
+            })?;
+            writeln!(w, "{}", script.trim())?;
+            Ok(())
+        }
+    }
+}
+
+/// Writes the action ops run by each route and event binding
+fn write_ops<W: Write>(w: &mut W, doc: &Document) -> io::Result<()> {
+    for route in doc.routes() {
+        match *route.action() {
+            RouteActionValue::Block(..) => writeln!(w, "route {:?} (content)", route.pattern())?,
+            RouteActionValue::Actions(ref ops, _) => {
+                writeln!(w, "route {:?}", route.pattern())?;
+                for op in ops.iter().flat_map(|ops| ops.iter()) {
+                    writeln!(w, "    {:?}", op)?;
+                }
+            }
+        };
+    }
+
+    for binding in doc.event_bindings() {
+        writeln!(w, "event {} ({})", binding.key(), binding.event_name())?;
+        for op in binding.event().actions().into_iter().flat_map(|ops| ops) {
+            writeln!(w, "    {:?}", op)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Contents of the generated `<script data-isymtope-main>` element in a rendered page
+fn main_script(page: &str) -> Option<&str> {
+    let start = page.find("<script data-isymtope-main")?;
+    let body_start = start + page[start..].find('>')? + 1;
+    let body_end = body_start + page[body_start..].find("</script>")?;
+
+    Some(&page[body_start..body_end])
+}
@@ -3,69 +3,283 @@ extern crate dotenv;
 extern crate quicli;
 
 extern crate isymtope_ast_common;
+extern crate isymtope_build;
 extern crate isymtope_generate;
+extern crate isymtope_server;
 
-use std::collections::HashMap;
-use std::env;
+use std::fmt;
 use std::fs::File;
-use std::path::PathBuf;
-use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::io::{self, Write};
+use std::str::FromStr;
 
 use dotenv::dotenv;
 use quicli::prelude::*;
 
 use isymtope_generate::*;
 
-/// Compile Isymtope files to static pages
+mod build;
+mod dump;
+mod serve;
+
+/// Print the rendered source diagnostic for template parse and type errors
+fn report_parse_error(err: IsymtopeGenerateError) -> IsymtopeGenerateError {
+    if let Some(diagnostic) = err.diagnostic() {
+        eprint!("{}", diagnostic);
+    }
+    err
+}
+
+/// Exit status of `check` when the template has errors
+const EXIT_TEMPLATE_ERROR: i32 = 1;
+/// Exit status of `check` when the template cannot be read
+const EXIT_IO_ERROR: i32 = 2;
+
+/// Compile, render and serve Isymtope apps
 #[derive(Debug, StructOpt)]
-struct Cli {
-    #[structopt(long = "base-url")]
-    base_url: String,
+enum Cli {
+    /// Parse and process a template, reporting any errors
+    #[structopt(name = "check")]
+    Check {
+        /// Main template of the app
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+
+    /// Render a single route to stdout
+    #[structopt(name = "render")]
+    Render {
+        /// Main template of the app
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        /// Route path to render
+        #[structopt(default_value = "/")]
+        path: String,
+
+        #[structopt(long = "base-url", default_value = "/")]
+        base_url: String,
+
+        /// Output file, instead of stdout
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Render every route into a directory, with app resources and a manifest
+    #[structopt(name = "build")]
+    Build {
+        /// Main template of the app
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        #[structopt(long = "base-url", default_value = "/")]
+        base_url: String,
+
+        /// Output directory
+        #[structopt(long = "output", short = "o", default_value = "dist", parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Run the app with isymtope-server
+    #[structopt(name = "serve")]
+    Serve {
+        /// Main template of the app
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        /// Address to listen on
+        #[structopt(long = "addr", default_value = "127.0.0.1:3000")]
+        addr: String,
+    },
+
+    /// Print an intermediate stage of the compiler, for debugging
+    #[structopt(name = "dump")]
+    Dump {
+        /// Main template of the app
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        /// Stage to print
+        #[structopt(long = "emit", default_value = "document",
+                    raw(possible_values = "&[\"tokens\", \"ast\", \"document\", \"ops\", \"js\"]"))]
+        emit: Emit,
+    },
+}
+
+/// Compiler stage printed by `dump`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Document,
+    Ops,
+    Js,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "document" => Ok(Emit::Document),
+            "ops" => Ok(Emit::Ops),
+            "js" => Ok(Emit::Js),
+            _ => Err(format!("unknown stage [{}]", s)),
+        }
+    }
+}
+
+/// App loaded from the path of its main template.  The directory containing the
+/// template is the app root, which imports and resources are resolved against.
+#[derive(Debug)]
+pub struct AppSource {
+    file: PathBuf,
+    app_root: PathBuf,
+    app_name: String,
+    template_path: String,
+}
+
+impl AppSource {
+    pub fn new(file: &Path) -> Self {
+        let app_root = match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+            _ => PathBuf::from("."),
+        };
+
+        let app_name = app_root
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "app".to_owned());
+
+        let template_path = file.file_name()
+            .map(|s| format!("/{}", s.to_string_lossy()))
+            .unwrap_or_else(|| "/app.ism".to_owned());
+
+        AppSource {
+            file: file.to_owned(),
+            app_root: app_root,
+            app_name: app_name,
+            template_path: template_path,
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn app_root(&self) -> &Path {
+        &self.app_root
+    }
+
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    pub fn template_path(&self) -> &str {
+        &self.template_path
+    }
+
+    /// Parses and processes the template
+    pub fn create_context(&self) -> IsymtopeGenerateResult<DefaultTemplateContext> {
+        DefaultTemplateContext::create(&self.app_root, &self.template_path)
+    }
+}
+
+/// Error, followed by its causes, as reported for errors without a source diagnostic
+struct ErrorChain<'a>(&'a IsymtopeGenerateError);
+
+impl<'a> fmt::Display for ErrorChain<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error: {}", self.0)?;
+        for cause in self.0.causes().skip(1) {
+            write!(f, ": {}", cause)?;
+        }
+        writeln!(f)
+    }
+}
+
+fn check(source: &AppSource) -> i32 {
+    if !source.file().is_file() {
+        eprintln!("error: cannot read template [{}]", source.file().display());
+        return EXIT_IO_ERROR;
+    };
 
-    #[structopt(long = "template-path")]
-    template_path: Option<String>,
+    match source.create_context() {
+        Ok(_) => {
+            eprintln!("{}: ok", source.file().display());
+            0
+        }
 
-    #[structopt(long = "app-name")]
-    app_name: Option<String>,
+        Err(IsymtopeGenerateError::IOError(ref err)) => {
+            eprintln!("error: cannot read template [{}]: {}", source.file().display(), err);
+            EXIT_IO_ERROR
+        }
 
-    #[structopt(long = "output", short = "o")]
-    output: String,
+        Err(ref err) => {
+            match err.diagnostic() {
+                Some(diagnostic) => eprint!("{}", diagnostic),
+                None => eprint!("{}", ErrorChain(err)),
+            };
+            EXIT_TEMPLATE_ERROR
+        }
+    }
 }
 
 main!(|args: Cli| {
     dotenv().ok();
 
-    let mut template_cache: HashMap<String, DefaultTemplateContext> = Default::default();
-    let app_dir: PathBuf = env::var_os("APP_DIR").expect("APP_DIR must be provided").into();
+    match args {
+        Cli::Check { file } => {
+            let status = check(&AppSource::new(&file));
+            process::exit(status);
+        }
 
-    let app_name = args.app_name.as_ref().map(|s| s.to_owned())
-        .unwrap_or_else(|| env::var_os("DEFAULT_APP").expect("DEFAULT_APP must be provided if --app-name is not supplied").to_string_lossy().to_string());
+        Cli::Render { file, path, base_url, output } => {
+            let source = AppSource::new(&file);
+            let mut template_context = source.create_context().map_err(report_parse_error)?;
 
-    let app_root = app_dir.join(&app_name);
-    let template_path = args.template_path.as_ref().map(|s| s.to_owned()).unwrap_or_else(|| "/app.ism".to_owned());
-    let template_key = format!("[templatePath={}]", template_path);
+            let response = build::render(&mut template_context, &base_url, &source, &path)
+                .map_err(report_parse_error)?;
+            match *response.status() {
+                RenderStatus::Ok => {}
+                RenderStatus::NotFound => eprintln!("[render] no route matches [{}]", path),
+                RenderStatus::Redirect(ref location) => {
+                    eprintln!("[render] route [{}] navigates to [{}]", path, location);
+                }
+            };
+            let body = response.into_inner();
 
-    let base_url = args.base_url;
-    let path = "/";
+            match output {
+                Some(output) => File::create(output)?.write_all(body.as_bytes())?,
+                None => io::stdout().write_all(body.as_bytes())?,
+            };
+        }
 
-    let template_context = template_cache
-        .entry(template_key.clone())
-        .or_insert_with(|| DefaultTemplateContext::create(&app_root, &template_path).unwrap());
+        Cli::Build { file, base_url, output } => {
+            let source = AppSource::new(&file);
+            let mut template_context = source.create_context().map_err(report_parse_error)?;
 
+            let manifest = build::build_app(&mut template_context, &source, &output, &base_url)?;
+            eprintln!("[build] wrote {:?}: {:?}", output, manifest);
+        }
 
-    let req = TemplateRequestMsg::RenderAppRoute(
-        base_url.to_owned(),
-        app_name.to_owned(),
-        template_path.to_owned(),
-        path.to_owned(),
-    );
+        Cli::Serve { file, addr } => {
+            let source = AppSource::new(&file);
+            source.create_context().map_err(report_parse_error)?;
 
-    let response = template_context.handle_msg(req)?;
-    let TemplateResponseMsg::RenderComplete(result) = response;
-    let body = result.into_inner();
+            serve::serve_app(&source, &addr)?;
+        }
 
-    let mut f = File::create(args.output)?;
-    f.write_all(body.as_bytes())?;
+        Cli::Dump { file, emit } => {
+            let source = AppSource::new(&file);
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
 
-    // eprintln!("{}", body);
+            dump::dump_app(&mut out, &source, emit).map_err(report_parse_error)?;
+        }
+    };
 });
@@ -0,0 +1,31 @@
+use std::env;
+use std::path::PathBuf;
+
+use quicli::prelude::*;
+
+use isymtope_server;
+
+use AppSource;
+
+/// Serves the app with `isymtope-server`, as the app named by its directory within
+/// the directory containing it.  The server is configured from the environment, so
+/// this sets the variables otherwise given in its `.env` file.
+pub fn serve_app(source: &AppSource, addr: &str) -> Result<()> {
+    if source.template_path() != "/app.ism" {
+        bail!("the server renders [app.ism] in the app directory, not [{}]", source.file().display());
+    };
+
+    let app_root = source.app_root().canonicalize()?;
+    let app_dir = app_root.parent().map(|p| p.to_owned()).unwrap_or_else(|| PathBuf::from("/"));
+
+    env::set_var("APP_DIR", &app_dir);
+    env::set_var("DEFAULT_APP", source.app_name());
+    if env::var_os("STATIC_RESOURCE_DIR").is_none() {
+        env::set_var("STATIC_RESOURCE_DIR", &app_root);
+    };
+
+    eprintln!("[serve] serving [{}] at http://{}/app/{}/", source.file().display(), addr, source.app_name());
+    isymtope_server::run_server(addr)?;
+
+    Ok(())
+}
@@ -4,8 +4,57 @@ const rootPath = baseUrl.length ? baseUrl + '/' : ''
 const mapRoute = href => rootPath + href.replace(/^\/+/, '').replace(/\/+$/, '')
 const navigate = href => ({ type: '@@redux-routing/navigate', href: mapRoute(href) })
 
+// Route matching, mirroring RoutePattern in isymtope-ast-common: paths are split on '/'
+// with empty segments ignored; static segments take precedence over :params, which
+// take precedence over *wildcards, comparing from the left, then declaration order.
+const STATIC_RANK = 3, PARAM_RANK = 2, END_RANK = 1, WILDCARD_RANK = 0
+
+const splitPath = path => path.split(/[?#]/)[0].split('/').filter(s => s.length)
+const stripRoot = href => baseUrl.length && (href + '/').indexOf(rootPath) === 0 ? href.slice(baseUrl.length) : href
+
+function decodeSegment(s) {
+    try { return decodeURIComponent(s) } catch (e) { return s }
+}
+
+function parseRoute(pattern) {
+    return splitPath(pattern).map(part => {
+        switch (part[0]) {
+            case ':': return { rank: PARAM_RANK, name: part.slice(1) }
+            case '*': return { rank: WILDCARD_RANK, name: part.slice(1) }
+            default: return { rank: STATIC_RANK, value: part }
+        }
+    })
+}
+
+function matchRoute(segments, parts) {
+    const params = {}
+    for (let idx = 0; idx < segments.length; idx++) {
+        const segment = segments[idx]
+        if (segment.rank === WILDCARD_RANK) {
+            if (segment.name.length) { params[segment.name] = parts.slice(idx).map(decodeSegment).join('/') }
+            return params
+        }
+        if (idx >= parts.length) { return null }
+        if (segment.rank === STATIC_RANK) {
+            if (segment.value !== parts[idx]) { return null }
+        } else {
+            params[segment.name] = decodeSegment(parts[idx])
+        }
+    }
+    return parts.length === segments.length ? params : null
+}
+
+function cmpPrecedence(a, b) {
+    const ranks = route => route.segments.map(s => s.rank).concat([END_RANK])
+    const ra = ranks(a), rb = ranks(b)
+    for (let idx = 0; idx < Math.min(ra.length, rb.length); idx++) {
+        if (ra[idx] !== rb[idx]) { return ra[idx] - rb[idx] }
+    }
+    return ra.length - rb.length
+}
+
 function buildRoutes(routes) {
-    return  Object.keys(routes).map(pattern =>({ regex: new RegExp('^' + mapRoute(pattern.replace(/\//g, '\/') + '$')), handler: routes[pattern].handler }))
+    return Object.keys(routes).map(pattern => ({ pattern, segments: parseRoute(pattern), handler: routes[pattern].handler }))
 }
 
 function createHistory(_window, store) {
@@ -52,12 +101,24 @@ class RouterRuntime {
         this._routes = buildRoutes(routes)
     }
 
+    match(href) {
+        const parts = splitPath(stripRoot(href))
+        let best = null
+        this._routes.forEach(route => {
+            const params = matchRoute(route.segments, parts)
+            if (params && (!best || cmpPrecedence(route, best.route) > 0)) {
+                best = { route, params }
+            }
+        })
+        return best
+    }
+
     handle(action, store) {
-        var href = action.href;
-        var match = this._routes.filter(function(route) { return !!route.regex.exec(href); })[0];
+        const href = action.href
+        const match = this.match(href)
 
-        if (match && match.handler) {
-            match.handler(href, store);
+        if (match && match.route.handler) {
+            match.route.handler(href, store, match.params)
         }
     }
 }
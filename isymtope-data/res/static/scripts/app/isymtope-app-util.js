@@ -143,6 +143,39 @@ function* flatten() {
     }
   }
 
+// Arithmetic with the same semantics as server-side evaluation: integer
+// overflow and division by zero throw instead of producing Infinity/NaN.
+const isInt32 = n => typeof n === 'number' && (n | 0) === n
+
+function checkedNumber(a, b, result) {
+    if (typeof a !== 'number' || typeof b !== 'number') {
+        return result
+    }
+
+    if (isInt32(a) && isInt32(b) && Number.isInteger(result) && !isInt32(result)) {
+        throw new RangeError('Numeric overflow')
+    }
+
+    if (!Number.isFinite(result) && Number.isFinite(a) && Number.isFinite(b)) {
+        throw new RangeError('Numeric overflow')
+    }
+
+    return result
+}
+
+function checkedDivisor(b) {
+    if (b === 0) {
+        throw new RangeError('Division by zero')
+    }
+}
+
+const checkedAdd = (a, b) => checkedNumber(a, b, a + b)
+const checkedSub = (a, b) => checkedNumber(a, b, a - b)
+const checkedMul = (a, b) => checkedNumber(a, b, a * b)
+const checkedDiv = (a, b) => (checkedDivisor(b), checkedNumber(a, b, a / b))
+const checkedMod = (a, b) => (checkedDivisor(b), checkedNumber(a, b, a % b))
+const checkedNeg = a => checkedNumber(a, 0, -a)
+
 const mapFunc = f => arr => map(f, arr)
 const enumerateFunc = arr => enumerate(arr)
 const takeFunc = n => arr => take(n, arr)
@@ -193,5 +226,11 @@ Object.assign(exports, {
     values,
     asMap,
     asMapFunc,
-    flatten
+    flatten,
+    checkedAdd,
+    checkedSub,
+    checkedMul,
+    checkedDiv,
+    checkedMod,
+    checkedNeg
 })
@@ -7,6 +7,10 @@ pub struct InternalTemplateData {
     pub route_keys: Vec<String>,
     pub route_func_keys: HashMap<String, String>,
     pub route_bodies: HashMap<String, String>,
+    pub route_content_keys: Vec<String>,
+    pub route_content_bodies: HashMap<String, String>,
+    pub content_route: Option<String>,
+    pub content_route_params: String,
     pub event_keys: Vec<String>,
     pub event_enterkeyflags: HashMap<String, bool>,
     pub event_action_keys: HashMap<String, Vec<String>>,
@@ -15,12 +19,15 @@ pub struct InternalTemplateData {
     pub reducer_action_keys: HashMap<String, Vec<String>>,
     pub reducer_bodies: HashMap<String, HashMap<String, String>>,
     pub reducer_defaults: HashMap<String, String>,
+    pub reducer_docs: HashMap<String, String>,
     pub extern_reducer_keys: Vec<String>,
     pub query_names: Vec<String>,
     pub query_params: HashMap<String, Vec<String>>,
     pub query_bodies: HashMap<String, String>,
     pub component_names: Vec<String>,
     pub component_bodies: HashMap<String, String>,
+    pub component_docs: HashMap<String, String>,
+    pub type_docs: Vec<String>,
     pub page_render_func_body: String,
     pub page_body_key: String,
     pub page_body_html: String,
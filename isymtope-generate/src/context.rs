@@ -4,6 +4,9 @@ use std::rc::Rc;
 use isymtope_build::*;
 use super::*;
 
+/// Maximum number of navigations followed when rendering a route
+const MAX_REDIRECTS: usize = 8;
+
 pub trait TemplateContext {
     fn handle_msg(
         &mut self,
@@ -16,6 +19,7 @@ pub struct DefaultTemplateContext {
     router: Router,
     executor: ActionExecutor,
     document_provider: Rc<DocumentProvider>,
+    renderer_factory: InternalTemplateRendererFactory,
 }
 
 impl DefaultTemplateContext {
@@ -26,9 +30,20 @@ impl DefaultTemplateContext {
             router: router,
             executor: Default::default(),
             document_provider: document_provider,
+            renderer_factory: Default::default(),
         }
     }
 
+    /// Renders forms which dispatch their actions when posted to the server
+    pub fn with_form_actions(mut self) -> Self {
+        self.renderer_factory = InternalTemplateRendererFactory::with_form_actions();
+        self
+    }
+
+    pub fn doc(&self) -> &Document {
+        self.document_provider.doc()
+    }
+
     pub fn create(
         app_root: &Path,
         template_path: &str
@@ -49,6 +64,158 @@ impl DefaultTemplateContext {
     }
 }
 
+impl DefaultTemplateContext {
+    /// Sets each reducer in a new session to its default value
+    pub fn initialize_session(&self, state: &mut Session) -> IsymtopeGenerateResult<()> {
+        let ref document_provider = self.document_provider;
+        let mut ctx = DefaultOutputContext::create(document_provider.clone(), None);
+
+        self.executor
+            .initialize_session_data(state, document_provider.doc(), &mut ctx)
+    }
+
+    /// Executes the actions of the route matching a path in a session.  Expressions
+    /// in the actions see the state of the session from before the route.
+    fn execute_route(
+        &self,
+        state: &mut MemorySession,
+        path: &str,
+    ) -> IsymtopeGenerateResult<(Option<RouteMatch>, ActionOutcome)> {
+        let ref document_provider = self.document_provider;
+        let mut ctx = DefaultOutputContext::create(
+            document_provider.clone(),
+            Some(Rc::new(state.clone())),
+        );
+
+        eprintln!("Processing route: {} in document", path);
+
+        // Paths not matched by any route render the root block, as not found except at `/`
+        let route_match = self.router.match_route(path);
+        let outcome = match route_match {
+            Some(ref route_match) => self.executor.execute_document_route(
+                state,
+                document_provider.doc(),
+                &mut ctx,
+                route_match,
+            )?,
+            None => ActionOutcome::Render,
+        };
+
+        Ok((route_match, outcome))
+    }
+
+    /// Renders the route matching a path with the state of a session, keeping the
+    /// changes made by its actions.  The session is left unchanged if rendering fails.
+    pub fn render_route(
+        &self,
+        state: &mut MemorySession,
+        base_url: &str,
+        path: &str,
+    ) -> IsymtopeGenerateResult<RenderResponse> {
+        let ref document_provider = self.document_provider;
+        let mut route_state = state.clone();
+        let (route_match, outcome) = self.execute_route(&mut route_state, path)?;
+
+        // Navigation is followed here, so that the client is only redirected once.  The
+        // actions of the final route are left to run when the client requests it.
+        if let ActionOutcome::Navigate(mut location) = outcome {
+            let mut visited = vec![path.to_owned()];
+            loop {
+                let is_loop = visited
+                    .iter()
+                    .any(|p| p.trim_right_matches('/') == location.trim_right_matches('/'));
+                visited.push(location.clone());
+                if is_loop || visited.len() > MAX_REDIRECTS {
+                    return Err(IsymtopeGenerateError::RedirectLoop(RedirectChain(visited)));
+                };
+
+                let mut next_state = route_state.clone();
+                match self.execute_route(&mut next_state, &location)?.1 {
+                    ActionOutcome::Navigate(next) => {
+                        route_state = next_state;
+                        location = next;
+                    }
+                    ActionOutcome::Render => break,
+                };
+            }
+
+            *state = route_state;
+            return Ok(RenderResponse::redirect(location));
+        };
+
+        *state = route_state.clone();
+
+        let renderer = self.renderer_factory.build(
+            document_provider.clone(),
+            Some(Rc::new(route_state)),
+            base_url,
+            route_match.as_ref(),
+        )?;
+        let body = renderer.render()?;
+
+        // The root block is the page at `/`, even when no route matches it
+        let response = match route_match {
+            Some(ref m) if !m.is_fallback() => RenderResponse::new(body),
+            None if path.trim_matches('/').is_empty() => RenderResponse::new(body),
+            _ => RenderResponse::not_found(body),
+        };
+        Ok(response)
+    }
+}
+
+impl DefaultTemplateContext {
+    /// Executes a store action dispatched by a client, such as `COUNTER.INCREMENT`, in a session
+    pub fn execute_action(
+        &self,
+        state: &mut MemorySession,
+        action_ty: &str,
+        params: &[(String, ExpressionValue<OutputExpression>)],
+    ) -> IsymtopeGenerateResult<()> {
+        let ref document_provider = self.document_provider;
+        let doc = document_provider.doc();
+
+        if !self.executor.has_store_action(doc, action_ty) {
+            return Err(IsymtopeGenerateError::UnknownAction(action_ty.to_owned()));
+        };
+
+        let mut action_state = state.clone();
+        let mut ctx = DefaultOutputContext::create(
+            document_provider.clone(),
+            Some(Rc::new(state.clone())),
+        );
+        self.executor.execute_store_action(
+            &mut action_state,
+            doc,
+            &mut ctx,
+            action_ty,
+            Some(params.iter().map(|p| (p.0.as_str(), &p.1))),
+        )?;
+
+        *state = action_state;
+        Ok(())
+    }
+
+    /// Renders the body of the route matching a path with the state of a session,
+    /// without executing the route's actions
+    pub fn render_fragment(
+        &self,
+        state: &MemorySession,
+        base_url: &str,
+        path: &str,
+    ) -> IsymtopeGenerateResult<String> {
+        let route_match = self.router.match_route(path);
+
+        let renderer = self.renderer_factory.build(
+            self.document_provider.clone(),
+            Some(Rc::new(state.clone())),
+            base_url,
+            route_match.as_ref(),
+        )?;
+
+        Ok(renderer.body_html().to_owned())
+    }
+}
+
 impl TemplateContext for DefaultTemplateContext {
     fn handle_msg(
         &mut self,
@@ -61,46 +228,118 @@ impl TemplateContext for DefaultTemplateContext {
                 ref _template_path,
                 ref path,
             ) => {
-                let ref document_provider = self.document_provider;
+                // Render in a temporary session with the default state
+                let mut state = MemorySession::default();
+                self.initialize_session(&mut state)?;
+
+                let response = self.render_route(&mut state, base_url, path)?;
+                Ok(TemplateResponseMsg::RenderComplete(response))
+            }
+        }
+    }
+}
 
-                // Create temporary session with default state
-                let mut default_state = MemorySession::default();
-                let mut default_ctx = DefaultOutputContext::create(document_provider.clone(), None);
-                self.executor.initialize_session_data(
-                    &mut default_state,
-                    document_provider.doc(),
-                    &mut default_ctx,
-                )?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let mut ctx = DefaultOutputContext::create(
-                    document_provider.clone(),
-                    Some(Rc::new(default_state)),
-                );
+    fn template_context(src: &str) -> DefaultTemplateContext {
+        DefaultTemplateContext::new(Rc::new(DocumentProvider::create(src).unwrap()))
+    }
 
-                eprintln!("Processing route: {} in document", path);
+    fn new_session(template_context: &DefaultTemplateContext) -> MemorySession {
+        let mut state = MemorySession::default();
+        template_context.initialize_session(&mut state).unwrap();
+        state
+    }
 
-                // Create temporary session for this route
-                let mut state = MemorySession::default();
-                self.executor.initialize_session_data(
-                    &mut state,
-                    document_provider.doc(),
-                    &mut ctx,
-                )?;
-                self.executor.execute_document_route(
-                    &mut state,
-                    document_provider.doc(),
-                    &mut ctx,
-                    path,
-                )?;
-
-                let factory = InternalTemplateRendererFactory::default();
-                let renderer =
-                    factory.build(document_provider.clone(), Some(Rc::new(state)), base_url)?;
-                let body = renderer.render()?;
-
-                let response = RenderResponse::new(body);
-                Ok(TemplateResponseMsg::RenderComplete(response))
+    fn counter(state: &MemorySession) -> Option<ExpressionValue<OutputExpression>> {
+        state.get_value("counter").unwrap().cloned()
+    }
+
+    const NAVIGATION_SRC: &str = "use html;
+        store { let counter = 0; counter { action increment => value + 1; } }
+        div { (get counter) }
+        route \"/\" || {}
+        route \"/login\" || { dispatch increment to counter; }
+        route \"/go\" || { navigate \"/login\"; }
+        route \"/chain\" || { dispatch increment to counter; navigate \"/old\"; }
+        route \"/old\" || { dispatch increment to counter; navigate \"/login\"; }
+        route \"/a\" || { navigate \"/b\"; }
+        route \"/b\" || { navigate \"/a/\"; }";
+
+    #[test]
+    fn test_render_route_navigate() {
+        let template_context = template_context(NAVIGATION_SRC);
+        let mut state = new_session(&template_context);
+
+        let response = template_context.render_route(&mut state, "/app/test/", "/go").unwrap();
+        assert_eq!(response.status(), &RenderStatus::Redirect("/login".to_owned()));
+        assert_eq!(response.into_inner(), "");
+
+        // The actions of the route navigated to run when it is requested
+        assert_eq!(counter(&state), Some(ExpressionValue::Primitive(Primitive::Int32Val(0))));
+    }
+
+    #[test]
+    fn test_render_route_navigate_chain() {
+        let template_context = template_context(NAVIGATION_SRC);
+        let mut state = new_session(&template_context);
+
+        // One redirect to the end of the chain, keeping the actions of the routes on the way
+        let response = template_context.render_route(&mut state, "/app/test/", "/chain").unwrap();
+        assert_eq!(response.redirect_location(), Some("/login"));
+        assert_eq!(counter(&state), Some(ExpressionValue::Primitive(Primitive::Int32Val(2))));
+    }
+
+    #[test]
+    fn test_render_route_redirect_loop() {
+        let template_context = template_context(NAVIGATION_SRC);
+        let mut state = new_session(&template_context);
+
+        match template_context.render_route(&mut state, "/app/test/", "/a") {
+            Err(IsymtopeGenerateError::RedirectLoop(RedirectChain(ref visited))) => {
+                assert_eq!(visited, &vec!["/a".to_owned(), "/b".to_owned(), "/a/".to_owned()]);
             }
+            other => panic!("expected a redirect loop, got {:?}", other),
+        };
+    }
+
+    fn render(src: &str, path: &str) -> RenderResponse {
+        let template_context = template_context(src);
+        let mut state = new_session(&template_context);
+        template_context.render_route(&mut state, "/app/test/", path).unwrap()
+    }
+
+    #[test]
+    fn test_render_route_status() {
+        let src = "use html; div { (\"page\") } route \"/about\" || {}";
+
+        // The root block is the page at `/` without a route for it
+        let response = render(src, "/");
+        assert_eq!(response.status(), &RenderStatus::Ok);
+        assert!(response.into_inner().contains(">page</div>"));
+
+        let response = render(src, "/about");
+        assert_eq!(response.status(), &RenderStatus::Ok);
+        assert!(response.into_inner().contains(">page</div>"));
+
+        let response = render(src, "/missing");
+        assert_eq!(response.status(), &RenderStatus::NotFound);
+        assert!(response.into_inner().contains(">page</div>"));
+    }
+
+    #[test]
+    fn test_render_route_fallback_status() {
+        for fallback in &["route fallback || {}", "route \"*\" || {}"] {
+            let src = format!("use html; div {{ (\"page\") }} route \"/about\" || {{}} {}", fallback);
+
+            let response = render(&src, "/about");
+            assert_eq!(response.status(), &RenderStatus::Ok);
+
+            let response = render(&src, "/missing");
+            assert_eq!(response.status(), &RenderStatus::NotFound);
+            assert!(response.into_inner().contains(">page</div>"));
         }
     }
 }
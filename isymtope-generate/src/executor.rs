@@ -3,10 +3,51 @@ use std::collections::HashMap;
 use isymtope_ast_common::*;
 use super::*;
 
+/// Result of executing action ops on the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// Continue rendering the current route
+    Render,
+    /// Navigate to another path within the app
+    Navigate(String),
+}
+
 #[derive(Debug, Default)]
 pub struct ActionExecutor {}
 
+/// Actions of each reducer, keyed by action type (`REDUCER.ACTION`), with the key of their reducer
+fn store_actions(doc: &Document) -> HashMap<String, (String, &ReducerAction<ProcessedExpression>)> {
+    let reducers: HashMap<_, _> = doc.reducers()
+        .map(|v| v.map(|(key, reducer)| (key.to_owned(), reducer)).collect())
+        .unwrap_or_default();
+
+    reducers
+        .iter()
+        .flat_map(|(reducer_key, reducer)| {
+            let actions: Vec<_> = reducer
+                .actions()
+                .map(|v| {
+                    v.map(|action| {
+                        let complete_action = format!(
+                            "{}.{}",
+                            reducer_key.to_uppercase(),
+                            action.name().to_uppercase()
+                        );
+                        (complete_action, (reducer_key.to_owned(), action))
+                    }).collect()
+                })
+                .unwrap_or_default();
+            actions.into_iter()
+        })
+        .collect()
+}
+
 impl ActionExecutor {
+    /// Whether the document has a reducer action of the given type
+    pub fn has_store_action(&self, doc: &Document, action_ty: &str) -> bool {
+        store_actions(doc).contains_key(&action_ty.to_uppercase())
+    }
+
     pub fn initialize_session_data(
         &self,
         state: &mut Session,
@@ -39,29 +80,7 @@ impl ActionExecutor {
         action_ty: &str,
         params: Option<P>,
     ) -> IsymtopeGenerateResult<()> {
-        let reducers: HashMap<_, _> = doc.reducers()
-            .map(|v| v.map(|(key, reducer)| (key.to_owned(), reducer)).collect())
-            .unwrap_or_default();
-
-        let actions: HashMap<_, _> = reducers
-            .iter()
-            .flat_map(|(reducer_key, reducer)| {
-                let actions: Vec<_> = reducer
-                    .actions()
-                    .map(|v| {
-                        v.map(|action| {
-                            let complete_action = format!(
-                                "{}.{}",
-                                reducer_key.to_uppercase(),
-                                action.name().to_uppercase()
-                            );
-                            (complete_action, (reducer_key.to_owned(), action))
-                        }).collect()
-                    })
-                    .unwrap_or_default();
-                actions.into_iter()
-            })
-            .collect();
+        let actions = store_actions(doc);
         eprintln!("[server/executor] actions: {:?}", actions);
 
         if let Some(&(ref reducer_key, ref action)) = actions.get(&action_ty.to_uppercase()) {
@@ -82,6 +101,14 @@ impl ActionExecutor {
             let expr = expr.unwrap();
 
             ctx.push_child_scope();
+
+            // The action computes the new value from the current value in the session
+            let current_value = match state.get_value(reducer_key)? {
+                Some(value) => value.to_owned(),
+                None => ctx.reducer_value(reducer_key)?,
+            };
+            ctx.bind_loop_value(CommonBindings::CurrentReducerState(Default::default()), current_value)?;
+
             if let Some(params) = params {
                 for (key, value) in params {
                     eprintln!(
@@ -90,10 +117,6 @@ impl ActionExecutor {
                     );
                     let binding =
                         CommonBindings::NamedReducerActionParam(key.to_owned(), Default::default());
-                    eprintln!(
-                        "[server/executor] adding binding [{:?}] with value [{:?}]",
-                        binding, value
-                    );
                     ctx.bind_value(binding, value.to_owned())?;
                 }
             };
@@ -109,7 +132,11 @@ impl ActionExecutor {
                 "[server/executor] setting reducer key [{}] to value [{:?}]",
                 reducer_key, expr
             );
+            #[cfg(feature = "types")]
+            state.set_value_with_type(&reducer_key, expr)?;
+            #[cfg(not(feature = "types"))]
             state.set_value(&reducer_key, expr, true)?;
+
             ctx.pop_scope();
         };
 
@@ -122,7 +149,7 @@ impl ActionExecutor {
         doc: &Document,
         ctx: &mut OutputContext,
         action_op: &ActionOp<ProcessedExpression>,
-    ) -> IsymtopeGenerateResult<()> {
+    ) -> IsymtopeGenerateResult<ActionOutcome> {
         match *action_op {
             ActionOp::DispatchAction(ref action_ty, ref params, _) => {
                 let params: Vec<_> = params
@@ -167,15 +194,22 @@ impl ActionExecutor {
                 )?;
             }
 
-            _ => {
-                return Err(try_eval_from_err!(format!(
-                    "Unsupported action_op for server execution: {:?}",
-                    action_op
-                )))?;
+            ActionOp::Navigate(ref path, _) => {
+                let path: ExpressionValue<OutputExpression> = TryEvalFrom::try_eval_from(path, ctx)?;
+
+                return match path {
+                    ExpressionValue::Primitive(Primitive::StringVal(path)) => {
+                        Ok(ActionOutcome::Navigate(path))
+                    }
+                    _ => Err(try_eval_from_err!(format!(
+                        "Navigate requires a string path, but was given [{:?}]",
+                        path
+                    )))?,
+                };
             }
         };
 
-        Ok(())
+        Ok(ActionOutcome::Render)
     }
 
     pub fn execute_document_route(
@@ -183,36 +217,48 @@ impl ActionExecutor {
         state: &mut Session,
         doc: &Document,
         ctx: &mut OutputContext,
-        path: &str,
-    ) -> IsymtopeGenerateResult<()> {
-        let routes: HashMap<String, Route<ProcessedExpression>> = doc.routes()
-            .map(|r| (r.pattern().to_owned(), r.to_owned()))
-            .collect();
-        let route = routes.get(path);
+        route_match: &RouteMatch,
+    ) -> IsymtopeGenerateResult<ActionOutcome> {
+        let route = doc.routes().find(|r| r.pattern() == route_match.pattern());
 
         if route.is_none() {
             return Err(try_eval_from_err!("Invalid route"))?;
         }
 
-        let action = route.unwrap().action();
-        match *action {
-            RouteActionValue::Actions(ref v, _) => {
-                if let Some(ref v) = *v {
-                    for action_op in v {
-                        eprintln!("[server/executor] Executing action_op: {:?}", action_op);
-                        self.execute_action_op(state, doc, ctx, action_op)?;
-                    }
-                };
+        // Bind values matched from the path to the route's params, in a scope
+        // which is popped however the route's actions finish
+        ctx.push_child_scope();
+        let result = (|| -> IsymtopeGenerateResult<ActionOutcome> {
+            for (key, value) in route_match.params() {
+                let binding = CommonBindings::NamedRouteParam(key.to_owned(), Default::default());
+                let value = ExpressionValue::Primitive(Primitive::StringVal(value.to_owned()));
+                ctx.bind_value(binding, value)?;
             }
 
-            _ => {
-                return Err(try_eval_from_err!(format!(
-                    "Unsupported action for server execution: {:?}",
-                    action
-                )))?;
-            }
-        };
+            let mut outcome = ActionOutcome::Render;
+            match *route.unwrap().action() {
+                RouteActionValue::Actions(ref v, _) => {
+                    if let Some(ref v) = *v {
+                        for action_op in v {
+                            eprintln!("[server/executor] Executing action_op: {:?}", action_op);
+                            outcome = self.execute_action_op(state, doc, ctx, action_op)?;
 
-        Ok(())
+                            // Remaining actions are not executed after navigating
+                            if outcome != ActionOutcome::Render {
+                                break;
+                            };
+                        }
+                    };
+                }
+
+                // Content is rendered in place of the root block by the page writer
+                RouteActionValue::Block(..) => {}
+            };
+
+            Ok(outcome)
+        })();
+        ctx.pop_scope();
+
+        result
     }
 }
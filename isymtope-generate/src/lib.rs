@@ -10,6 +10,9 @@ extern crate isymtope_ast_common;
 extern crate isymtope_build;
 extern crate regex;
 
+#[cfg(feature = "session_time")]
+extern crate time;
+
 // mod actions;
 pub mod executor;
 pub mod context;
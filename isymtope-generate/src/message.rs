@@ -1,9 +1,42 @@
+/// Status of a rendered route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderStatus {
+    Ok,
+    /// Path not matched by any route other than the fallback route
+    NotFound,
+    /// The route navigated to another path within the app
+    Redirect(String),
+}
+
 #[derive(Debug)]
-pub struct RenderResponse(String);
+pub struct RenderResponse(String, RenderStatus);
 
 impl RenderResponse {
     pub fn new(body: String) -> Self {
-        RenderResponse(body)
+        RenderResponse(body, RenderStatus::Ok)
+    }
+
+    pub fn not_found(body: String) -> Self {
+        RenderResponse(body, RenderStatus::NotFound)
+    }
+
+    pub fn redirect(location: String) -> Self {
+        RenderResponse(String::new(), RenderStatus::Redirect(location))
+    }
+
+    pub fn status(&self) -> &RenderStatus {
+        &self.1
+    }
+
+    pub fn redirect_location(&self) -> Option<&str> {
+        match self.1 {
+            RenderStatus::Redirect(ref location) => Some(location),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.1 == RenderStatus::NotFound
     }
 
     pub fn into_inner(self) -> String {
@@ -21,6 +21,42 @@ pub enum IsymtopeGenerateError {
 
     #[fail(display = "Session error")]
     SessionError(SessionError),
+
+    #[fail(display = "Redirect loop: {}", _0)]
+    RedirectLoop(RedirectChain),
+
+    #[fail(display = "Unknown action type [{}]", _0)]
+    UnknownAction(String),
+}
+
+/// Paths visited while following navigation from a route
+#[derive(Debug)]
+pub struct RedirectChain(pub Vec<String>);
+
+impl ::std::fmt::Display for RedirectChain {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}", self.0.join(" -> "))
+    }
+}
+
+impl IsymtopeGenerateError {
+    pub fn parse_error(&self) -> Option<&TemplateParseError> {
+        match *self {
+            IsymtopeGenerateError::TemplateParseError(ref err) => Some(err),
+            IsymtopeGenerateError::DocumentProcessingError(ref err) => err.parse_error(),
+            _ => None,
+        }
+    }
+
+    /// Rendered diagnostic, for errors caused by the template rather than the server.
+    pub fn diagnostic(&self) -> Option<String> {
+        match *self {
+            IsymtopeGenerateError::TemplateParseError(ref err) => Some(err.diagnostic()),
+            IsymtopeGenerateError::DocumentProcessingError(ref err) => err.diagnostic(),
+            IsymtopeGenerateError::RedirectLoop(..) => Some(format!("error: {}\n", self)),
+            _ => None,
+        }
+    }
 }
 
 impl From<IOError> for IsymtopeGenerateError {
@@ -1,24 +1,28 @@
 use std::rc::Rc;
 
-use regex::RegexSet;
-
 use isymtope_build::*;
 
 #[derive(Debug, Default)]
 pub struct Router {
-    routes: Option<RegexSet>,
+    routes: Vec<(String, RoutePattern)>,
 }
 
 impl Router {
     pub fn with_document_provider(document_provider: Rc<DocumentProvider>) -> Self {
         let doc = document_provider.doc();
-        let routes = doc.routes();
+        let routes = doc.routes()
+            .map(|r| (r.pattern().to_owned(), r.route_pattern().to_owned()))
+            .collect();
 
-        let route_patterns: Vec<_> = routes.map(|r| r.pattern()).collect();
-        let routes = RegexSet::new(route_patterns.into_iter()).unwrap();
+        Router { routes: routes }
+    }
 
-        Router {
-            routes: Some(routes),
-        }
+    /// Finds the route for a path, using the same precedence rules as the client router
+    pub fn match_route(&self, path: &str) -> Option<RouteMatch> {
+        match_route_patterns(self.routes.iter().map(|r| &r.1), path)
+            .map(|(idx, params)| {
+                let (ref pattern, ref route_pattern) = self.routes[idx];
+                RouteMatch::new(pattern.to_owned(), params, route_pattern.is_fallback())
+            })
     }
 }
@@ -5,13 +5,15 @@ use std::collections::HashMap;
 #[cfg(feature = "session_time")]
 use time::{get_time, Duration, Timespec};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MemorySession {
     #[cfg(feature = "session_time")]
     created: Timespec,
     #[cfg(feature = "session_time")]
     expires: Option<Timespec>,
     data: HashMap<String, ExpressionValue<OutputExpression>>,
+    #[cfg(feature = "types")]
+    types: HashMap<String, VarType>,
 }
 
 #[cfg(feature = "session_time")]
@@ -37,6 +39,20 @@ impl MemorySession {
             created: created,
             expires: expires,
             data: Default::default(),
+            #[cfg(feature = "types")]
+            types: Default::default(),
+        }
+    }
+
+    /// Session restored from storage, with the times it was created and expires
+    #[cfg(feature = "session_time")]
+    pub fn restore(created: Timespec, expires: Option<Timespec>) -> Self {
+        MemorySession {
+            created: created,
+            expires: expires,
+            data: Default::default(),
+            #[cfg(feature = "types")]
+            types: Default::default(),
         }
     }
 
@@ -44,8 +60,15 @@ impl MemorySession {
     pub fn new() -> Self {
         MemorySession {
             data: Default::default(),
+            #[cfg(feature = "types")]
+            types: Default::default(),
         }
     }
+
+    /// Value of each key set in the session
+    pub fn values<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a ExpressionValue<OutputExpression>)> {
+        self.data.iter().map(|(key, value)| (key.as_str(), value))
+    }
 }
 
 impl Session for MemorySession {
@@ -111,22 +134,25 @@ impl Session for MemorySession {
     }
 
     #[cfg(feature = "types")]
-    fn set_with_type(
+    fn set_value_with_type(
         &mut self,
         key: &str,
         value: ExpressionValue<OutputExpression>,
     ) -> SessionResult<()> {
-        let ty = value.peek_ty();
-
-        let mode = ty.map(|ty| {
-            if initial {
-                DataItemMode::InitialType(ty)
-            } else {
-                DataItemMode::ReplaceType(ty)
-            }
-        });
-
-        self.set_with_type_mode(key, value, mode)?;
+        // The first known type of each value is kept for the life of the session
+        if let Some(ty) = value.peek_ty() {
+            match self.types.get(key) {
+                Some(existing) if !existing.is_compatible(&ty) => {
+                    return Err(SessionError::ValueTypeError(key.to_owned(), existing.to_owned(), ty));
+                }
+                Some(_) => {}
+                None => {
+                    self.types.insert(key.to_owned(), ty);
+                }
+            };
+        };
+
+        self.set_value(key, value, true)
     }
 }
 
@@ -0,0 +1,36 @@
+(function(script) {
+    "use strict";
+
+    const overlayId = "isymtope-error-overlay"
+
+    // The overlay is attached outside of the body, which is patched by the app driver
+    function showError(diagnostic) {
+        let overlay = document.getElementById(overlayId)
+        if (!overlay) {
+            overlay = document.createElement("div")
+            overlay.id = overlayId
+            overlay.setAttribute("style", "position: fixed; top: 0; right: 0; bottom: 0; left: 0; z-index: 2147483647; overflow: auto; padding: 2em; background: rgba(24, 24, 24, 0.92); color: #f0f0f0")
+
+            let title = document.createElement("h2")
+            title.setAttribute("style", "margin: 0 0 1em; font: bold 16px sans-serif; color: #ff6b6b")
+            title.textContent = "Compile error"
+            overlay.appendChild(title)
+
+            let pre = document.createElement("pre")
+            pre.setAttribute("style", "margin: 0; font: 14px/1.4 monospace; white-space: pre-wrap")
+            overlay.appendChild(pre)
+
+            document.documentElement.appendChild(overlay)
+        }
+        overlay.lastChild.textContent = diagnostic
+    }
+
+    let initialError = script.getAttribute("data-isymtope-error")
+    if (initialError !== null) {
+        showError(initialError)
+    }
+
+    const events = new EventSource(script.getAttribute("data-isymtope-live-reload"))
+    events.addEventListener("reload", () => window.location.reload())
+    events.addEventListener("compile-error", evt => showError(evt.data))
+}(document.currentScript));
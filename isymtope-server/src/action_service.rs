@@ -0,0 +1,298 @@
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+
+use futures::{self, future, Future, Stream};
+use hyper::{Request, Response, StatusCode};
+use hyper::Method::Post;
+use hyper::header::{ContentLength, ContentType, Location, Referer, SetCookie};
+use hyper::mime;
+use hyper::Error as HyperError;
+use serde_json;
+use url::form_urlencoded;
+
+use tokio_core::reactor::Handle;
+
+use isymtope_generate::*;
+use super::*;
+
+/// Largest action or form body accepted, in bytes
+pub const MAX_ACTION_BODY: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct ActionServiceFactory {
+    sender: RequestMsgChannel,
+    handle: Handle,
+}
+
+impl ActionServiceFactory {
+    pub fn new(sender: RequestMsgChannel, handle: Handle) -> Self {
+        ActionServiceFactory {
+            sender: sender,
+            handle: handle,
+        }
+    }
+}
+
+impl IsymtopeAppServiceFactory for ActionServiceFactory {
+    type Request = Request;
+    type Response = Response;
+    type Error = HyperError;
+    type Instance = ActionService;
+
+    fn create(&self) -> Self::Instance {
+        ActionService {
+            sender: self.sender.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+/// Executes store actions posted by clients in their session, responding with the
+/// new state, or the body of a route rendered with it.  Forms posted without scripts
+/// are redirected back to the page they were posted from, or the path navigated to.
+#[derive(Debug)]
+pub struct ActionService {
+    sender: RequestMsgChannel,
+    handle: Handle,
+}
+
+fn error_response(err: &IsymtopeServerError) -> Response {
+    let (status, message) = match *err {
+        IsymtopeServerError::BadActionRequest(..) => (StatusCode::BadRequest, err.to_string()),
+        IsymtopeServerError::ActionRequestTooLarge(..) => (StatusCode::PayloadTooLarge, err.to_string()),
+        IsymtopeServerError::GenerateError(ref err @ IsymtopeGenerateError::UnknownAction(..)) => {
+            (StatusCode::BadRequest, err.to_string())
+        }
+        _ => (StatusCode::InternalServerError, err.to_string()),
+    };
+    let body = err.diagnostic().unwrap_or_else(|| format!("error: {}\n", message));
+
+    Response::new()
+        .with_status(status)
+        .with_header(ContentType(mime::TEXT_PLAIN_UTF_8))
+        .with_body(body)
+}
+
+fn make_response(result: IsymtopeServerResult<ResponseMsg>) -> Response {
+    match result {
+        Ok(ResponseMsg::ActionComplete(action_response, set_cookie)) => {
+            let mut response = match action_response {
+                ActionResponse::State(state) => Response::new()
+                    .with_header(ContentType(mime::APPLICATION_JSON))
+                    .with_body(state.to_string()),
+                ActionResponse::Fragment(body) => Response::new()
+                    .with_header(ContentType(mime::TEXT_HTML_UTF_8))
+                    .with_body(body),
+            };
+            if let Some(set_cookie) = set_cookie {
+                response.headers_mut().set(SetCookie(vec![set_cookie]));
+            };
+            response
+        }
+
+        Ok(other) => {
+            let body = format!("Unknown response message from action task: {:?}", other);
+            Response::new()
+                .with_status(StatusCode::InternalServerError)
+                .with_body(body)
+        }
+
+        Err(ref err) => error_response(err),
+    }
+}
+
+/// Location to redirect to after a form is posted
+fn form_location(base_url: &str, navigate: Option<&str>, referer: Option<&str>) -> String {
+    match (navigate, referer) {
+        // Paths navigated to are relative to the app
+        (Some(path), _) => format!("{}{}", base_url, path.trim_left_matches('/')),
+        (None, Some(referer)) if referer.starts_with(base_url) => referer.to_owned(),
+        _ => base_url.to_owned(),
+    }
+}
+
+fn make_form_response(result: IsymtopeServerResult<ResponseMsg>, location: String) -> Response {
+    match result {
+        Ok(ResponseMsg::FormComplete(set_cookie)) => {
+            let mut response = Response::new()
+                .with_status(StatusCode::SeeOther)
+                .with_header(Location::new(location));
+            if let Some(set_cookie) = set_cookie {
+                response.headers_mut().set(SetCookie(vec![set_cookie]));
+            };
+            response
+        }
+
+        Ok(other) => {
+            let body = format!("Unknown response message from action task: {:?}", other);
+            Response::new()
+                .with_status(StatusCode::InternalServerError)
+                .with_body(body)
+        }
+
+        Err(ref err) => error_response(err),
+    }
+}
+
+/// Reads the body of a request, failing once it is longer than `limit`, so that
+/// clients cannot make the server buffer bodies of any size
+fn read_body(req: Request, limit: usize) -> Box<Future<Item = Vec<u8>, Error = IsymtopeServerError>> {
+    let too_large = move || IsymtopeServerError::ActionRequestTooLarge(limit);
+
+    if let Some(&ContentLength(len)) = req.headers().get::<ContentLength>() {
+        if len > limit as u64 {
+            return Box::new(future::err(too_large()));
+        };
+    };
+
+    let body = req.body()
+        .from_err::<IsymtopeServerError>()
+        .fold(Vec::new(), move |mut body, chunk| {
+            if body.len() + chunk.len() > limit {
+                return Err(too_large());
+            };
+            body.extend_from_slice(&chunk);
+            Ok(body)
+        });
+
+    Box::new(body)
+}
+
+/// Runs `f` with the body of an action request, responding with the error when the
+/// body cannot be read
+fn with_body<F>(req: Request, f: F) -> Box<Future<Item = Response, Error = HyperError>>
+where
+    F: FnOnce(Vec<u8>) -> Box<Future<Item = Response, Error = HyperError>> + 'static,
+{
+    let work = read_body(req, MAX_ACTION_BODY).then(move |body| match body {
+        Ok(body) => f(body),
+        Err(IsymtopeServerError::HyperError(err)) => Box::new(future::err(err)),
+        Err(err) => Box::new(future::ok(error_response(&err))),
+    });
+
+    Box::new(work)
+}
+
+fn is_form(req: &Request) -> bool {
+    req.headers()
+        .get::<ContentType>()
+        .map(|ct| ct.type_() == mime::APPLICATION && ct.subtype() == mime::WWW_FORM_URLENCODED)
+        .unwrap_or(false)
+}
+
+impl ActionService {
+    fn submit_form(&self, base_url: &str, app_name: &str, req: Request) -> <Self as IsymtopeAppService>::Future {
+        let sender = self.sender.clone();
+        let template_path = "/app.ism".to_owned();
+        let base_url = base_url.to_owned();
+        let app_name = app_name.to_owned();
+        let session_cookie = request_session_cookie(&req);
+        let referer = req.headers().get::<Referer>().map(|referer| referer.to_string());
+
+        with_body(req, move |chunk| -> <Self as IsymtopeAppService>::Future {
+            let fields: Vec<(String, String)> = form_urlencoded::parse(&chunk).into_owned().collect();
+            let submission = match FormSubmission::from_form(&fields) {
+                Ok(submission) => submission,
+                Err(err) => return Box::new(future::ok(error_response(&err))),
+            };
+
+            let location = form_location(&base_url, submission.navigate(), referer.as_ref().map(|s| s.as_str()));
+
+            let (tx, rx) = futures::sync::oneshot::channel::<IsymtopeServerResult<ResponseMsg>>();
+            let submit = Msg::SubmitForm(base_url, app_name, template_path, session_cookie, submission.into_actions());
+            sender.unbounded_send((submit, tx)).unwrap();
+
+            let response = rx.map_err(|_| {
+                HyperError::Io(IOError::new(
+                    IOErrorKind::Other,
+                    "Failed making request on ServerContext.",
+                ))
+            }).map(move |result| make_form_response(result, location));
+
+            Box::new(response)
+        })
+    }
+}
+
+impl IsymtopeAppService for ActionService {
+    type Request = Request;
+    type Response = Response;
+    type Error = HyperError;
+    type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn call(&self, base_url: &str, app_name: &str, req: Request) -> Self::Future {
+        if *req.method() != Post {
+            return Box::new(future::ok(Response::new().with_status(StatusCode::MethodNotAllowed)));
+        };
+
+        if is_form(&req) {
+            return self.submit_form(base_url, app_name, req);
+        };
+
+        let sender = self.sender.clone();
+        let template_path = "/app.ism".to_owned();
+        let base_url = base_url.to_owned();
+        let app_name = app_name.to_owned();
+        let session_cookie = request_session_cookie(&req);
+
+        with_body(req, move |chunk| -> Self::Future {
+            let action_req = serde_json::from_slice(&chunk)
+                .map_err(|err| IsymtopeServerError::BadActionRequest(err.to_string()))
+                .and_then(|json| ActionRequest::from_json(&json));
+
+            let action_req = match action_req {
+                Ok(action_req) => action_req,
+                Err(err) => return Box::new(future::ok(error_response(&err))),
+            };
+
+            let (tx, rx) = futures::sync::oneshot::channel::<IsymtopeServerResult<ResponseMsg>>();
+            let dispatch = Msg::DispatchAction(base_url, app_name, template_path, session_cookie, action_req);
+            sender.unbounded_send((dispatch, tx)).unwrap();
+
+            let response = rx.map_err(|_| {
+                HyperError::Io(IOError::new(
+                    IOErrorKind::Other,
+                    "Failed making request on ServerContext.",
+                ))
+            }).map(make_response);
+
+            Box::new(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Body, Request};
+    use hyper::Method::Post;
+
+    use super::*;
+
+    fn request(body: Vec<u8>, content_length: bool) -> Request {
+        let mut req = Request::new(Post, "/app/test/_actions".parse().unwrap());
+        if content_length {
+            req.headers_mut().set(ContentLength(body.len() as u64));
+        };
+        req.set_body(Body::from(body));
+        req
+    }
+
+    fn status(result: Result<Vec<u8>, IsymtopeServerError>) -> Option<StatusCode> {
+        result.err().map(|err| error_response(&err).status())
+    }
+
+    #[test]
+    fn test_read_body() {
+        let body = read_body(request(b"{}".to_vec(), true), 16).wait().unwrap();
+        assert_eq!(body, b"{}".to_vec());
+    }
+
+    #[test]
+    fn test_read_body_too_large() {
+        let declared = read_body(request(vec![b' '; 17], true), 16).wait();
+        assert_eq!(status(declared), Some(StatusCode::PayloadTooLarge));
+
+        // Bodies without a length are limited as they are read
+        let streamed = read_body(request(vec![b' '; 17], false), 16).wait();
+        assert_eq!(status(streamed), Some(StatusCode::PayloadTooLarge));
+    }
+}
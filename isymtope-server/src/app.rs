@@ -7,6 +7,7 @@ use std::str;
 #[cfg(feature = "session_time")]
 use time::Duration;
 
+use isymtope_ast_common::*;
 use isymtope_generate::*;
 use super::*;
 
@@ -21,16 +22,27 @@ pub trait AppContext {
 #[derive(Debug)]
 pub struct DefaultAppContext {
     app_root: PathBuf,
+    template_path: String,
     template_context: DefaultTemplateContext,
     sessions: MemorySessions,
+    /// Diagnostic of the last failed recompile, reported by requests until the
+    /// templates compile again
+    compile_error: Option<String>,
 }
 
 impl DefaultAppContext {
-    pub fn new(app_root: &Path, template_context: DefaultTemplateContext) -> Self {
+    pub fn new(
+        app_root: &Path,
+        template_path: &str,
+        template_context: DefaultTemplateContext,
+        sessions: MemorySessions,
+    ) -> Self {
         DefaultAppContext {
             app_root: app_root.to_owned(),
+            template_path: template_path.to_owned(),
             template_context: template_context,
-            sessions: Default::default(),
+            sessions: sessions,
+            compile_error: None,
         }
     }
 
@@ -43,20 +55,248 @@ impl DefaultAppContext {
             app_root, template_path
         );
 
-        let template_context = DefaultTemplateContext::create(app_root, template_path)?;
-        let app_context = DefaultAppContext::new(app_root, template_context);
+        let template_context = DefaultTemplateContext::create(app_root, template_path)?.with_form_actions();
+
+        let app_name = app_root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let sessions = MemorySessions::with_store(open_session_store(&SESSION_STORE, &app_name)?);
+
+        let app_context = DefaultAppContext::new(app_root, template_path, template_context, sessions);
 
         Ok(app_context)
     }
+
+    pub fn template_path(&self) -> &str {
+        &self.template_path
+    }
+
+    /// Recompiles the app's templates, keeping its sessions.  When the templates fail
+    /// to compile, the previous templates are kept, and requests report the error.
+    pub fn recompile(&mut self) -> IsymtopeServerResult<()> {
+        match DefaultTemplateContext::create(&self.app_root, &self.template_path) {
+            Ok(template_context) => {
+                self.template_context = template_context.with_form_actions();
+                self.compile_error = None;
+                Ok(())
+            }
+            Err(err) => {
+                let err: IsymtopeServerError = err.into();
+                self.compile_error = Some(err.diagnostic().unwrap_or_else(|| format!("error: {}\n", err)));
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the session with the given id if it is valid, or creates a new session
+    /// with the default state of the app
+    pub fn get_or_create_session(
+        &mut self,
+        session_id: Option<&str>,
+        srs: &mut SecureRandomStringGenerator,
+    ) -> IsymtopeServerResult<SessionCreationType> {
+        let session = self.sessions.get_or_create(session_id, srs)?;
+
+        if let SessionCreationType::CreatedSession(ref session_id) = session {
+            {
+                let state = self.sessions
+                    .get_mut(session_id)
+                    .ok_or(IsymtopeServerError::CannotFindSession)?;
+                self.template_context.initialize_session(state)?;
+            }
+            self.sessions.persist(session_id)?;
+        };
+
+        Ok(session)
+    }
 }
 
 impl AppContext for DefaultAppContext {
     fn handle_msg(&mut self, msg: AppRequestMsg) -> IsymtopeServerResult<AppResponseMsg> {
+        if let Some(ref diagnostic) = self.compile_error {
+            return Err(IsymtopeServerError::AppCompileError(diagnostic.to_owned()));
+        };
+
         match msg {
             AppRequestMsg::TemplateRequest(template_req_msg) => {
                 let response_msg = self.template_context.handle_msg(template_req_msg)?;
                 Ok(AppResponseMsg::TemplateResponse(response_msg))
             }
+
+            AppRequestMsg::SessionTemplateRequest(session_id, template_req_msg) => {
+                let response = {
+                    let session = self.sessions
+                        .get_mut(&session_id)
+                        .ok_or(IsymtopeServerError::CannotFindSession)?;
+
+                    match template_req_msg {
+                        TemplateRequestMsg::RenderAppRoute(ref base_url, _, _, ref path) => {
+                            self.template_context.render_route(session, base_url, path)?
+                        }
+                    }
+                };
+
+                // Route actions change the state of the session
+                self.sessions.persist(&session_id)?;
+                let response_msg = TemplateResponseMsg::RenderComplete(response);
+                Ok(AppResponseMsg::TemplateResponse(response_msg))
+            }
+
+            AppRequestMsg::ActionRequest(base_url, session_id, action_req) => {
+                let response = {
+                    let mut temporary_session = MemorySession::default();
+                    let session = match session_id {
+                        Some(ref session_id) => self.sessions
+                            .get_mut(session_id)
+                            .ok_or(IsymtopeServerError::CannotFindSession)?,
+                        None => {
+                            self.template_context.initialize_session(&mut temporary_session)?;
+                            &mut temporary_session
+                        }
+                    };
+
+                    self.template_context
+                        .execute_action(session, action_req.action_ty(), action_req.params())?;
+
+                    match action_req.render() {
+                        Some(path) => {
+                            let body = self.template_context.render_fragment(session, &base_url, path)?;
+                            ActionResponse::Fragment(body)
+                        }
+                        None => ActionResponse::State(state_to_json(self.template_context.doc(), session)?),
+                    }
+                };
+
+                if let Some(ref session_id) = session_id {
+                    self.sessions.persist(session_id)?;
+                };
+                Ok(AppResponseMsg::ActionResponse(response))
+            }
+
+            AppRequestMsg::FormRequest(session_id, action_reqs) => {
+                {
+                    let mut temporary_session = MemorySession::default();
+                    let session = match session_id {
+                        Some(ref session_id) => self.sessions
+                            .get_mut(session_id)
+                            .ok_or(IsymtopeServerError::CannotFindSession)?,
+                        None => {
+                            self.template_context.initialize_session(&mut temporary_session)?;
+                            &mut temporary_session
+                        }
+                    };
+
+                    for action_req in action_reqs {
+                        self.template_context
+                            .execute_action(session, action_req.action_ty(), action_req.params())?;
+                    }
+                }
+
+                if let Some(ref session_id) = session_id {
+                    self.sessions.persist(session_id)?;
+                };
+                Ok(AppResponseMsg::FormResponse)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+
+    fn temp_app(src: &str) -> PathBuf {
+        let suffix: String = thread_rng().gen_ascii_chars().take(12).collect();
+        let app_root = env::temp_dir().join(format!("isymtope-app-{}", suffix));
+        fs::create_dir_all(&app_root).unwrap();
+        fs::write(app_root.join("app.ism"), src).unwrap();
+        app_root
+    }
+
+    #[test]
+    fn test_recompile_keeps_sessions() {
+        let app_root = temp_app("use html; component app() { h1 { (\"one\") } } app() {} route \"/\" || {}");
+        let mut srs = DefaultSecureRandomStringGenerator::default();
+
+        let mut app_context = DefaultAppContext::create(&app_root, "/app.ism").unwrap();
+        let session = app_context.get_or_create_session(None, &mut srs).unwrap();
+        let session_id = session.session_id().to_owned();
+
+        fs::write(app_root.join("app.ism"), "use html; component app() { h1 { (\"two\") } } app() {} route \"/\" || {}").unwrap();
+        app_context.recompile().unwrap();
+
+        let session = app_context.get_or_create_session(Some(&session_id), &mut srs).unwrap();
+        assert_eq!(session, SessionCreationType::ExistingSession(session_id));
+
+        fs::remove_dir_all(&app_root).ok();
+    }
+
+    #[test]
+    fn test_failed_recompile_keeps_sessions() {
+        let app_root = temp_app("use html; div { (\"one\") }");
+        let mut srs = DefaultSecureRandomStringGenerator::default();
+        let render = || {
+            let render = TemplateRequestMsg::RenderAppRoute("/app/test/".to_owned(), "test".to_owned(), "/app.ism".to_owned(), "/".to_owned());
+            AppRequestMsg::TemplateRequest(render)
+        };
+
+        let mut app_context = DefaultAppContext::create(&app_root, "/app.ism").unwrap();
+        let session = app_context.get_or_create_session(None, &mut srs).unwrap();
+        let session_id = session.session_id().to_owned();
+
+        // Requests report the error until the templates compile again
+        fs::write(app_root.join("app.ism"), "use html; div { (").unwrap();
+        assert!(app_context.recompile().is_err());
+        match app_context.handle_msg(render()) {
+            Err(ref err @ IsymtopeServerError::AppCompileError(..)) => assert!(err.diagnostic().is_some()),
+            other => panic!("expected a compile error, got {:?}", other),
+        };
+
+        fs::write(app_root.join("app.ism"), "use html; div { (\"two\") }").unwrap();
+        app_context.recompile().unwrap();
+        assert!(app_context.handle_msg(render()).is_ok());
+
+        let session = app_context.get_or_create_session(Some(&session_id), &mut srs).unwrap();
+        assert_eq!(session, SessionCreationType::ExistingSession(session_id));
+
+        fs::remove_dir_all(&app_root).ok();
+    }
+
+    #[test]
+    fn test_render_persists_route_actions() {
+        let src = "use html;
+            store { let counter = 0; counter { action increment => value + 1; } }
+            div { (get counter) }
+            route \"/bump\" || { dispatch increment to counter; }";
+        let app_root = temp_app(src);
+        let store_dir = app_root.join("sessions");
+        let mut srs = DefaultSecureRandomStringGenerator::default();
+
+        let open_sessions = || MemorySessions::with_store(Some(Box::new(FileSessionStore::open(&store_dir).unwrap())));
+        let template_context = DefaultTemplateContext::create(&app_root, "/app.ism").unwrap();
+        let mut app_context = DefaultAppContext::new(&app_root, "/app.ism", template_context, open_sessions());
+
+        let session = app_context.get_or_create_session(None, &mut srs).unwrap();
+        let session_id = session.session_id().to_owned();
+
+        let render = TemplateRequestMsg::RenderAppRoute("/app/test/".to_owned(), "test".to_owned(), "/app.ism".to_owned(), "/bump".to_owned());
+        app_context
+            .handle_msg(AppRequestMsg::SessionTemplateRequest(session_id.clone(), render))
+            .unwrap();
+
+        // The state changed by the route is restored by a restarted server
+        let mut sessions = open_sessions();
+        sessions.get_or_create(Some(&session_id), &mut srs).unwrap();
+        let value = sessions.get(&session_id).unwrap().get_value("counter").unwrap();
+        assert_eq!(value, Some(&ExpressionValue::Primitive(Primitive::Int32Val(1))));
+
+        fs::remove_dir_all(&app_root).ok();
+    }
+}
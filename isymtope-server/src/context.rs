@@ -33,66 +33,187 @@ impl DefaultServerContext {
             cookies: Default::default(),
         }
     }
+
+    /// Session carried by the request's cookie, creating a session for clients without
+    /// a valid cookie.  Returns the session id, and the `Set-Cookie` header value for a
+    /// new session.
+    #[cfg(feature = "cookies")]
+    fn request_session(
+        &mut self,
+        app_context: &mut DefaultAppContext,
+        base_url: &str,
+        session_cookie: Option<&str>,
+    ) -> IsymtopeServerResult<(Option<String>, Option<String>)> {
+        let session_id = session_cookie.and_then(|cookie| self.cookies.verify(cookie));
+        let session = app_context.get_or_create_session(session_id.as_ref().map(|s| s.as_str()), &mut self.srs)?;
+
+        let set_cookie = match session {
+            SessionCreationType::CreatedSession(ref session_id) => {
+                let cookie = self.cookies.create(session_id);
+                Some(cookie.set_cookie_header(cookie_path(base_url)))
+            }
+            SessionCreationType::ExistingSession(_) => None,
+        };
+
+        Ok((Some(session.session_id().to_owned()), set_cookie))
+    }
+
+    /// Requests use a temporary session with the default state, as sessions require cookies
+    #[cfg(not(feature = "cookies"))]
+    fn request_session(
+        &mut self,
+        _app_context: &mut DefaultAppContext,
+        _base_url: &str,
+        _session_cookie: Option<&str>,
+    ) -> IsymtopeServerResult<(Option<String>, Option<String>)> {
+        Ok((None, None))
+    }
 }
 
 #[derive(Debug)]
 pub enum Msg {
-    RenderAppRoute(String, String, String, String),
+    /// Renders a route of an app, in the session carried by the session cookie if given
+    RenderAppRoute(String, String, String, String, Option<String>),
+    /// Executes a store action of an app, in the session carried by the session cookie if given
+    DispatchAction(String, String, String, Option<String>, ActionRequest),
+    /// Executes the store actions of a posted form, in the session carried by the session cookie if given
+    SubmitForm(String, String, String, Option<String>, Vec<ActionRequest>),
+    /// Recompiles the cached templates of an app, after its files have changed
+    ReloadApp(String),
 }
 
 #[derive(Debug)]
 pub enum ResponseMsg {
-    RenderComplete(RenderResponse),
+    /// Rendered route, with the `Set-Cookie` header value for a new session
+    RenderComplete(RenderResponse, Option<String>),
+    /// Result of an action, with the `Set-Cookie` header value for a new session
+    ActionComplete(ActionResponse, Option<String>),
+    /// Form actions executed, with the `Set-Cookie` header value for a new session
+    FormComplete(Option<String>),
+    ReloadComplete,
+}
+
+fn app_key(app_name: &str, template_path: &str) -> String {
+    format!("[appName={}, templatePath={}]", app_name, template_path)
+}
+
+/// Runs `f` with the cached context of an app, creating the context on first use
+fn with_app_context<T, F>(app_name: &str, template_path: &str, f: F) -> IsymtopeServerResult<T>
+where
+    F: FnOnce(&mut DefaultAppContext) -> IsymtopeServerResult<T>,
+{
+    let app_root = &*APP_DIR.join(app_name);
+
+    let app_key = app_key(app_name, template_path);
+    eprintln!(
+        "[server context] get or creating context for app with key ({})",
+        app_key
+    );
+
+    APP_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+
+        let app_context = match cache.entry(app_key.clone()) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(v) => {
+                v.insert(DefaultAppContext::create(&app_root, template_path)?)
+            }
+        };
+
+        f(app_context)
+    })
 }
 
 impl ServerContext for DefaultServerContext {
     fn handle_msg(&mut self, msg: Msg) -> IsymtopeServerResult<ResponseMsg> {
         match msg {
-            Msg::RenderAppRoute(ref base_url, ref app_name, ref template_path, ref path) => {
-                // let template_path = if path == "/" { "/app.ism".to_owned() } else { path.to_owned() };
+            Msg::RenderAppRoute(ref base_url, ref app_name, ref template_path, ref path, ref session_cookie) => {
+                let (app_response, set_cookie) = with_app_context(app_name, template_path, |app_context| {
+                    let session_cookie = session_cookie.as_ref().map(|s| s.as_str());
+                    let (session_id, set_cookie) = self.request_session(app_context, base_url, session_cookie)?;
 
-                // let trimmed_path = path.trim_left_matches('/').to_owned();
-                // let template_path = app_dir.join(app_name).join(trimmed_path);
+                    let template_req_msg = TemplateRequestMsg::RenderAppRoute(
+                        base_url.to_owned(),
+                        app_name.to_owned(),
+                        template_path.to_owned(),
+                        path.to_owned(),
+                    );
+                    let app_req_msg = match session_id {
+                        Some(session_id) => AppRequestMsg::SessionTemplateRequest(session_id, template_req_msg),
+                        None => AppRequestMsg::TemplateRequest(template_req_msg),
+                    };
 
-                let app_root = &*APP_DIR.join(app_name);
+                    Ok((app_context.handle_msg(app_req_msg)?, set_cookie))
+                })?;
 
-                let app_key = format!("[appName={}, templatePath={}]", app_name, template_path);
-                eprintln!(
-                    "[server context] get or creating context for app with key ({})",
-                    app_key
-                );
+                match app_response {
+                    AppResponseMsg::TemplateResponse(TemplateResponseMsg::RenderComplete(render_response)) => {
+                        Ok(ResponseMsg::RenderComplete(render_response, set_cookie))
+                    }
+                    _ => Err(IsymtopeServerError::RenderRequestFailed),
+                }
+            }
 
-                // let route_key = format!("[appName={}, templatePath={}]/{}", app_name, template_path, path);
-                // eprintln!("[server context] get or creating context for app with key ({})", app_key);
+            Msg::DispatchAction(ref base_url, ref app_name, ref template_path, ref session_cookie, action_req) => {
+                let (app_response, set_cookie) = with_app_context(app_name, template_path, |app_context| {
+                    let session_cookie = session_cookie.as_ref().map(|s| s.as_str());
+                    let (session_id, set_cookie) = self.request_session(app_context, base_url, session_cookie)?;
 
-                let app_response = APP_CACHE.with(|cache| {
-                    let mut cache = cache.borrow_mut();
+                    let app_req_msg = AppRequestMsg::ActionRequest(base_url.to_owned(), session_id, action_req);
+                    Ok((app_context.handle_msg(app_req_msg)?, set_cookie))
+                })?;
 
-                    let app_context = match cache.entry(app_key.clone()) {
-                        Entry::Occupied(e) => e.into_mut(),
-                        Entry::Vacant(v) => {
-                            v.insert(DefaultAppContext::create(&app_root, template_path)?)
-                        }
-                    };
+                match app_response {
+                    AppResponseMsg::ActionResponse(action_response) => {
+                        Ok(ResponseMsg::ActionComplete(action_response, set_cookie))
+                    }
+                    _ => Err(IsymtopeServerError::RenderRequestFailed),
+                }
+            }
 
-                    // let app_context = cache.entry(app_key.clone()).or_insert_with(|| {
-                    //     DefaultAppContext::create(&app_root, template_path).unwrap()
-                    // });
+            Msg::SubmitForm(ref base_url, ref app_name, ref template_path, ref session_cookie, action_reqs) => {
+                let (app_response, set_cookie) = with_app_context(app_name, template_path, |app_context| {
+                    let session_cookie = session_cookie.as_ref().map(|s| s.as_str());
+                    let (session_id, set_cookie) = self.request_session(app_context, base_url, session_cookie)?;
 
-                    let template_req_msg = TemplateRequestMsg::RenderAppRoute(
-                        base_url.to_owned(),
-                        app_name.to_owned(),
-                        template_path.to_owned(),
-                        path.to_owned(),
-                    );
-                    let app_req_msg = AppRequestMsg::TemplateRequest(template_req_msg);
-                    app_context.handle_msg(app_req_msg)
+                    let app_req_msg = AppRequestMsg::FormRequest(session_id, action_reqs);
+                    Ok((app_context.handle_msg(app_req_msg)?, set_cookie))
                 })?;
 
-                let AppResponseMsg::TemplateResponse(template_response) = app_response;
-                let TemplateResponseMsg::RenderComplete(render_response) = template_response;
+                match app_response {
+                    AppResponseMsg::FormResponse => Ok(ResponseMsg::FormComplete(set_cookie)),
+                    _ => Err(IsymtopeServerError::RenderRequestFailed),
+                }
+            }
+
+            Msg::ReloadApp(ref app_name) => {
+                // Apps keep their sessions, and apps whose templates fail to compile
+                // report the error until they are fixed.  The first error is returned
+                // once every template of the app is recompiled.
+                APP_CACHE.with(|cache| -> IsymtopeServerResult<()> {
+                    let mut cache = cache.borrow_mut();
+                    let app_keys: Vec<_> = cache
+                        .iter()
+                        .filter(|&(key, app_context)| *key == app_key(app_name, app_context.template_path()))
+                        .map(|(key, _)| key.to_owned())
+                        .collect();
+
+                    let mut result = Ok(());
+                    for app_key in app_keys {
+                        eprintln!("[server context] recompiling app with key ({})", app_key);
+
+                        let recompiled = cache.get_mut(&app_key).map(|app_context| app_context.recompile());
+                        if let Some(Err(err)) = recompiled {
+                            if result.is_ok() {
+                                result = Err(err);
+                            };
+                        };
+                    }
+
+                    result
+                })?;
 
-                Ok(ResponseMsg::RenderComplete(render_response))
+                Ok(ResponseMsg::ReloadComplete)
             }
         }
     }
@@ -1,10 +1,16 @@
 #[cfg(feature = "session_time")]
 use time::{get_time, Timespec};
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{OsRng, Rng};
+use sha2::Sha256;
 
 use super::*;
 
-pub const SESSION_COOKIES_RANDOM_STRING_BYTES: usize = 128;
+/// Name of the cookie carrying the session id
+pub const SESSION_COOKIE_NAME: &str = "isymtope_session";
 
+/// Signed cookie value, with the time it was created and expires
 #[cfg(feature = "session_time")]
 #[derive(Debug)]
 pub struct ReturnedCookie(String, Timespec, Timespec);
@@ -13,44 +19,136 @@ pub struct ReturnedCookie(String, Timespec, Timespec);
 #[derive(Debug)]
 pub struct ReturnedCookie(String);
 
-#[derive(Debug, Default)]
+impl ReturnedCookie {
+    /// Value of the `Set-Cookie` header for a cookie sent with requests under `path`
+    #[cfg(feature = "session_time")]
+    pub fn set_cookie_header(&self, path: &str) -> String {
+        let max_age = (self.2 - self.1).num_seconds();
+        format!(
+            "{}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Lax",
+            SESSION_COOKIE_NAME, self.0, path, max_age
+        )
+    }
+
+    /// Value of the `Set-Cookie` header for a cookie sent with requests under `path`
+    #[cfg(not(feature = "session_time"))]
+    pub fn set_cookie_header(&self, path: &str) -> String {
+        format!(
+            "{}={}; Path={}; HttpOnly; SameSite=Lax",
+            SESSION_COOKIE_NAME, self.0, path
+        )
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs session ids, so that only ids issued by this server are accepted.  The
+/// signature is an HMAC-SHA256 tag keyed with `SESSION_SECRET`, or with a key
+/// generated at startup without it, in which case cookies from a previous run of
+/// the server are not valid.
+#[derive(Debug)]
 pub struct Cookies {
-    cookie_secure_prefix: Option<String>,
-    // cookie_name: Option<(String, Timespec)>
+    key: Vec<u8>,
+}
+
+impl Default for Cookies {
+    fn default() -> Self {
+        if let Some(ref secret) = *SESSION_SECRET {
+            return Cookies::with_key(secret.as_bytes());
+        };
+
+        let mut gen = OsRng::new().expect("Failed to get OS random number generator");
+        let mut key = [0u8; 32];
+        gen.fill_bytes(&mut key);
+
+        Cookies::with_key(&key)
+    }
 }
 
 impl Cookies {
-    fn create<'s>(&'s mut self, cookie: &str) -> IsymtopeServerResult<&'s ReturnedCookie> {
-        unimplemented!()
-    }
-
-    fn refresh_cookie<'s>(
-        &'s mut self,
-        old_cookie: &str,
-        cookie: &str,
-    ) -> IsymtopeServerResult<&'s ReturnedCookie> {
-        unimplemented!()
-    }
-
-    // fn cookie_name(&mut self) -> &str {
-    //     // TODO: Handle expiration and retaining old values for session rotation
-    //     if self.cookie_secure_prefix.is_none() {
-    //         // Initialized at server startup
-    //         self.cookie_secure_prefix = Some(self.srs.generate_secure_string(SESSION_COOKIES_RANDOM_STRING_BYTES));
-    //     };
-
-    //     if self.cookie_name.is_none() {
-    //         let ts = get_time();
-    //         let prefix = self.cookie_secure_prefix.as_ref().unwrap();
-    //         let name = self.srs.generate_secure_string(self::SESSION_COOKIES_RANDOM_STRING_BYTES);
-
-    //         self.cookie_name = Some((
-    //             format!("{}_{}_{}_{}", &prefix, &name, ts.sec, ts.nsec),
-    //             ts
-    //         ));
-    //     };
-
-    //     self.cookie_name.as_ref().map(|s| s.0.as_str())
-    //         .expect("Cookie name should have been populated above. This should not happen.")
-    // }
+    pub fn with_key(key: &[u8]) -> Self {
+        Cookies { key: key.to_owned() }
+    }
+
+    /// Creates the cookie carrying a new session id
+    #[cfg(feature = "session_time")]
+    pub fn create(&self, session_id: &str) -> ReturnedCookie {
+        let created = get_time();
+        let expires = created + *SESSION_EXPIRES;
+
+        ReturnedCookie(self.signed_value(session_id), created, expires)
+    }
+
+    /// Creates the cookie carrying a new session id
+    #[cfg(not(feature = "session_time"))]
+    pub fn create(&self, session_id: &str) -> ReturnedCookie {
+        ReturnedCookie(self.signed_value(session_id))
+    }
+
+    /// Session id carried by a cookie value, if it was signed by this server
+    pub fn verify(&self, cookie: &str) -> Option<String> {
+        let idx = cookie.rfind('.')?;
+        let (session_id, signature) = (&cookie[..idx], &cookie[idx + 1..]);
+        let signature = BASE64URL_NOPAD.decode(signature.as_bytes()).ok()?;
+
+        // The tag is compared in constant time, so that the time taken does not
+        // reveal how much of a forged signature is correct
+        self.mac(session_id).verify_slice(&signature).ok()?;
+
+        Some(session_id.to_owned())
+    }
+
+    fn signed_value(&self, session_id: &str) -> String {
+        let signature = self.mac(session_id).finalize().into_bytes();
+        format!("{}.{}", session_id, BASE64URL_NOPAD.encode(&signature))
+    }
+
+    fn mac(&self, session_id: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(session_id.as_bytes());
+        mac
+    }
+}
+
+/// Path of an absolute base url, which session cookies are limited to
+pub fn cookie_path(base_url: &str) -> &str {
+    let after_scheme = base_url.find("://").map(|idx| idx + 3).unwrap_or(0);
+
+    base_url[after_scheme..]
+        .find('/')
+        .map(|idx| &base_url[after_scheme + idx..])
+        .unwrap_or("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signed_cookie() {
+        let cookies = Cookies::with_key(b"secret");
+        let value = cookies.signed_value("session");
+
+        assert_eq!(cookies.verify(&value), Some("session".to_owned()));
+        assert_eq!(Cookies::with_key(b"other").verify(&value), None);
+    }
+
+    #[test]
+    fn test_reject_tampered_cookie() {
+        let cookies = Cookies::with_key(b"secret");
+        let value = cookies.signed_value("session");
+        let signature = &value["session.".len()..];
+
+        assert_eq!(cookies.verify(&format!("other.{}", signature)), None);
+        assert_eq!(cookies.verify(&value[..value.len() - 1]), None);
+        assert_eq!(cookies.verify("session"), None);
+        assert_eq!(cookies.verify("session.!"), None);
+    }
+
+    #[test]
+    fn test_cookie_path() {
+        assert_eq!(cookie_path("http://localhost:3000/app/todomvc/"), "/app/todomvc/");
+        assert_eq!(cookie_path("http://localhost:3000"), "/");
+        assert_eq!(cookie_path("/app/todomvc/"), "/app/todomvc/");
+    }
 }
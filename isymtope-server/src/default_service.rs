@@ -7,14 +7,14 @@ use std::result::Result;
 use regex::Regex;
 
 use futures::{future, Future};
-use hyper::header::{Host, Location};
+use hyper::header::{Cookie, Host, Location};
 
 use hyper::{self, Error as HyperError, Method, Request, Response, StatusCode};
 use hyper::server::{NewService, Service};
 
 use tokio_core::reactor::Handle;
 
-use server::{APP_DIR, DEFAULT_APP};
+use server::{APP_DIR, DEFAULT_APP, WATCH_APPS};
 use super::*;
 
 lazy_static! {
@@ -39,6 +39,7 @@ lazy_static! {
 #[derive(Debug)]
 pub struct DefaultServiceFactory {
     render_service_factory: TemplateRenderServiceFactory,
+    action_service_factory: ActionServiceFactory,
     resource_service_factory: TemplateResourceServiceFactory,
     static_resource_service_factory: StaticResourceServiceFactory,
     #[cfg(feature = "playground_api")] playground_service_factory: PlaygroundApiServiceFactory,
@@ -49,12 +50,14 @@ impl DefaultServiceFactory {
     #[cfg(not(feature = "playground_api"))]
     pub fn new(
         render_service_factory: TemplateRenderServiceFactory,
+        action_service_factory: ActionServiceFactory,
         resource_service_factory: TemplateResourceServiceFactory,
         static_resource_service_factory: StaticResourceServiceFactory,
         handle: Handle,
     ) -> Self {
         DefaultServiceFactory {
             render_service_factory: render_service_factory,
+            action_service_factory: action_service_factory,
             resource_service_factory: resource_service_factory,
             static_resource_service_factory: static_resource_service_factory,
             handle: handle,
@@ -64,6 +67,7 @@ impl DefaultServiceFactory {
     #[cfg(feature = "playground_api")]
     pub fn new(
         render_service_factory: TemplateRenderServiceFactory,
+        action_service_factory: ActionServiceFactory,
         resource_service_factory: TemplateResourceServiceFactory,
         static_resource_service_factory: StaticResourceServiceFactory,
         playground_service_factory: PlaygroundApiServiceFactory,
@@ -71,6 +75,7 @@ impl DefaultServiceFactory {
     ) -> Self {
         DefaultServiceFactory {
             render_service_factory: render_service_factory,
+            action_service_factory: action_service_factory,
             resource_service_factory: resource_service_factory,
             static_resource_service_factory: static_resource_service_factory,
             playground_service_factory: playground_service_factory,
@@ -87,6 +92,7 @@ impl NewService for DefaultServiceFactory {
 
     fn new_service(&self) -> Result<Self::Instance, io::Error> {
         let render_service = self.render_service_factory.create();
+        let action_service = self.action_service_factory.create();
         let resource_service = self.resource_service_factory.create();
         let static_resource_service = self.static_resource_service_factory.create();
         #[cfg(feature = "playground_api")]
@@ -94,6 +100,7 @@ impl NewService for DefaultServiceFactory {
 
         Ok(DefaultService {
             render_service: render_service,
+            action_service: action_service,
             resource_service: resource_service,
             static_resource_service: static_resource_service,
             #[cfg(feature = "playground_api")]
@@ -106,6 +113,7 @@ impl NewService for DefaultServiceFactory {
 #[derive(Debug)]
 pub struct DefaultService {
     render_service: TemplateRenderService,
+    action_service: ActionService,
     resource_service: TemplateResourceService,
     static_resource_service: StaticResourceService,
     #[cfg(feature = "playground_api")] playground_service: PlaygroundApiService,
@@ -230,6 +238,12 @@ impl Service for DefaultService {
                         .unwrap_or_default();
                     let path = if path == "" { "/" } else { path };
 
+                    // Execute store actions posted by the client, including forms
+                    if path.trim_left_matches('/') == "_actions" {
+                        let response = self.action_service.call(&base_url, &app_name, req);
+                        return Box::new(response);
+                    };
+
                     // Serve app static resource
                     let res = self.try_serve_app_resource(&base_url, &app_name, &path);
                     if let Some(res) = res {
@@ -247,7 +261,10 @@ impl Service for DefaultService {
 
                     // Render route
                     let path = format!("/{}", path.trim_left_matches('/'));
-                    let isymtope_req = Request::new(Method::Get, FromStr::from_str(&path).unwrap());
+                    let mut isymtope_req = Request::new(Method::Get, FromStr::from_str(&path).unwrap());
+                    if let Some(cookie) = req.headers().get::<Cookie>() {
+                        isymtope_req.headers_mut().set(cookie.clone());
+                    };
                     let response = self.render_service.call(&base_url, &app_name, isymtope_req);
                     return Box::new(response);
                 }
@@ -343,14 +360,29 @@ impl Service for DefaultService {
                 .unwrap_or_default();
             let path = if path == "" { "/" } else { path };
 
+            // Notify the browser when the app is recompiled
+            if *WATCH_APPS && path == "/_reload" {
+                let response = LIVE_RELOAD.connect(&app_name);
+                return Box::new(future::ok(response));
+            };
+
+            // Execute store actions posted by the client
+            if path == "/_actions" {
+                let response = self.action_service.call(&base_url, &app_name, req);
+                return Box::new(response);
+            };
+
             // Serve app static resource
             let res = self.try_serve_app_resource(&base_url, &app_name, &path);
             if let Some(res) = res {
                 return res;
             }
 
-            // Render route
-            let isymtope_req = Request::new(Method::Get, FromStr::from_str(&path).unwrap());
+            // Render route, in the session carried by the request's cookie
+            let mut isymtope_req = Request::new(Method::Get, FromStr::from_str(&path).unwrap());
+            if let Some(cookie) = req.headers().get::<Cookie>() {
+                isymtope_req.headers_mut().set(cookie.clone());
+            };
             let response = self.render_service.call(&base_url, &app_name, isymtope_req);
             return Box::new(response);
         };
@@ -22,6 +22,8 @@ pub enum IsymtopeServerError {
     SessionAllocationError,
     #[fail(display = "Sesison not found")]
     CannotFindSession,
+    #[fail(display = "Invalid session store configuration: {}", _0)]
+    BadSessionStore(String),
 
     #[fail(display = "Internal error: IOError")]
     IOError(IOError),
@@ -39,6 +41,9 @@ pub enum IsymtopeServerError {
     #[fail(display = "Internal error: render request failed")]
     RenderRequestFailed,
 
+    #[fail(display = "App failed to compile")]
+    AppCompileError(String),
+
     #[fail(display = "Error rendering internal template")]
     InternalRenderError(String),
 
@@ -48,10 +53,37 @@ pub enum IsymtopeServerError {
     #[fail(display = "Session error")]
     SessionError(SessionError),
 
+    #[fail(display = "Invalid action request: {}", _0)]
+    BadActionRequest(String),
+    #[fail(display = "Action request is larger than {} bytes", _0)]
+    ActionRequestTooLarge(usize),
+    #[fail(display = "Value cannot be sent to the client: {}", _0)]
+    ValueNotSerializable(String),
+
     #[fail(display = "Generate error")]
     GenerateError(IsymtopeGenerateError),
 }
 
+impl IsymtopeServerError {
+    pub fn parse_error(&self) -> Option<&TemplateParseError> {
+        match *self {
+            IsymtopeServerError::DocumentProcessingError(ref err) => err.parse_error(),
+            IsymtopeServerError::GenerateError(ref err) => err.parse_error(),
+            _ => None,
+        }
+    }
+
+    /// Rendered diagnostic, for errors that refer to locations in the template source.
+    pub fn diagnostic(&self) -> Option<String> {
+        match *self {
+            IsymtopeServerError::DocumentProcessingError(ref err) => err.diagnostic(),
+            IsymtopeServerError::GenerateError(ref err) => err.diagnostic(),
+            IsymtopeServerError::AppCompileError(ref diagnostic) => Some(diagnostic.to_owned()),
+            _ => None,
+        }
+    }
+}
+
 impl From<AddrParseError> for IsymtopeServerError {
     fn from(err: AddrParseError) -> Self {
         IsymtopeServerError::AddrParseError(err)
@@ -62,6 +94,11 @@ impl From<HyperError> for IsymtopeServerError {
         IsymtopeServerError::HyperError(err)
     }
 }
+impl From<IOError> for IsymtopeServerError {
+    fn from(err: IOError) -> Self {
+        IsymtopeServerError::IOError(err)
+    }
+}
 impl From<FutureCanceled> for IsymtopeServerError {
     fn from(err: FutureCanceled) -> Self {
         IsymtopeServerError::FutureCanceled(err)
@@ -0,0 +1,107 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{self, Map, Value};
+
+use isymtope_ast_common::*;
+use super::*;
+
+const SESSION_FILE_EXTENSION: &str = "json";
+
+/// Keeps each session of an app in a JSON file named by the session id, such as
+/// `{"expires": 1520000000, "state": {...}}`.
+#[derive(Debug)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+fn storage_error<E: ToString>(err: E) -> SessionError {
+    SessionError::StorageError(err.to_string())
+}
+
+fn read_record(path: &Path) -> SessionResult<Option<SessionRecord>> {
+    let mut contents = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut contents).map_err(storage_error)?,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(storage_error(err)),
+    };
+
+    let json: Value = serde_json::from_str(&contents).map_err(storage_error)?;
+    let expires = json.get("expires").and_then(|expires| expires.as_i64());
+    let state = json.get("state")
+        .cloned()
+        .ok_or_else(|| SessionError::StorageError(format!("no state in session file {:?}", path)))?;
+
+    Ok(Some(SessionRecord::new(expires, state)))
+}
+
+impl FileSessionStore {
+    pub fn open(dir: &Path) -> IsymtopeServerResult<Self> {
+        fs::create_dir_all(dir)?;
+
+        Ok(FileSessionStore { dir: dir.to_owned() })
+    }
+
+    /// Path of the file of a session.  Session ids are generated by the server, but
+    /// are checked so that an id can never refer to a file outside the directory.
+    fn session_path(&self, session_id: &str) -> SessionResult<PathBuf> {
+        if session_id.is_empty() || !session_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(SessionError::NotFound);
+        };
+
+        Ok(self.dir
+            .join(session_id)
+            .with_extension(SESSION_FILE_EXTENSION))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&mut self, session_id: &str) -> SessionResult<Option<SessionRecord>> {
+        read_record(&self.session_path(session_id)?)
+    }
+
+    fn save(&mut self, session_id: &str, record: &SessionRecord) -> SessionResult<()> {
+        let path = self.session_path(session_id)?;
+
+        let mut json = Map::new();
+        json.insert("expires".to_owned(), record.expires().map(|expires| expires.into()).unwrap_or(Value::Null));
+        json.insert("state".to_owned(), record.state().to_owned());
+
+        // Replace the file in one step, so that a session is never left half written
+        let temp_path = path.with_extension("tmp");
+        File::create(&temp_path)
+            .and_then(|mut file| file.write_all(Value::Object(json).to_string().as_bytes()))
+            .map_err(storage_error)?;
+        fs::rename(&temp_path, &path).map_err(storage_error)
+    }
+
+    fn remove(&mut self, session_id: &str) -> SessionResult<()> {
+        match fs::remove_file(self.session_path(session_id)?) {
+            Err(ref err) if err.kind() != io::ErrorKind::NotFound => Err(storage_error(err)),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove_expired(&mut self, now: i64) -> SessionResult<()> {
+        for entry in fs::read_dir(&self.dir).map_err(storage_error)? {
+            let path = entry.map_err(storage_error)?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SESSION_FILE_EXTENSION) {
+                continue;
+            };
+
+            // Files which cannot be read are left for the administrator
+            let expired = match read_record(&path) {
+                Ok(Some(record)) => record.is_expired(now),
+                _ => false,
+            };
+
+            if expired {
+                fs::remove_file(&path).map_err(storage_error)?;
+            };
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use serde_json::{Map, Number, Value};
+
+use isymtope_ast_common::*;
+use super::*;
+
+/// Value of each reducer in a session
+pub fn state_to_json(doc: &Document, state: &Session) -> IsymtopeServerResult<Value> {
+    let mut map = Map::new();
+
+    if let Some(reducers) = doc.reducers() {
+        for (key, _) in reducers {
+            if let Some(value) = state.get_value(key)? {
+                map.insert(key.to_owned(), value_to_json(value)?);
+            };
+        }
+    };
+
+    Ok(Value::Object(map))
+}
+
+/// Converts a JSON value sent by a client into an expression value
+pub fn value_from_json(json: &Value) -> ExpressionValue<OutputExpression> {
+    match *json {
+        Value::Null => ExpressionValue::Primitive(Primitive::NullVal),
+        Value::Bool(b) => ExpressionValue::Primitive(Primitive::BoolVal(b)),
+        Value::Number(ref n) => match n.as_i64() {
+            Some(i) if i >= i64::from(i32::min_value()) && i <= i64::from(i32::max_value()) => {
+                ExpressionValue::Primitive(Primitive::Int32Val(i as i32))
+            }
+            _ => ExpressionValue::Primitive(Primitive::FloatVal(n.as_f64().unwrap_or_default())),
+        },
+        Value::String(ref s) => ExpressionValue::Primitive(Primitive::StringVal(s.to_owned())),
+
+        // Empty arrays and objects have no entries, as when parsed from a template
+        Value::Array(ref items) => {
+            let items: Vec<_> = items
+                .iter()
+                .map(|item| ParamValue::new(value_from_json(item)))
+                .collect();
+            let items = if items.is_empty() { None } else { Some(Box::new(items)) };
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(
+                ArrayValue(items),
+            )))
+        }
+        Value::Object(ref props) => {
+            let props: Vec<_> = props
+                .iter()
+                .map(|(key, value)| PropValue::new(key.to_owned(), value_from_json(value), None))
+                .collect();
+            let props = if props.is_empty() { None } else { Some(Box::new(props)) };
+
+            ExpressionValue::Expression(Expression::Composite(CompositeValue::ObjectValue(
+                ObjectValue(props),
+            )))
+        }
+    }
+}
+
+/// Converts an evaluated expression value into JSON
+pub fn value_to_json(value: &ExpressionValue<OutputExpression>) -> IsymtopeServerResult<Value> {
+    match *value {
+        ExpressionValue::Primitive(ref primitive) => Ok(primitive_to_json(primitive)),
+
+        ExpressionValue::Expression(Expression::Composite(CompositeValue::ArrayValue(ArrayValue(
+            ref items,
+        )))) => {
+            let items: IsymtopeServerResult<Vec<_>> = items
+                .iter()
+                .flat_map(|items| items.iter())
+                .map(|item| value_to_json(item.value()))
+                .collect();
+            Ok(Value::Array(items?))
+        }
+
+        ExpressionValue::Expression(Expression::Composite(CompositeValue::ObjectValue(
+            ObjectValue(ref props),
+        ))) => {
+            let mut map = Map::new();
+            for prop in props.iter().flat_map(|props| props.iter()) {
+                map.insert(prop.key().to_owned(), value_to_json(prop.value())?);
+            }
+            Ok(Value::Object(map))
+        }
+
+        _ => Err(IsymtopeServerError::ValueNotSerializable(format!("{:?}", value))),
+    }
+}
+
+fn primitive_to_json(primitive: &Primitive) -> Value {
+    match *primitive {
+        Primitive::CharVal(c) => Value::String(c.to_string()),
+        Primitive::StringVal(ref s) => Value::String(s.to_owned()),
+        Primitive::Int32Val(i) => Value::Number(i.into()),
+        Primitive::FloatVal(f) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        Primitive::BoolVal(b) => Value::Bool(b),
+        Primitive::NullVal | Primitive::Undefined => Value::Null,
+    }
+}
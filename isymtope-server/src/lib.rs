@@ -0,0 +1,89 @@
+#![recursion_limit = "240"]
+#![feature(box_patterns, specialization)]
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate colored;
+
+extern crate log;
+
+#[macro_use]
+extern crate failure;
+
+#[cfg(feature = "session_time")]
+extern crate time;
+
+#[cfg(feature = "sqlite")]
+#[macro_use]
+extern crate rusqlite;
+
+extern crate data_encoding;
+extern crate futures;
+extern crate hmac;
+extern crate hyper;
+extern crate hyper_staticfile;
+extern crate rand;
+extern crate regex;
+extern crate serde_json;
+extern crate sha2;
+extern crate tokio_core;
+extern crate url;
+
+extern crate isymtope_ast_common;
+extern crate isymtope_build;
+extern crate isymtope_generate;
+
+#[cfg(feature = "playground_api")]
+extern crate compiler_service;
+
+pub mod action_service;
+pub mod app;
+pub mod context;
+#[cfg(feature = "cookies")]
+pub mod cookies;
+pub mod sessions;
+pub mod session_store;
+pub mod file_session_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_session_store;
+pub mod default_service;
+pub mod errors;
+pub mod json;
+pub mod render_service;
+pub mod resource_service;
+pub mod message;
+pub mod service;
+pub mod server;
+pub mod srs_generator;
+pub mod static_resource_service;
+pub mod watch;
+
+#[cfg(feature = "playground_api")]
+pub mod playground_api_service;
+
+pub use self::action_service::*;
+pub use self::app::*;
+pub use self::context::*;
+#[cfg(feature = "cookies")]
+pub use self::cookies::*;
+pub use self::srs_generator::*;
+pub use self::sessions::*;
+pub use self::session_store::*;
+pub use self::file_session_store::*;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_session_store::*;
+pub use self::default_service::*;
+pub use self::errors::*;
+pub use self::json::*;
+pub use self::render_service::*;
+pub use self::resource_service::*;
+pub use self::message::*;
+pub use self::service::*;
+pub use self::server::*;
+pub use self::srs_generator::*;
+pub use self::static_resource_service::*;
+pub use self::watch::*;
+
+#[cfg(feature = "playground_api")]
+pub use self::playground_api_service::*;
@@ -1,11 +1,267 @@
+use std::collections::BTreeMap;
+
+use serde_json::{self, Map, Value};
+
+use isymtope_ast_common::*;
+use isymtope_build::{FORM_DISPATCH_FIELD, FORM_FIELD_REFERENCE};
 use isymtope_generate::*;
+use super::*;
 
 #[derive(Debug)]
 pub enum AppRequestMsg {
     TemplateRequest(TemplateRequestMsg),
+    /// Template request using the state of a session, which is kept between requests
+    SessionTemplateRequest(String, TemplateRequestMsg),
+    /// Store action executed in a session, or in a temporary session without one
+    ActionRequest(String, Option<String>, ActionRequest),
+    /// Store actions of a posted form, executed in order in a session
+    FormRequest(Option<String>, Vec<ActionRequest>),
 }
 
 #[derive(Debug)]
 pub enum AppResponseMsg {
     TemplateResponse(TemplateResponseMsg),
+    ActionResponse(ActionResponse),
+    FormResponse,
+}
+
+/// Store action dispatched by a client, sent as JSON such as
+/// `{"type": "COUNTER.INCREMENT", "params": {"by": 2}, "render": "/"}`
+#[derive(Debug)]
+pub struct ActionRequest {
+    action_ty: String,
+    params: Vec<(String, ExpressionValue<OutputExpression>)>,
+    render: Option<String>,
+}
+
+impl ActionRequest {
+    pub fn from_json(json: &Value) -> IsymtopeServerResult<Self> {
+        let action_ty = json.get("type")
+            .and_then(|ty| ty.as_str())
+            .ok_or_else(|| IsymtopeServerError::BadActionRequest("missing action type".to_owned()))?;
+
+        let params = match json.get("params") {
+            Some(&Value::Object(ref params)) => params
+                .iter()
+                .map(|(key, value)| (key.to_owned(), value_from_json(value)))
+                .collect(),
+            Some(&Value::Null) | None => vec![],
+            Some(_) => {
+                return Err(IsymtopeServerError::BadActionRequest("params must be an object".to_owned()));
+            }
+        };
+
+        let render = match json.get("render") {
+            Some(&Value::String(ref path)) if path.starts_with('/') => Some(path.to_owned()),
+            Some(&Value::Null) | None => None,
+            Some(_) => {
+                return Err(IsymtopeServerError::BadActionRequest("render must be a path starting with /".to_owned()));
+            }
+        };
+
+        Ok(ActionRequest {
+            action_ty: action_ty.to_owned(),
+            params: params,
+            render: render,
+        })
+    }
+
+    pub fn action_ty(&self) -> &str {
+        &self.action_ty
+    }
+
+    pub fn params(&self) -> &[(String, ExpressionValue<OutputExpression>)] {
+        &self.params
+    }
+
+    /// Path of the route rendered after executing the action, in place of returning the state
+    pub fn render(&self) -> Option<&str> {
+        self.render.as_ref().map(|s| s.as_str())
+    }
+}
+
+/// Store actions dispatched by the submit button of a form posted without scripts,
+/// described by the hidden fields rendered with the button.
+#[derive(Debug, Default)]
+pub struct FormSubmission {
+    actions: Vec<ActionRequest>,
+    navigate: Option<String>,
+}
+
+impl FormSubmission {
+    pub fn from_form(fields: &[(String, String)]) -> IsymtopeServerResult<Self> {
+        let dispatch_key = match fields.iter().find(|&&(ref name, _)| name == FORM_DISPATCH_FIELD) {
+            Some(&(_, ref value)) => format!("{}.", value),
+            None => return Ok(Default::default()),
+        };
+
+        let mut actions: BTreeMap<usize, (Option<&str>, Option<&str>)> = Default::default();
+        let mut navigate = None;
+
+        for &(ref name, ref value) in fields {
+            if !name.starts_with(&dispatch_key) {
+                continue;
+            };
+
+            let field = &name[dispatch_key.len()..];
+            if field == "navigate" {
+                if !value.starts_with('/') {
+                    return Err(IsymtopeServerError::BadActionRequest("navigate must be a path starting with /".to_owned()));
+                };
+                navigate = Some(value.to_owned());
+                continue;
+            };
+
+            let mut parts = field.splitn(2, '.');
+            let idx = parts.next().and_then(|idx| idx.parse::<usize>().ok());
+            let action = idx.map(|idx| actions.entry(idx).or_insert((None, None)));
+            match (action, parts.next()) {
+                (Some(action), Some("type")) => action.0 = Some(value),
+                (Some(action), Some("params")) => action.1 = Some(value),
+                _ => {
+                    return Err(IsymtopeServerError::BadActionRequest(format!("unknown form field {}", name)));
+                }
+            };
+        }
+
+        let actions: IsymtopeServerResult<Vec<_>> = actions
+            .into_iter()
+            .map(|(_, (action_ty, params))| {
+                let action_ty = action_ty
+                    .ok_or_else(|| IsymtopeServerError::BadActionRequest("missing action type".to_owned()))?;
+
+                let params = match params {
+                    Some(params) => match serde_json::from_str(params) {
+                        Ok(Value::Object(params)) => params
+                            .iter()
+                            .map(|(key, value)| (key.to_owned(), value_from_json(&resolve_form_fields(value, fields))))
+                            .collect(),
+                        Ok(_) => {
+                            return Err(IsymtopeServerError::BadActionRequest("params must be an object".to_owned()));
+                        }
+                        Err(err) => return Err(IsymtopeServerError::BadActionRequest(err.to_string())),
+                    },
+                    None => vec![],
+                };
+
+                Ok(ActionRequest {
+                    action_ty: action_ty.to_owned(),
+                    params: params,
+                    render: None,
+                })
+            })
+            .collect();
+
+        Ok(FormSubmission {
+            actions: actions?,
+            navigate: navigate,
+        })
+    }
+
+    pub fn into_actions(self) -> Vec<ActionRequest> {
+        self.actions
+    }
+
+    /// Path navigated to after executing the actions, in place of returning to the page
+    pub fn navigate(&self) -> Option<&str> {
+        self.navigate.as_ref().map(|s| s.as_str())
+    }
+}
+
+/// Replaces references to form fields in action params with the posted values
+fn resolve_form_fields(value: &Value, fields: &[(String, String)]) -> Value {
+    match *value {
+        Value::Object(ref props) => {
+            if let (1, Some(&Value::String(ref name))) = (props.len(), props.get(FORM_FIELD_REFERENCE)) {
+                let posted = fields.iter().find(|&&(ref field, _)| field == name);
+                return Value::String(posted.map(|&(_, ref value)| value.to_owned()).unwrap_or_default());
+            };
+
+            let props: Map<_, _> = props
+                .iter()
+                .map(|(key, value)| (key.to_owned(), resolve_form_fields(value, fields)))
+                .collect();
+            Value::Object(props)
+        }
+        Value::Array(ref items) => Value::Array(items.iter().map(|item| resolve_form_fields(item, fields)).collect()),
+        ref value => value.to_owned(),
+    }
+}
+
+#[derive(Debug)]
+pub enum ActionResponse {
+    /// Value of each reducer after the action
+    State(Value),
+    /// Body of the rendered route
+    Fragment(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect()
+    }
+
+    fn param<'a>(action: &'a ActionRequest, key: &str) -> Option<&'a ExpressionValue<OutputExpression>> {
+        action.params().iter().find(|p| p.0 == key).map(|p| &p.1)
+    }
+
+    fn string_val(s: &str) -> ExpressionValue<OutputExpression> {
+        ExpressionValue::Primitive(Primitive::StringVal(s.to_owned()))
+    }
+
+    #[test]
+    fn test_form_submission() {
+        let form = fields(&[
+            ("entry", "hello"),
+            ("_dispatch", "btn"),
+            ("btn.1.type", "TODOS.CLEAR"),
+            ("btn.0.type", "TODOS.ADD"),
+            ("btn.0.params", r#"{"text": {"$field": "entry"}, "tags": [{"$field": "missing"}], "n": 2}"#),
+            ("btn.navigate", "/done"),
+            ("other.0.type", "TODOS.REMOVE"),
+        ]);
+        let submission = FormSubmission::from_form(&form).unwrap();
+        assert_eq!(submission.navigate(), Some("/done"));
+
+        // Actions are ordered by their index, rather than the order of the fields
+        let actions = submission.into_actions();
+        let action_tys: Vec<_> = actions.iter().map(|action| action.action_ty()).collect();
+        assert_eq!(action_tys, vec!["TODOS.ADD", "TODOS.CLEAR"]);
+
+        assert_eq!(param(&actions[0], "text"), Some(&string_val("hello")));
+        assert_eq!(param(&actions[0], "tags"), Some(&value_from_json(&serde_json::from_str(r#"[""]"#).unwrap())));
+        assert_eq!(param(&actions[0], "n"), Some(&ExpressionValue::Primitive(Primitive::Int32Val(2))));
+        assert!(actions[1].params().is_empty());
+    }
+
+    #[test]
+    fn test_form_submission_without_dispatch() {
+        let form = fields(&[("entry", "hello"), ("btn.0.type", "TODOS.ADD")]);
+        let submission = FormSubmission::from_form(&form).unwrap();
+        assert_eq!(submission.navigate(), None);
+        assert!(submission.into_actions().is_empty());
+    }
+
+    #[test]
+    fn test_form_submission_rejects_bad_fields() {
+        let submit = |extra: (&str, &str)| FormSubmission::from_form(&fields(&[("_dispatch", "btn"), ("btn.0.type", "TODOS.ADD"), extra]));
+
+        // Only paths within the app are navigated to
+        for navigate in &["http://example.com/", "example.com", ""] {
+            match submit(("btn.navigate", navigate)) {
+                Err(IsymtopeServerError::BadActionRequest(..)) => {}
+                other => panic!("expected a bad action request, got {:?}", other),
+            };
+        }
+
+        for extra in &[("btn.x.type", "TODOS.ADD"), ("btn.0.render", "/"), ("btn.1.params", "{}"), ("btn.0.params", "[1]")] {
+            match submit(*extra) {
+                Err(IsymtopeServerError::BadActionRequest(..)) => {}
+                other => panic!("expected a bad action request for {:?}, got {:?}", extra, other),
+            };
+        }
+    }
 }
@@ -1,9 +1,6 @@
-use std::error::Error;
 use std::io::{Error as IOError, ErrorKind as IOErrorKind};
 use std::str;
 
-use failure::Fail;
-
 use futures::{self, future, Future, Stream};
 use hyper::{Request, Response};
 use hyper::Method::Post;
@@ -86,10 +83,7 @@ fn make_response(compiler_resp: IsymtopeGenerateResult<CompilerResponseMsg>) ->
                 }
 
                 Err(err) => {
-
-                    let err_text = err.cause()
-                            .and_then(|cause| cause.downcast_ref::<ParsingError>())
-                            .map(|err| err.description().to_owned())
+                    let err_text = err.diagnostic()
                         .unwrap_or_else(|| "Unknown error".to_owned());
 
                     response = Response::new()
@@ -3,8 +3,10 @@ use std::io::{Error as IOError, ErrorKind as IOErrorKind};
 #[cfg(feature = "session_time")]
 use time::Duration;
 use futures::{self, future, Future};
-use hyper::{Request, Response};
-use hyper::header::ContentType;
+use hyper::{Request, Response, StatusCode};
+#[cfg(feature = "cookies")]
+use hyper::header::Cookie;
+use hyper::header::{ContentType, Location, SetCookie};
 use hyper::mime;
 use hyper::Error as HyperError;
 
@@ -14,6 +16,33 @@ use super::*;
 pub type ResponseMsgChannel = futures::sync::oneshot::Sender<IsymtopeServerResult<ResponseMsg>>;
 pub type RequestMsgChannel = futures::sync::mpsc::UnboundedSender<(Msg, ResponseMsgChannel)>;
 
+/// Value of the session cookie sent with a request
+#[cfg(feature = "cookies")]
+pub fn request_session_cookie(req: &Request) -> Option<String> {
+    req.headers()
+        .get::<Cookie>()
+        .and_then(|cookie| cookie.get(SESSION_COOKIE_NAME))
+        .map(|value| value.to_owned())
+}
+
+/// Sessions require cookies, so requests are never in a session
+#[cfg(not(feature = "cookies"))]
+pub fn request_session_cookie(_req: &Request) -> Option<String> {
+    None
+}
+
+/// Redirects the client to a path navigated to by a route, which is relative to the app
+pub fn redirect_response(base_url: &str, location: &str, set_cookie: Option<&String>) -> Response {
+    let location = location.trim_left_matches('/');
+    let mut response = Response::new()
+        .with_status(StatusCode::Found)
+        .with_header(Location::new(format!("{}{}", base_url, location)));
+    if let Some(set_cookie) = set_cookie {
+        response.headers_mut().set(SetCookie(vec![set_cookie.to_owned()]));
+    };
+    response
+}
+
 #[derive(Debug)]
 pub struct TemplateRenderServiceFactory {
     sender: RequestMsgChannel,
@@ -63,12 +92,15 @@ impl IsymtopeAppService for TemplateRenderService {
         let (tx, rx) = futures::sync::oneshot::channel::<IsymtopeServerResult<ResponseMsg>>();
         let template_path = "/app.ism".to_owned();
         let path = req.path().to_owned();
+        let base_url = base_url.to_owned();
 
+        let session_cookie = request_session_cookie(&req);
         let render = Msg::RenderAppRoute(
-            base_url.to_owned(),
+            base_url.clone(),
             app_name.to_owned(),
             template_path,
-            path
+            path,
+            session_cookie,
         );
         self.sender.unbounded_send((render, tx)).unwrap();
 
@@ -82,9 +114,44 @@ impl IsymtopeAppService for TemplateRenderService {
             eprintln!("Got render result: {:?}", rendered);
 
             match rendered {
-                Ok(ResponseMsg::RenderComplete(response)) => {
-                    let body = response.into_inner();
-                    let response = Response::new().with_body(body);
+                Ok(ResponseMsg::RenderComplete(ref response, ref set_cookie)) if response.redirect_location().is_some() => {
+                    let location = response.redirect_location().unwrap();
+                    future::ok(redirect_response(&base_url, location, set_cookie.as_ref()))
+                }
+
+                Ok(ResponseMsg::RenderComplete(response, set_cookie)) => {
+                    let status = if response.is_not_found() {
+                        StatusCode::NotFound
+                    } else {
+                        StatusCode::Ok
+                    };
+                    let mut body = response.into_inner();
+                    if *WATCH_APPS {
+                        body = inject_live_reload(body, &base_url);
+                    };
+                    let mut response = Response::new().with_status(status).with_body(body);
+                    if let Some(set_cookie) = set_cookie {
+                        response.headers_mut().set(SetCookie(vec![set_cookie]));
+                    };
+                    future::ok(response)
+                }
+
+                // Show the error in place of the app until it is fixed
+                Err(ref err) if err.diagnostic().is_some() && *WATCH_APPS => {
+                    let body = compile_error_page(&err.diagnostic().unwrap(), &base_url);
+                    let response = Response::new()
+                        .with_status(StatusCode::InternalServerError)
+                        .with_header(ContentType(mime::TEXT_HTML_UTF_8))
+                        .with_body(body);
+                    future::ok(response)
+                }
+
+                Err(ref err) if err.diagnostic().is_some() => {
+                    let body = err.diagnostic().unwrap();
+                    let response = Response::new()
+                        .with_status(StatusCode::InternalServerError)
+                        .with_header(ContentType(mime::TEXT_PLAIN_UTF_8))
+                        .with_body(body);
                     future::ok(response)
                 }
 
@@ -111,3 +178,21 @@ impl IsymtopeAppService for TemplateRenderService {
 //     fn call(&self, req: Self::Request) -> Self::Future {
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_response() {
+        let response = redirect_response("/app/test/", "/login", None);
+        assert_eq!(response.status(), StatusCode::Found);
+        assert_eq!(response.headers().get::<Location>().map(|l| &**l), Some("/app/test/login"));
+        assert!(response.headers().get::<SetCookie>().is_none());
+
+        let set_cookie = "session=abc".to_owned();
+        let response = redirect_response("/", "/", Some(&set_cookie));
+        assert_eq!(response.headers().get::<Location>().map(|l| &**l), Some("/"));
+        assert_eq!(response.headers().get::<SetCookie>(), Some(&SetCookie(vec![set_cookie.clone()])));
+    }
+}
@@ -6,6 +6,8 @@ use std::thread;
 use futures::{self, Stream};
 use futures::future;
 
+#[cfg(feature = "session_time")]
+use time::Duration;
 use tokio_core::reactor::Core;
 use tokio_core::net::TcpListener;
 use hyper::server::{Http, NewService, Service};
@@ -18,6 +20,22 @@ lazy_static! {
     pub static ref APP_DIR: Box<PathBuf> = Box::new(env::var_os("APP_DIR").expect("APP_DIR must be provided").into());
     pub static ref STATIC_RESOURCE_DIR: Box<PathBuf> = Box::new(env::var_os("STATIC_RESOURCE_DIR").expect("STATIC_RESOURCE_DIR must be provided").into());
     pub static ref DEFAULT_APP: String = env::var_os("DEFAULT_APP").expect("DEFAULT_APP must be provided").to_string_lossy().to_string();
+    /// Recompile apps when their files change, reloading connected browsers
+    pub static ref WATCH_APPS: bool = env::var_os("WATCH_APPS").is_some();
+    /// Where sessions are kept: `memory`, `file:<dir>` or `sqlite:<path>`
+    pub static ref SESSION_STORE: String = env::var("SESSION_STORE").unwrap_or_else(|_| "memory".to_owned());
+    /// Key the session cookies are signed with, so that cookies stay
+    /// valid across restarts.  The key is random for each run when not set.
+    pub static ref SESSION_SECRET: Option<String> = env::var("SESSION_SECRET").ok();
+}
+
+#[cfg(feature = "session_time")]
+lazy_static! {
+    /// Lifetime of new sessions, in seconds (one day by default)
+    pub static ref SESSION_EXPIRES: Duration = env::var("SESSION_EXPIRES")
+        .ok()
+        .map(|secs| Duration::seconds(secs.parse().expect("SESSION_EXPIRES must be a number of seconds")))
+        .unwrap_or_else(|| Duration::days(1));
 }
 
 pub trait ServiceInject: Debug {
@@ -53,6 +71,9 @@ pub fn run_server(addr: &str) -> IsymtopeServerResult<()> {
     let app_dir = &*APP_DIR;
 
     let server_msg_handler = spawn_server_msg_handler(app_dir)?;
+    if *WATCH_APPS {
+        spawn_app_watcher(app_dir, server_msg_handler.clone())?;
+    };
 
     #[cfg(feature = "playground_api")]
     let compiler_msg_handler = spawn_compiler_service()?;
@@ -66,7 +87,8 @@ pub fn run_server(addr: &str) -> IsymtopeServerResult<()> {
     let handle = core.handle();
 
     let render_service_factory =
-        TemplateRenderServiceFactory::new(server_msg_handler, handle.clone(), default_app_str.to_owned());
+        TemplateRenderServiceFactory::new(server_msg_handler.clone(), handle.clone(), default_app_str.to_owned());
+    let action_service_factory = ActionServiceFactory::new(server_msg_handler, handle.clone());
     let static_resource_service_factory = StaticResourceServiceFactory::new(handle.clone());
     let resource_service_factory = TemplateResourceServiceFactory::new(handle.clone());
 
@@ -75,6 +97,7 @@ pub fn run_server(addr: &str) -> IsymtopeServerResult<()> {
 
     let factory = DefaultServiceFactory::new(
         render_service_factory,
+        action_service_factory,
         resource_service_factory,
         static_resource_service_factory,
         #[cfg(feature = "playground_api")]
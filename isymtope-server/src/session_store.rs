@@ -0,0 +1,304 @@
+use std::fmt::Debug;
+use std::path::Path;
+
+#[cfg(feature = "session_time")]
+use time::{get_time, Timespec};
+use serde_json::{Map, Value};
+
+use isymtope_ast_common::*;
+use isymtope_generate::*;
+use super::*;
+
+/// Serialized state of a session, with the time it expires in seconds since the epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRecord {
+    expires: Option<i64>,
+    state: Value,
+}
+
+impl SessionRecord {
+    pub fn new(expires: Option<i64>, state: Value) -> Self {
+        SessionRecord {
+            expires: expires,
+            state: state,
+        }
+    }
+
+    pub fn expires(&self) -> Option<i64> {
+        self.expires
+    }
+
+    pub fn state(&self) -> &Value {
+        &self.state
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
+    }
+}
+
+/// Durable storage of the sessions of an app, so that they survive restarts of the
+/// server.  Sessions are kept in memory while in use, and written to the store
+/// whenever their state changes.
+pub trait SessionStore: Debug {
+    fn load(&mut self, session_id: &str) -> SessionResult<Option<SessionRecord>>;
+    fn save(&mut self, session_id: &str, record: &SessionRecord) -> SessionResult<()>;
+    fn remove(&mut self, session_id: &str) -> SessionResult<()>;
+    /// Removes the sessions which expired at or before `now`, in seconds since the epoch
+    fn remove_expired(&mut self, now: i64) -> SessionResult<()>;
+}
+
+/// Opens the store for the sessions of an app, as configured by `SESSION_STORE`,
+/// which is `memory` to keep sessions only in memory, `file:<dir>` to keep each
+/// session in a file, or `sqlite:<path>` to keep sessions in an SQLite database.
+pub fn open_session_store(config: &str, app_name: &str) -> IsymtopeServerResult<Option<Box<SessionStore>>> {
+    let (kind, location) = match config.find(':') {
+        Some(idx) => (&config[..idx], Some(&config[idx + 1..])),
+        None => (config, None),
+    };
+
+    match (kind, location) {
+        ("memory", None) => Ok(None),
+
+        ("file", Some(dir)) if !dir.is_empty() => {
+            let store = FileSessionStore::open(&Path::new(dir).join(app_name))?;
+            Ok(Some(Box::new(store)))
+        }
+
+        #[cfg(feature = "sqlite")]
+        ("sqlite", Some(path)) if !path.is_empty() => {
+            let store = SqliteSessionStore::open(Path::new(path), app_name)?;
+            Ok(Some(Box::new(store)))
+        }
+
+        #[cfg(not(feature = "sqlite"))]
+        ("sqlite", _) => Err(IsymtopeServerError::BadSessionStore(
+            "the server was built without the sqlite feature".to_owned(),
+        )),
+
+        _ => Err(IsymtopeServerError::BadSessionStore(config.to_owned())),
+    }
+}
+
+/// Serializes the state of a session, with the value of each reducer
+#[cfg(feature = "session_time")]
+pub fn session_to_record(session: &MemorySession) -> IsymtopeServerResult<SessionRecord> {
+    let mut state = Map::new();
+    state.insert("created".to_owned(), Value::Number(session.created().sec.into()));
+    state.insert("values".to_owned(), values_to_json(session)?);
+
+    Ok(SessionRecord::new(session.expires().map(|expires| expires.sec), Value::Object(state)))
+}
+
+/// Serializes the state of a session, with the value of each reducer
+#[cfg(not(feature = "session_time"))]
+pub fn session_to_record(session: &MemorySession) -> IsymtopeServerResult<SessionRecord> {
+    let mut state = Map::new();
+    state.insert("values".to_owned(), values_to_json(session)?);
+
+    Ok(SessionRecord::new(None, Value::Object(state)))
+}
+
+/// Restores a session from its serialized state
+#[cfg(feature = "session_time")]
+pub fn session_from_record(record: &SessionRecord) -> IsymtopeServerResult<MemorySession> {
+    let created = record.state()
+        .get("created")
+        .and_then(|created| created.as_i64())
+        .map(|created| Timespec::new(created, 0))
+        .unwrap_or_else(get_time);
+    let expires = record.expires().map(|expires| Timespec::new(expires, 0));
+
+    let mut session = MemorySession::restore(created, expires);
+    values_from_json(&mut session, record.state())?;
+    Ok(session)
+}
+
+/// Restores a session from its serialized state
+#[cfg(not(feature = "session_time"))]
+pub fn session_from_record(record: &SessionRecord) -> IsymtopeServerResult<MemorySession> {
+    let mut session = MemorySession::new();
+    values_from_json(&mut session, record.state())?;
+    Ok(session)
+}
+
+fn values_to_json(session: &MemorySession) -> IsymtopeServerResult<Value> {
+    let mut values = Map::new();
+    for (key, value) in session.values() {
+        values.insert(key.to_owned(), value_to_json(value)?);
+    }
+
+    Ok(Value::Object(values))
+}
+
+fn values_from_json(session: &mut MemorySession, state: &Value) -> IsymtopeServerResult<()> {
+    let values = state
+        .get("values")
+        .and_then(|values| values.as_object())
+        .ok_or_else(|| SessionError::StorageError("stored session has no values".to_owned()))?;
+
+    for (key, value) in values {
+        session.set_value(key, value_from_json(value), false)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rand::{thread_rng, Rng};
+    use serde_json::Value;
+
+    use isymtope_ast_common::*;
+    use isymtope_generate::*;
+    use super::super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let suffix: String = thread_rng().gen_ascii_chars().take(12).collect();
+        env::temp_dir().join(format!("isymtope-{}-{}", name, suffix))
+    }
+
+    fn record(expires: Option<i64>, counter: i64) -> SessionRecord {
+        let state = json_state(counter);
+        SessionRecord::new(expires, state)
+    }
+
+    fn json_state(counter: i64) -> Value {
+        let mut values = ::serde_json::Map::new();
+        values.insert("counter".to_owned(), Value::Number(counter.into()));
+        let mut state = ::serde_json::Map::new();
+        state.insert("values".to_owned(), Value::Object(values));
+        Value::Object(state)
+    }
+
+    fn counter(value: Option<&ExpressionValue<OutputExpression>>) -> Option<i32> {
+        match value {
+            Some(&ExpressionValue::Primitive(Primitive::Int32Val(n))) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Behaviour every session store must have
+    fn check_store(store: &mut SessionStore) {
+        assert_eq!(store.load("missing").unwrap(), None);
+
+        store.save("a", &record(None, 1)).unwrap();
+        store.save("b", &record(Some(100), 2)).unwrap();
+        store.save("c", &record(Some(200), 3)).unwrap();
+        assert_eq!(store.load("a").unwrap(), Some(record(None, 1)));
+        assert_eq!(store.load("b").unwrap(), Some(record(Some(100), 2)));
+
+        // Saving replaces the previous state
+        store.save("a", &record(None, 4)).unwrap();
+        assert_eq!(store.load("a").unwrap(), Some(record(None, 4)));
+
+        store.remove("a").unwrap();
+        assert_eq!(store.load("a").unwrap(), None);
+        store.remove("a").unwrap();
+
+        // Sessions expire at their expiry time, and sessions without one never expire
+        store.save("d", &record(None, 5)).unwrap();
+        store.remove_expired(100).unwrap();
+        assert_eq!(store.load("b").unwrap(), None);
+        assert_eq!(store.load("c").unwrap(), Some(record(Some(200), 3)));
+        assert_eq!(store.load("d").unwrap(), Some(record(None, 5)));
+    }
+
+    /// Sessions written to a store are restored by the sessions of a restarted server
+    fn check_restart<F>(open: F)
+    where
+        F: Fn() -> Box<SessionStore>,
+    {
+        let mut srs = DefaultSecureRandomStringGenerator::default();
+
+        let session_id = {
+            let mut sessions = MemorySessions::with_store(Some(open()));
+            let session = sessions.get_or_create(None, &mut srs).unwrap();
+            let session_id = session.session_id().to_owned();
+
+            let value = ExpressionValue::Primitive(Primitive::Int32Val(7));
+            sessions.get_mut(&session_id).unwrap().set_value("counter", value, true).unwrap();
+            sessions.persist(&session_id).unwrap();
+            session_id
+        };
+
+        let mut sessions = MemorySessions::with_store(Some(open()));
+        let session = sessions.get_or_create(Some(&session_id), &mut srs).unwrap();
+        assert_eq!(session, SessionCreationType::ExistingSession(session_id.clone()));
+
+        let value = sessions.get(&session_id).unwrap().get_value("counter").unwrap();
+        assert_eq!(counter(value), Some(7));
+
+        // Destroyed sessions are removed from the store
+        sessions.destroy(&session_id).unwrap();
+        let mut sessions = MemorySessions::with_store(Some(open()));
+        let session = sessions.get_or_create(Some(&session_id), &mut srs).unwrap();
+        assert!(session != SessionCreationType::ExistingSession(session_id));
+    }
+
+    #[test]
+    fn test_session_record_roundtrip() {
+        let mut session = MemorySession::default();
+        let values = vec![
+            ("counter", ExpressionValue::Primitive(Primitive::Int32Val(3))),
+            ("entry", ExpressionValue::Primitive(Primitive::StringVal("text".to_owned()))),
+        ];
+        for (key, value) in values {
+            session.set_value(key, value, true).unwrap();
+        }
+
+        let record = session_to_record(&session).unwrap();
+        let restored = session_from_record(&record).unwrap();
+        assert_eq!(counter(restored.get_value("counter").unwrap()), Some(3));
+        assert_eq!(
+            restored.get_value("entry").unwrap(),
+            Some(&ExpressionValue::Primitive(Primitive::StringVal("text".to_owned())))
+        );
+        assert_eq!(session_to_record(&restored).unwrap(), record);
+    }
+
+    #[test]
+    fn test_open_session_store() {
+        assert!(open_session_store("memory", "app").unwrap().is_none());
+        assert!(open_session_store("file:", "app").is_err());
+        assert!(open_session_store("redis:localhost", "app").is_err());
+    }
+
+    #[test]
+    fn test_file_session_store() {
+        let dir = temp_path("file-store");
+        check_store(&mut FileSessionStore::open(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_session_store_restart() {
+        let dir = temp_path("file-restart");
+        check_restart(|| Box::new(FileSessionStore::open(&dir).unwrap()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_session_store() {
+        let path = temp_path("sqlite-store");
+        check_store(&mut SqliteSessionStore::open(&path, "app").unwrap());
+
+        // Apps sharing a database have separate sessions
+        let mut other = SqliteSessionStore::open(&path, "other").unwrap();
+        assert_eq!(other.load("c").unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_session_store_restart() {
+        let path = temp_path("sqlite-restart");
+        check_restart(|| Box::new(SqliteSessionStore::open(&path, "app").unwrap()));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -4,10 +4,136 @@ use std::collections::HashMap;
 
 use isymtope_ast_common::*;
 use isymtope_generate::*;
+use super::*;
 
+/// Random bytes in each session id, a multiple of 5 so that the encoded id is not padded
+pub const SESSIONS_SECURE_STRING_BYTES: usize = 30;
+
+/// Least time between removals of expired sessions, in seconds
+#[cfg(feature = "session_time")]
+pub const EXPIRED_SESSIONS_INTERVAL_SECS: i64 = 60;
+
+/// Session used for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionCreationType {
+    ExistingSession(String),
+    /// Created because the request had no valid session
+    CreatedSession(String),
+}
+
+impl SessionCreationType {
+    pub fn session_id(&self) -> &str {
+        match *self {
+            SessionCreationType::ExistingSession(ref session_id) => session_id,
+            SessionCreationType::CreatedSession(ref session_id) => session_id,
+        }
+    }
+}
+
+/// Sessions kept in memory for the life of the server, and written to a session
+/// store if one is given, so that they are restored after a restart.  Without
+/// `session_time`, sessions do not expire and are only removed when destroyed.
 #[derive(Debug, Default)]
 pub struct MemorySessions {
     session_map: HashMap<String, MemorySession>,
+    store: Option<Box<SessionStore>>,
+    /// When expired sessions were last removed
+    #[cfg(feature = "session_time")]
+    expired_removed: Option<Timespec>,
+}
+
+impl MemorySessions {
+    pub fn with_store(store: Option<Box<SessionStore>>) -> Self {
+        MemorySessions {
+            session_map: Default::default(),
+            store: store,
+            #[cfg(feature = "session_time")]
+            expired_removed: None,
+        }
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<&MemorySession> {
+        self.session_map.get(session_id)
+    }
+
+    pub fn get_mut(&mut self, session_id: &str) -> Option<&mut MemorySession> {
+        self.session_map.get_mut(session_id)
+    }
+
+    /// Returns the session with the given id if it is valid, or creates a new session
+    /// with a randomly generated id.
+    pub fn get_or_create(
+        &mut self,
+        session_id: Option<&str>,
+        srs: &mut SecureRandomStringGenerator,
+    ) -> IsymtopeServerResult<SessionCreationType> {
+        if let Some(session_id) = session_id {
+            self.load(session_id)?;
+            match self.validate(session_id) {
+                Ok(_) => {
+                    return Ok(SessionCreationType::ExistingSession(session_id.to_owned()));
+                }
+                Err(SessionError::Expired) => self.destroy(session_id)?,
+                Err(_) => {}
+            };
+        };
+
+        #[cfg(feature = "session_time")]
+        self.remove_expired_periodically(&get_time())?;
+
+        let session_id = srs.generate_secure_string(SESSIONS_SECURE_STRING_BYTES)?;
+        if self.session_map.contains_key(&session_id) {
+            return Err(IsymtopeServerError::SessionKeyConflict);
+        };
+
+        #[cfg(feature = "session_time")]
+        self.create(&session_id, Some(*SESSION_EXPIRES))?;
+        #[cfg(not(feature = "session_time"))]
+        self.create(&session_id)?;
+
+        Ok(SessionCreationType::CreatedSession(session_id))
+    }
+
+    /// Removes expired sessions, unless they were removed less than
+    /// `EXPIRED_SESSIONS_INTERVAL_SECS` ago, so that creating sessions does not
+    /// scan the store each time
+    #[cfg(feature = "session_time")]
+    fn remove_expired_periodically(&mut self, now: &Timespec) -> SessionResult<()> {
+        let interval = Duration::seconds(EXPIRED_SESSIONS_INTERVAL_SECS);
+        if let Some(removed) = self.expired_removed {
+            if *now < removed + interval {
+                return Ok(());
+            };
+        };
+
+        self.remove_expired(now)?;
+        self.expired_removed = Some(*now);
+        Ok(())
+    }
+
+    /// Restores a session which is not in memory from the store
+    fn load(&mut self, session_id: &str) -> IsymtopeServerResult<()> {
+        if self.session_map.contains_key(session_id) {
+            return Ok(());
+        };
+
+        let record = match self.store {
+            Some(ref mut store) => store.load(session_id)?,
+            None => None,
+        };
+
+        if let Some(record) = record {
+            let session = session_from_record(&record)?;
+            self.session_map.insert(session_id.to_owned(), session);
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "session_time")]
+fn is_expired(session: &MemorySession, now: &Timespec) -> bool {
+    session.expires().map(|expires| expires <= now).unwrap_or(false)
 }
 
 impl Sessions for MemorySessions {
@@ -28,73 +154,110 @@ impl Sessions for MemorySessions {
         Ok(())
     }
 
-    fn validate(&mut self, _session_id: &str) -> SessionResult<()> {
+    #[cfg(feature = "session_time")]
+    fn validate(&mut self, session_id: &str) -> SessionResult<()> {
+        match self.session_map.get(session_id) {
+            Some(session) if is_expired(session, &get_time()) => Err(SessionError::Expired),
+            Some(_) => Ok(()),
+            None => Err(SessionError::NotFound),
+        }
+    }
+
+    #[cfg(not(feature = "session_time"))]
+    fn validate(&mut self, session_id: &str) -> SessionResult<()> {
+        match self.session_map.get(session_id) {
+            Some(_) => Ok(()),
+            None => Err(SessionError::NotFound),
+        }
+    }
+
+    fn destroy(&mut self, session_id: &str) -> SessionResult<()> {
+        self.session_map.remove(session_id);
+        if let Some(ref mut store) = self.store {
+            store.remove(session_id)?;
+        };
+        Ok(())
+    }
+
+    #[cfg(feature = "session_time")]
+    fn remove_expired(&mut self, now: &Timespec) -> SessionResult<()> {
+        self.session_map.retain(|_, session| !is_expired(session, now));
+        if let Some(ref mut store) = self.store {
+            store.remove_expired(now.sec)?;
+        };
         Ok(())
     }
 
-    fn destroy(&mut self, _session_id: &str) -> SessionResult<()> {
+    fn persist(&mut self, session_id: &str) -> SessionResult<()> {
+        let session = self.session_map.get(session_id).ok_or(SessionError::NotFound)?;
+        if let Some(ref mut store) = self.store {
+            let record = session_to_record(session).map_err(|err| SessionError::StorageError(err.to_string()))?;
+            store.save(session_id, &record)?;
+        };
         Ok(())
     }
 
     fn execute_action(
         &mut self,
-        _session_id: &str,
+        session_id: &str,
         _action_op: &ActionOp<ProcessedExpression>,
     ) -> SessionResult<()> {
-        Ok(())
+        // Actions are executed against the session by the app's template context,
+        // which has the document the reducers belong to.
+        self.validate(session_id)
     }
 }
 
-// impl Sessions {
-//     pub fn get_or_create_session<'s>(&'s mut self, previous_key: Option<&str>) -> IsymtopeServerResult<ReturnedSession<'s>> {
-
-//     pub fn create_empty_session(&self) -> IsymtopeServerResult<Session> {
-//         let session_key = self.srs.generate_secure_string(self::SESSIONS_SECURE_STRING_BYTES);
-//         let ts = get_time();
-
-//         create_session_with_key(session_key, ts)
-//     }
-
-//     #[allow(dead_code)]
-//     fn create_session(&self) -> IsymtopeServerResult<(String, Session)> {
-//         let session_key = self.srs.generate_secure_string(self::SESSIONS_SECURE_STRING_BYTES);
-//         let ts = get_time();
-
-//         create_session_with_key(session_key.clone(), ts)
-//             .map(|session| (session_key, session))
-//     }
-
-//     pub fn expire_session(&mut self, session_key: &str) -> IsymtopeServerVoidResult {
-//         // TODO: Implement expiration mechanism
-//         Ok(())
-//     }
-
-//     fn allocate_new_session<'s>(&'s mut self) -> IsymtopeServerResult<ReturnedSession<'s>> {
-//         let (session_key, item) = self.create_session()?;
-
-//         match self.session_map.entry(session_key.clone()) {
-//             Entry::Occupied(o) => {
-//                 // This should not happen
-//                 panic!(format!("Had previous entry with newly generated session key {:?}", session_key));
-//             }
-
-//             Entry::Vacant(v) => {
-//                 let entry = v.insert(item);
-//                 Ok(ReturnedSession(SessionCreationType::CreatedSession(session_key), entry))
-//             }
-//         }
-//     }
-
-//     #[allow(dead_code)]
-//     pub fn get_or_create_session<'s>(&'s mut self, previous_key: Option<&str>) -> IsymtopeServerResult<ReturnedSession<'s>> {
-//         if let Some(previous_key) = previous_key {
-//             if let Some(session) = self.session_map.get_mut(previous_key) {
-//                 return Ok(ReturnedSession(SessionCreationType::ExistingSession, session));
-//             };
-
-//             Err(IsymtopeServerError::CannotFindSession)
-//         } else {
-//             self.allocate_new_session()
-//         }
-//     }
-// }
+#[cfg(all(test, feature = "session_time"))]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use time::{get_time, Duration};
+
+    use isymtope_ast_common::*;
+    use super::super::*;
+
+    /// Store counting the removals of expired sessions
+    #[derive(Debug, Default)]
+    struct CountingStore {
+        removals: Rc<Cell<usize>>,
+    }
+
+    impl SessionStore for CountingStore {
+        fn load(&mut self, _session_id: &str) -> SessionResult<Option<SessionRecord>> {
+            Ok(None)
+        }
+
+        fn save(&mut self, _session_id: &str, _record: &SessionRecord) -> SessionResult<()> {
+            Ok(())
+        }
+
+        fn remove(&mut self, _session_id: &str) -> SessionResult<()> {
+            Ok(())
+        }
+
+        fn remove_expired(&mut self, _now: i64) -> SessionResult<()> {
+            self.removals.set(self.removals.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_remove_expired_periodically() {
+        let store = CountingStore::default();
+        let removals = store.removals.clone();
+        let mut sessions = MemorySessions::with_store(Some(Box::new(store)));
+        let mut srs = DefaultSecureRandomStringGenerator::default();
+
+        // Creating sessions removes expired sessions at most once an interval
+        sessions.get_or_create(None, &mut srs).unwrap();
+        sessions.get_or_create(None, &mut srs).unwrap();
+        assert_eq!(removals.get(), 1);
+
+        let later = get_time() + Duration::seconds(EXPIRED_SESSIONS_INTERVAL_SECS);
+        sessions.remove_expired_periodically(&later).unwrap();
+        sessions.remove_expired_periodically(&later).unwrap();
+        assert_eq!(removals.get(), 2);
+    }
+}
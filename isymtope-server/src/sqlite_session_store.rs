@@ -0,0 +1,97 @@
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{Connection, Error as SqliteError, OptionalExtension};
+use serde_json;
+
+use isymtope_ast_common::*;
+use super::*;
+
+/// Time to wait for other connections to release the database
+const SQLITE_BUSY_TIMEOUT_MS: u64 = 5000;
+
+fn storage_error(err: SqliteError) -> SessionError {
+    SessionError::StorageError(err.to_string())
+}
+
+/// Keeps the sessions of apps in an SQLite database, with a row for each session
+/// holding its state as JSON.  Apps can share a database, as rows are keyed by the
+/// app name and session id.
+#[derive(Debug)]
+pub struct SqliteSessionStore {
+    conn: Connection,
+    app_name: String,
+}
+
+impl SqliteSessionStore {
+    pub fn open(path: &Path, app_name: &str) -> IsymtopeServerResult<Self> {
+        let conn = Connection::open(path).map_err(storage_error)?;
+        conn.busy_timeout(Duration::from_millis(SQLITE_BUSY_TIMEOUT_MS))
+            .map_err(storage_error)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                app TEXT NOT NULL,
+                id TEXT NOT NULL,
+                expires INTEGER,
+                state TEXT NOT NULL,
+                PRIMARY KEY (app, id)
+            )",
+            [],
+        ).map_err(storage_error)?;
+
+        Ok(SqliteSessionStore {
+            conn: conn,
+            app_name: app_name.to_owned(),
+        })
+    }
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn load(&mut self, session_id: &str) -> SessionResult<Option<SessionRecord>> {
+        let row: Option<(Option<i64>, String)> = self.conn
+            .query_row(
+                "SELECT expires, state FROM sessions WHERE app = ?1 AND id = ?2",
+                params![self.app_name, session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(storage_error)?;
+
+        match row {
+            Some((expires, state)) => serde_json::from_str(&state)
+                .map(|state| Some(SessionRecord::new(expires, state)))
+                .map_err(|err| SessionError::StorageError(err.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&mut self, session_id: &str, record: &SessionRecord) -> SessionResult<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO sessions (app, id, expires, state) VALUES (?1, ?2, ?3, ?4)",
+                params![self.app_name, session_id, record.expires(), record.state().to_string()],
+            )
+            .map(|_| ())
+            .map_err(storage_error)
+    }
+
+    fn remove(&mut self, session_id: &str) -> SessionResult<()> {
+        self.conn
+            .execute(
+                "DELETE FROM sessions WHERE app = ?1 AND id = ?2",
+                params![self.app_name, session_id],
+            )
+            .map(|_| ())
+            .map_err(storage_error)
+    }
+
+    fn remove_expired(&mut self, now: i64) -> SessionResult<()> {
+        self.conn
+            .execute(
+                "DELETE FROM sessions WHERE app = ?1 AND expires IS NOT NULL AND expires <= ?2",
+                params![self.app_name, now],
+            )
+            .map(|_| ())
+            .map_err(storage_error)
+    }
+}
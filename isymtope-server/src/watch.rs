@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use futures::{self, Future};
+use hyper::{Body, Chunk, Response};
+use hyper::Error as HyperError;
+use hyper::header::{CacheControl, CacheDirective, ContentType};
+use hyper::mime;
+
+use super::*;
+
+/// Interval between scans of the app directory for changes
+const WATCH_INTERVAL_MS: u64 = 500;
+
+const LIVE_RELOAD_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/res/scripts/isymtope-live-reload.js"
+));
+
+lazy_static! {
+    pub static ref LIVE_RELOAD: LiveReloadClients = Default::default();
+}
+
+/// Event pushed to browsers when an app has been recompiled
+#[derive(Debug)]
+pub enum LiveReloadEvent {
+    Reload,
+    CompileError(String),
+}
+
+impl LiveReloadEvent {
+    fn to_event_stream(&self) -> String {
+        match *self {
+            LiveReloadEvent::Reload => "event: reload\ndata: \n\n".to_owned(),
+            LiveReloadEvent::CompileError(ref diagnostic) => {
+                let data: Vec<_> = diagnostic.lines().map(|l| format!("data: {}\n", l)).collect();
+                format!("event: compile-error\n{}\n", data.concat())
+            }
+        }
+    }
+}
+
+type EventStreamSender = futures::sync::mpsc::Sender<Result<Chunk, HyperError>>;
+
+/// Browsers connected to the live reload endpoint of each app
+#[derive(Debug, Default)]
+pub struct LiveReloadClients(Mutex<Vec<(String, EventStreamSender)>>);
+
+impl LiveReloadClients {
+    /// Creates an event stream response for a browser showing the app
+    pub fn connect(&self, app_name: &str) -> Response {
+        let (mut sender, body) = Body::pair();
+        sender.try_send(Ok(Chunk::from("retry: 1000\n\n"))).ok();
+        self.0.lock().unwrap().push((app_name.to_owned(), sender));
+
+        Response::new()
+            .with_header(ContentType(mime::TEXT_EVENT_STREAM))
+            .with_header(CacheControl(vec![CacheDirective::NoCache]))
+            .with_body(body)
+    }
+
+    /// Sends an event to the browsers showing the app, dropping those that disconnected
+    pub fn notify(&self, app_name: &str, event: &LiveReloadEvent) {
+        let chunk = event.to_event_stream();
+        let mut clients = self.0.lock().unwrap();
+
+        let mut connected = Vec::with_capacity(clients.len());
+        for (name, mut sender) in clients.drain(..) {
+            if name == app_name {
+                // A full buffer means the browser has not read the previous event,
+                // which will cause it to reload anyway.
+                if let Err(err) = sender.try_send(Ok(Chunk::from(chunk.clone()))) {
+                    if err.is_disconnected() {
+                        continue;
+                    };
+                };
+            };
+            connected.push((name, sender));
+        }
+        *clients = connected;
+    }
+}
+
+/// Adds the live reload client to a rendered page
+pub fn inject_live_reload(page: String, base_url: &str) -> String {
+    let script = live_reload_script(base_url, None);
+
+    match page.rfind("</body>") {
+        Some(idx) => format!("{}{}{}", &page[..idx], script, &page[idx..]),
+        None => page + &script,
+    }
+}
+
+/// Page shown in place of an app which failed to compile, until it is fixed
+pub fn compile_error_page(diagnostic: &str, base_url: &str) -> String {
+    format!(
+        "<!doctype HTML>\n<html>\n<head>\n  <meta charset=\"utf-8\" />\n  <title>Compile error</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        live_reload_script(base_url, Some(diagnostic))
+    )
+}
+
+fn live_reload_script(base_url: &str, error: Option<&str>) -> String {
+    let error_attr = error
+        .map(|e| format!(" data-isymtope-error=\"{}\"", escape_attr(e)))
+        .unwrap_or_default();
+
+    format!(
+        "<script data-isymtope-live-reload=\"{}\"{}>\n{}</script>\n",
+        escape_attr(&format!("{}_reload", base_url)),
+        error_attr,
+        LIVE_RELOAD_SCRIPT
+    )
+}
+
+fn escape_attr(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+type AppSnapshot = HashMap<PathBuf, (SystemTime, u64)>;
+
+/// Watches each app in the app directory, recompiling an app when any of its
+/// templates or resources change and notifying the browsers showing it.  Changes
+/// are found by comparing the modification time and size of each file.
+pub fn spawn_app_watcher(app_dir: &Path, sender: RequestMsgChannel) -> IsymtopeServerResult<()> {
+    let app_dir = app_dir.to_owned();
+    let mut snapshots = snapshot_apps(&app_dir)?;
+
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(WATCH_INTERVAL_MS));
+
+        let current = match snapshot_apps(&app_dir) {
+            Ok(current) => current,
+            Err(err) => {
+                eprintln!("[watch] cannot read app directory [{:?}]: {}", app_dir, err);
+                continue;
+            }
+        };
+
+        for (app_name, snapshot) in current.iter() {
+            if snapshots.get(app_name) != Some(snapshot) {
+                reload_app(&sender, app_name);
+            };
+        }
+
+        snapshots = current;
+    });
+
+    Ok(())
+}
+
+fn reload_app(sender: &RequestMsgChannel, app_name: &str) {
+    eprintln!("[watch] app [{}] changed, recompiling", app_name);
+
+    let (tx, rx) = futures::sync::oneshot::channel::<IsymtopeServerResult<ResponseMsg>>();
+    if sender.unbounded_send((Msg::ReloadApp(app_name.to_owned()), tx)).is_err() {
+        return;
+    };
+
+    let event = match rx.wait() {
+        Ok(Ok(_)) => LiveReloadEvent::Reload,
+        Ok(Err(err)) => {
+            let diagnostic = err.diagnostic().unwrap_or_else(|| format!("error: {}\n", err));
+            eprint!("{}", diagnostic);
+            LiveReloadEvent::CompileError(diagnostic)
+        }
+        Err(_) => return,
+    };
+
+    LIVE_RELOAD.notify(app_name, &event);
+}
+
+fn snapshot_apps(app_dir: &Path) -> io::Result<HashMap<String, AppSnapshot>> {
+    let mut apps: HashMap<String, AppSnapshot> = Default::default();
+
+    for entry in fs::read_dir(app_dir)? {
+        let path = entry?.path();
+        if !path.is_dir() || is_hidden(&path) {
+            continue;
+        };
+
+        let app_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let mut snapshot: AppSnapshot = Default::default();
+        snapshot_dir(&path, &mut snapshot)?;
+        apps.insert(app_name, snapshot);
+    }
+
+    Ok(apps)
+}
+
+fn snapshot_dir(dir: &Path, snapshot: &mut AppSnapshot) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_hidden(&path) {
+            continue;
+        };
+
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_dir() {
+            snapshot_dir(&path, snapshot)?;
+        } else {
+            snapshot.insert(path, (metadata.modified()?, metadata.len()));
+        };
+    }
+
+    Ok(())
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().starts_with('.'))
+        .unwrap_or(false)
+}
@@ -16,7 +16,7 @@ function prerender() {
     local -r base_url="${APPS_BASE}/${APP_NAME}/"
 
     log "Building ${APP_NAME} prerender with base_url (${base_url})..."
-    APP_DIR=./res/tests/app ${CLI_BIN} --app-name ${APP_NAME} --base-url ${base_url} --template-path /app.ism -o ${out} 2>/dev/null
+    ${CLI_BIN} render ./res/tests/app/${APP_NAME}/app.ism / --base-url ${base_url} -o ${out} 2>/dev/null
 }
 
 pushd ${ROOT}/isymtope-server